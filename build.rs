@@ -0,0 +1,12 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(mock_ndk)");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // There's no libmediandk to link against outside Android, so fall back to the mock there
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if env::var_os("CARGO_FEATURE_MOCK_NDK").is_some() || target_os != "android" {
+        println!("cargo:rustc-cfg=mock_ndk");
+    }
+}
@@ -1,5 +1,5 @@
 use log::debug;
-use mediacodec::{Frame, MediaExtractor, SampleFormat, VideoFrame};
+use mediacodec::MediaExtractor;
 
 #[no_mangle]
 extern "C" fn process() {
@@ -9,9 +9,9 @@ extern "C" fn process() {
 
     for i in 0..extractor.track_count() {
         let format = extractor.track_format(i).unwrap();
-        debug!("{}", format.to_string());
-        let mime_type = format.get_string("mime").unwrap();
-        extractor.select_track(i);
+        debug!("{format}");
+        let _mime_type = format.get_string("mime").unwrap();
+        extractor.select_track(i).unwrap();
     }
 
     while extractor.has_next() {
@@ -0,0 +1,412 @@
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    codec::error, AMediaCodec_flush, BufferInfo, CodecInputBuffer, CodecOutputBuffer, CodecState,
+    Error, MediaCodec, MediaStatus,
+};
+
+/// The buffers a codec made available, waiting to be picked up by a future
+#[derive(Debug, Default)]
+struct Buffers {
+    inputs: VecDeque<usize>,
+    outputs: VecDeque<(usize, BufferInfo)>,
+    error: Option<MediaStatus>,
+    input_waker: Option<Waker>,
+    output_waker: Option<Waker>,
+}
+
+impl Buffers {
+    fn push_input(&mut self, index: usize) {
+        self.inputs.push_back(index);
+        if let Some(waker) = self.input_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn push_output(&mut self, index: usize, info: BufferInfo) {
+        self.outputs.push_back((index, info));
+        if let Some(waker) = self.output_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Fails every future waiting on the codec, until the codec gets flushed or stopped
+    fn fail(&mut self, error: MediaStatus) {
+        self.error = Some(error);
+        for waker in [self.input_waker.take(), self.output_waker.take()]
+            .into_iter()
+            .flatten()
+        {
+            waker.wake();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.inputs.clear();
+        self.outputs.clear();
+        self.error = None;
+    }
+}
+
+fn lock(buffers: &Mutex<Buffers>) -> MutexGuard<'_, Buffers> {
+    buffers.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Collects the buffers of a codec for [next_input](MediaCodec::next_input) and [next_output](MediaCodec::next_output)
+#[derive(Debug)]
+pub(crate) struct AsyncDriver {
+    buffers: Arc<Mutex<Buffers>>,
+    #[cfg(not(feature = "api28"))]
+    poller: Option<poller::Poller>,
+}
+
+impl AsyncDriver {
+    /// Runs `f`, which invalidates every buffer the codec handed out so far
+    pub(crate) fn run_reset(&self, f: impl FnOnce() -> MediaStatus) -> Result<(), MediaStatus> {
+        // Holding the lock keeps buffers from coming in while the codec resets
+        let mut buffers = lock(&self.buffers);
+        buffers.clear();
+
+        f().result().map(|_| ())
+    }
+
+    pub(crate) fn flush(&self, codec: *mut crate::AMediaCodec) -> Result<(), MediaStatus> {
+        self.run_reset(|| unsafe {
+            let status = AMediaCodec_flush(codec);
+
+            // A flushed codec in asynchronous mode only resumes once it's started again
+            #[cfg(feature = "api28")]
+            if status.is_ok() {
+                return crate::AMediaCodec_start(codec);
+            }
+
+            status
+        })
+    }
+
+    /// Starts collecting buffers, once the codec is started
+    pub(crate) fn resume(&mut self, codec: *mut crate::AMediaCodec) {
+        #[cfg(not(feature = "api28"))]
+        if self.poller.is_none() {
+            self.poller = Some(poller::Poller::spawn(codec, self.buffers.clone()));
+        }
+        #[cfg(feature = "api28")]
+        let _ = codec;
+    }
+
+    /// Stops collecting buffers. This must be done before the codec is stopped or deleted
+    pub(crate) fn shutdown(&mut self) {
+        #[cfg(not(feature = "api28"))]
+        if let Some(poller) = self.poller.take() {
+            poller.stop();
+        }
+    }
+
+    fn poll_input(&self, cx: &mut Context<'_>) -> Poll<Result<usize, MediaStatus>> {
+        let mut buffers = lock(&self.buffers);
+
+        if let Some(index) = buffers.inputs.pop_front() {
+            return Poll::Ready(Ok(index));
+        }
+
+        if let Some(error) = buffers.error {
+            return Poll::Ready(Err(error));
+        }
+
+        buffers.input_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn poll_output(&self, cx: &mut Context<'_>) -> Poll<Result<(usize, BufferInfo), MediaStatus>> {
+        let mut buffers = lock(&self.buffers);
+
+        if let Some(output) = buffers.outputs.pop_front() {
+            return Poll::Ready(Ok(output));
+        }
+
+        if let Some(error) = buffers.error {
+            return Poll::Ready(Err(error));
+        }
+
+        buffers.output_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// On API 28+, the codec reports its buffers through the asynchronous notify callback
+#[cfg(feature = "api28")]
+mod notify {
+    use std::{
+        ffi::c_void,
+        os::raw::c_char,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{lock, AsyncDriver, Buffers};
+    use crate::{
+        _AMediaCodecOnAsyncNotifyCallback, AMediaCodec, AMediaCodec_setAsyncNotifyCallback,
+        AMediaFormat, BufferInfo, MediaCodec, MediaFormat, MediaStatus,
+    };
+
+    unsafe fn buffers<'a>(userdata: *const c_void) -> &'a Mutex<Buffers> {
+        &*(userdata as *const Mutex<Buffers>)
+    }
+
+    extern "C" fn on_input_available(
+        _codec: *const AMediaCodec,
+        userdata: *const c_void,
+        index: i32,
+    ) {
+        unsafe { lock(buffers(userdata)).push_input(index as usize) }
+    }
+
+    extern "C" fn on_output_available(
+        _codec: *const AMediaCodec,
+        userdata: *const c_void,
+        index: i32,
+        info: *const BufferInfo,
+    ) {
+        unsafe { lock(buffers(userdata)).push_output(index as usize, *info) }
+    }
+
+    extern "C" fn on_format_changed(
+        _codec: *const AMediaCodec,
+        _userdata: *const c_void,
+        format: *const AMediaFormat,
+    ) {
+        // Output buffers carry the current format, so we only have to free this one
+        if !format.is_null() {
+            drop(MediaFormat::from_raw(format as *mut AMediaFormat));
+        }
+    }
+
+    extern "C" fn on_error(
+        _codec: *const AMediaCodec,
+        userdata: *const c_void,
+        error: i32,
+        _action_code: i32,
+        _detail: *const c_char,
+    ) {
+        let error = MediaStatus::try_from(error as isize).unwrap_or(MediaStatus::ErrorUnknown);
+        unsafe { lock(buffers(userdata)).fail(error) }
+    }
+
+    pub(super) fn start(codec: &mut MediaCodec<'_>) -> Result<AsyncDriver, MediaStatus> {
+        let buffers: Arc<Mutex<Buffers>> = Arc::default();
+
+        let callbacks = _AMediaCodecOnAsyncNotifyCallback {
+            on_async_input_available: on_input_available,
+            on_async_output_available: on_output_available,
+            on_async_format_changed: on_format_changed,
+            on_async_error: on_error,
+        };
+
+        unsafe {
+            AMediaCodec_setAsyncNotifyCallback(
+                codec.inner,
+                callbacks,
+                Arc::as_ptr(&buffers) as *mut c_void,
+            )
+            .result()?;
+        }
+
+        // This replaced any callback set before
+        codec.callback = None;
+
+        Ok(AsyncDriver { buffers })
+    }
+}
+
+/// Before API 28, a background thread polls the codec for buffers while it's started
+#[cfg(not(feature = "api28"))]
+mod poller {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread::JoinHandle,
+        time::Duration,
+    };
+
+    use super::{lock, AsyncDriver, Buffers};
+    use crate::{
+        AMediaCodec, AMediaCodec_dequeueInputBuffer, AMediaCodec_dequeueOutputBuffer, BufferInfo,
+        CodecState, MediaCodec, MediaStatus,
+    };
+
+    /// How long each dequeue call waits for a buffer
+    const DEQUEUE_TIMEOUT_US: i64 = 1000;
+
+    /// How long to wait before polling again, when the codec had nothing to hand out
+    const IDLE_INTERVAL: Duration = Duration::from_millis(1);
+
+    #[derive(Debug)]
+    pub(super) struct Poller {
+        stop: Arc<AtomicBool>,
+        thread: JoinHandle<()>,
+    }
+
+    impl Poller {
+        pub(super) fn spawn(codec: *mut AMediaCodec, buffers: Arc<Mutex<Buffers>>) -> Self {
+            let stop = Arc::new(AtomicBool::new(false));
+
+            let thread = {
+                let codec = codec as usize;
+                let stop = stop.clone();
+
+                std::thread::spawn(move || poll(codec, buffers, stop))
+            };
+
+            Self { stop, thread }
+        }
+
+        pub(super) fn stop(self) {
+            self.stop.store(true, Ordering::Release);
+            let _ = self.thread.join();
+        }
+    }
+
+    /// Records the errors that the futures should see.
+    /// Not being started yet is not one of them, the codec will get there
+    fn check(buffers: &mut Buffers, value: isize) {
+        match MediaStatus::try_from(value) {
+            Ok(MediaStatus::ErrorInvalidOperation) | Err(_) => {}
+            Ok(status) if status.is_err() => buffers.fail(status),
+            Ok(_) => {}
+        }
+    }
+
+    fn poll(codec: usize, buffers: Arc<Mutex<Buffers>>, stop: Arc<AtomicBool>) {
+        let codec = codec as *mut AMediaCodec;
+
+        while !stop.load(Ordering::Acquire) {
+            let found = {
+                // Holding the lock keeps a flush from happening between dequeueing a buffer and recording it
+                let mut buffers = lock(&buffers);
+
+                let input = unsafe { AMediaCodec_dequeueInputBuffer(codec, DEQUEUE_TIMEOUT_US) };
+                if input >= 0 {
+                    buffers.push_input(input as usize);
+                } else {
+                    check(&mut buffers, input);
+                }
+
+                let mut info = BufferInfo::default();
+                let output = unsafe {
+                    AMediaCodec_dequeueOutputBuffer(codec, &mut info, DEQUEUE_TIMEOUT_US)
+                };
+                if output >= 0 {
+                    buffers.push_output(output as usize, info);
+                } else {
+                    check(&mut buffers, output);
+                }
+
+                input >= 0 || output >= 0
+            };
+
+            if !found {
+                std::thread::sleep(IDLE_INTERVAL);
+            }
+        }
+    }
+
+    pub(super) fn start(codec: &mut MediaCodec<'_>) -> Result<AsyncDriver, MediaStatus> {
+        let mut driver = AsyncDriver {
+            buffers: Arc::default(),
+            poller: None,
+        };
+
+        // Otherwise, polling starts along with the codec
+        if matches!(
+            codec.state.get(),
+            CodecState::Executing | CodecState::EndOfStream
+        ) {
+            driver.resume(codec.inner);
+        }
+
+        Ok(driver)
+    }
+}
+
+impl MediaCodec<'_> {
+    /// Makes the codec buffers available through [next_input](MediaCodec::next_input) and
+    /// [next_output](MediaCodec::next_output).
+    ///
+    /// With the `api28` feature, this switches the codec to asynchronous mode, so it must be called before the codec is
+    /// initialized. Otherwise, a background thread polls the codec for buffers, from when it's started until it's
+    /// stopped
+    pub fn enable_async(&mut self) -> Result<(), Error> {
+        if self.driver.is_some() {
+            return Ok(());
+        }
+
+        #[cfg(feature = "api28")]
+        self.state
+            .expect("enable async", &[CodecState::Uninitialized])?;
+
+        #[cfg(feature = "api28")]
+        let driver = notify::start(self);
+        #[cfg(not(feature = "api28"))]
+        let driver = poller::start(self);
+        let driver = driver.map_err(error("enable async"))?;
+
+        self.driver = Some(driver);
+        Ok(())
+    }
+
+    /// Waits for an input buffer.
+    ///
+    /// Fails if [enable_async](MediaCodec::enable_async) was not called, or when the codec runs into an error
+    pub async fn next_input(&mut self) -> Result<CodecInputBuffer<'_>, Error> {
+        let error = error("dequeue input buffer");
+
+        let driver = self
+            .driver
+            .as_ref()
+            .ok_or(error(MediaStatus::ErrorInvalidOperation))?;
+        if self.state.get() == CodecState::EndOfStream {
+            return Err(
+                error(MediaStatus::ErrorEndOfStream).with_codec_state(CodecState::EndOfStream)
+            );
+        }
+        self.state
+            .expect("dequeue input buffer", &[CodecState::Executing])?;
+
+        let index = self
+            .state
+            .check(poll_fn(|cx| driver.poll_input(cx)).await)
+            .map_err(&error)?;
+
+        unsafe { CodecInputBuffer::from_index(self.inner, self.state.clone(), index) }
+            .map_err(error)
+    }
+
+    /// Waits for an output buffer.
+    ///
+    /// Fails if [enable_async](MediaCodec::enable_async) was not called, or when the codec runs into an error
+    pub async fn next_output(&mut self) -> Result<CodecOutputBuffer<'_>, Error> {
+        let error = error("dequeue output buffer");
+
+        let driver = self
+            .driver
+            .as_ref()
+            .ok_or(error(MediaStatus::ErrorInvalidOperation))?;
+        self.state.expect(
+            "dequeue output buffer",
+            &[CodecState::Executing, CodecState::EndOfStream],
+        )?;
+
+        let (index, info) = self
+            .state
+            .check(poll_fn(|cx| driver.poll_output(cx)).await)
+            .map_err(&error)?;
+
+        self.output_buffer_at(index, info)
+    }
+}
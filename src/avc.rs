@@ -0,0 +1,480 @@
+use crate::{
+    format_csd, remove_emulation_prevention, split_nal_units, BitReader, ChromaFormat, Error,
+    FrameCrop, MediaFormat, MediaStatus, Rect, Timing, VuiParameters,
+};
+
+const NAL_SPS: u8 = 7;
+const NAL_PPS: u8 = 8;
+
+/// Returns the RBSP of `nal` if it's a NAL unit of type `nal_type`, without its header
+fn nal_payload(nal: &[u8], nal_type: u8, operation: &'static str) -> Result<Vec<u8>, Error> {
+    match nal.first() {
+        Some(header) if header & 0x1F == nal_type => Ok(remove_emulation_prevention(&nal[1..])),
+        _ => Err(Error::new(MediaStatus::ErrorMalformed, operation)
+            .with_detail(&format!("expected a NAL unit of type {nal_type}"))),
+    }
+}
+
+/// An H.264 sequence parameter set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcSps {
+    pub profile_idc: u8,
+    /// The `constraint_set0_flag` to `constraint_set5_flag`, from the most significant bit
+    pub constraint_flags: u8,
+    pub level_idc: u8,
+    pub id: u32,
+    pub chroma_format: ChromaFormat,
+    pub separate_colour_plane: bool,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub max_num_ref_frames: u32,
+    /// False for interlaced content, coded as fields or MBAFF frames
+    pub frame_mbs_only: bool,
+    /// The size of the decoded frames, a multiple of the macroblock size
+    pub coded_width: u32,
+    pub coded_height: u32,
+    pub crop: FrameCrop,
+    pub vui: Option<VuiParameters>,
+}
+
+impl AvcSps {
+    /// Parses a NAL unit holding an SPS, header included but without a start code
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let data = nal_payload(nal, NAL_SPS, "parse SPS")?;
+        let mut reader = BitReader::new(&data, "parse SPS");
+
+        let profile_idc = reader.read_bits(8)? as u8;
+        let constraint_flags = reader.read_bits(8)? as u8;
+        let level_idc = reader.read_bits(8)? as u8;
+        let id = reader.read_ue()?;
+        if id > 31 {
+            return Err(reader.error("invalid SPS id"));
+        }
+
+        let mut chroma_format = ChromaFormat::Yuv420;
+        let mut separate_colour_plane = false;
+        let mut bit_depth_luma = 8;
+        let mut bit_depth_chroma = 8;
+        if matches!(
+            profile_idc,
+            100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+        ) {
+            chroma_format = ChromaFormat::from_idc(reader.read_ue()?)
+                .ok_or_else(|| reader.error("invalid chroma format"))?;
+            if chroma_format == ChromaFormat::Yuv444 {
+                separate_colour_plane = reader.read_bit()?;
+            }
+
+            let luma = reader.read_ue()?;
+            let chroma = reader.read_ue()?;
+            if luma > 6 || chroma > 6 {
+                return Err(reader.error("invalid bit depth"));
+            }
+            bit_depth_luma = luma as u8 + 8;
+            bit_depth_chroma = chroma as u8 + 8;
+
+            // qpprime_y_zero_transform_bypass_flag
+            reader.skip_bits(1)?;
+            if reader.read_bit()? {
+                let lists = if chroma_format == ChromaFormat::Yuv444 {
+                    12
+                } else {
+                    8
+                };
+                for index in 0..lists {
+                    if reader.read_bit()? {
+                        skip_scaling_list(&mut reader, if index < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+
+        // log2_max_frame_num_minus4
+        reader.read_ue()?;
+        match reader.read_ue()? {
+            0 => {
+                // log2_max_pic_order_cnt_lsb_minus4
+                reader.read_ue()?;
+            }
+            1 => {
+                // delta_pic_order_always_zero_flag, offset_for_non_ref_pic, offset_for_top_to_bottom_field
+                reader.skip_bits(1)?;
+                reader.read_se()?;
+                reader.read_se()?;
+                for _ in 0..reader.read_ue()? {
+                    reader.read_se()?;
+                }
+            }
+            2 => {}
+            _ => return Err(reader.error("invalid picture order count type")),
+        }
+
+        let max_num_ref_frames = reader.read_ue()?;
+        // gaps_in_frame_num_value_allowed_flag
+        reader.skip_bits(1)?;
+
+        let width_in_mbs = reader.read_ue()? as u64 + 1;
+        let height_in_map_units = reader.read_ue()? as u64 + 1;
+        let frame_mbs_only = reader.read_bit()?;
+        if !frame_mbs_only {
+            // mb_adaptive_frame_field_flag
+            reader.skip_bits(1)?;
+        }
+        // direct_8x8_inference_flag
+        reader.skip_bits(1)?;
+
+        let field_factor = if frame_mbs_only { 1 } else { 2 };
+        let coded_width =
+            u32::try_from(width_in_mbs * 16).map_err(|_| reader.error("invalid frame size"))?;
+        let coded_height = u32::try_from(height_in_map_units * 16 * field_factor)
+            .map_err(|_| reader.error("invalid frame size"))?;
+
+        let mut crop = FrameCrop::default();
+        if reader.read_bit()? {
+            let (unit_x, unit_y) = if separate_colour_plane {
+                (1, 1)
+            } else {
+                chroma_format.subsampling()
+            };
+            let unit_y = unit_y * field_factor as u32;
+
+            crop = FrameCrop {
+                left: reader.read_ue()?.saturating_mul(unit_x),
+                right: reader.read_ue()?.saturating_mul(unit_x),
+                top: reader.read_ue()?.saturating_mul(unit_y),
+                bottom: reader.read_ue()?.saturating_mul(unit_y),
+            };
+            if crop.left as u64 + crop.right as u64 >= coded_width as u64
+                || crop.top as u64 + crop.bottom as u64 >= coded_height as u64
+            {
+                return Err(reader.error("cropping is larger than the frame"));
+            }
+        }
+
+        let vui = if reader.read_bit()? {
+            let mut vui = VuiParameters::parse_common(&mut reader)?;
+            if reader.read_bit()? {
+                vui.timing = Some(Timing {
+                    num_units_in_tick: reader.read_bits(32)?,
+                    time_scale: reader.read_bits(32)?,
+                });
+            }
+            Some(vui)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            profile_idc,
+            constraint_flags,
+            level_idc,
+            id,
+            chroma_format,
+            separate_colour_plane,
+            bit_depth_luma,
+            bit_depth_chroma,
+            max_num_ref_frames,
+            frame_mbs_only,
+            coded_width,
+            coded_height,
+            crop,
+            vui,
+        })
+    }
+
+    /// The width of the displayed frames, once cropped
+    pub fn width(&self) -> u32 {
+        self.coded_width - self.crop.left - self.crop.right
+    }
+
+    /// The height of the displayed frames, once cropped
+    pub fn height(&self) -> u32 {
+        self.coded_height - self.crop.top - self.crop.bottom
+    }
+
+    /// The displayed part of the frames, like the `crop` format key
+    pub fn display_rect(&self) -> Rect {
+        self.crop.display_rect(self.coded_width, self.coded_height)
+    }
+
+    /// The frame rate, from the VUI timing. H.264 ticks once per field, so this assumes two fields per frame
+    pub fn frame_rate(&self) -> Option<f64> {
+        let timing = self.vui?.timing?;
+        (timing.num_units_in_tick != 0)
+            .then(|| timing.time_scale as f64 / (2.0 * timing.num_units_in_tick as f64))
+    }
+
+    fn constraint_set(&self, index: u8) -> bool {
+        self.constraint_flags & (0x80 >> index) != 0
+    }
+
+    /// The profile as a `MediaCodecInfo.CodecProfileLevel` value (like `AVCProfileHigh`), for the `profile` format key
+    pub fn codec_profile(&self) -> Option<i32> {
+        match self.profile_idc {
+            66 if self.constraint_set(1) => Some(0x10000),
+            66 => Some(0x01),
+            77 => Some(0x02),
+            88 => Some(0x04),
+            100 if self.constraint_set(4) && self.constraint_set(5) => Some(0x80000),
+            100 => Some(0x08),
+            110 => Some(0x10),
+            122 => Some(0x20),
+            244 => Some(0x40),
+            _ => None,
+        }
+    }
+
+    /// The level as a `MediaCodecInfo.CodecProfileLevel` value (like `AVCLevel41`), for the `level` format key
+    pub fn codec_level(&self) -> Option<i32> {
+        let level = match self.level_idc {
+            9 => 0x02,
+            10 => 0x01,
+            // Level 1b, in the Baseline, Main and Extended profiles
+            11 if self.constraint_set(3) && matches!(self.profile_idc, 66 | 77 | 88) => 0x02,
+            11 => 0x04,
+            12 => 0x08,
+            13 => 0x10,
+            20 => 0x20,
+            21 => 0x40,
+            22 => 0x80,
+            30 => 0x100,
+            31 => 0x200,
+            32 => 0x400,
+            40 => 0x800,
+            41 => 0x1000,
+            42 => 0x2000,
+            50 => 0x4000,
+            51 => 0x8000,
+            52 => 0x10000,
+            60 => 0x20000,
+            61 => 0x40000,
+            62 => 0x80000,
+            _ => return None,
+        };
+        Some(level)
+    }
+}
+
+fn skip_scaling_list(reader: &mut BitReader, size: usize) -> Result<(), Error> {
+    let mut last = 8;
+    let mut next = 8;
+    for _ in 0..size {
+        if next != 0 {
+            next = (last + reader.read_se()? + 256) % 256;
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+
+    Ok(())
+}
+
+/// An H.264 picture parameter set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcPps {
+    pub id: u32,
+    pub sps_id: u32,
+    /// CABAC when set, CAVLC otherwise
+    pub entropy_coding_mode: bool,
+    pub bottom_field_pic_order_in_frame_present: bool,
+    pub num_slice_groups: u32,
+    pub num_ref_idx_l0_default_active: u32,
+    pub num_ref_idx_l1_default_active: u32,
+    pub weighted_pred: bool,
+    pub weighted_bipred_idc: u8,
+    pub pic_init_qp: i32,
+    pub pic_init_qs: i32,
+    pub chroma_qp_index_offset: i32,
+    pub deblocking_filter_control_present: bool,
+    pub constrained_intra_pred: bool,
+    pub redundant_pic_cnt_present: bool,
+    pub transform_8x8_mode: bool,
+    pub pic_scaling_matrix_present: bool,
+    /// Only known when there's no scaling matrix, since reading past it needs the chroma format of the SPS
+    pub second_chroma_qp_index_offset: Option<i32>,
+}
+
+impl AvcPps {
+    /// Parses a NAL unit holding a PPS, header included but without a start code
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let data = nal_payload(nal, NAL_PPS, "parse PPS")?;
+        let mut reader = BitReader::new(&data, "parse PPS");
+
+        let id = reader.read_ue()?;
+        let sps_id = reader.read_ue()?;
+        if id > 255 || sps_id > 31 {
+            return Err(reader.error("invalid parameter set id"));
+        }
+        let entropy_coding_mode = reader.read_bit()?;
+        let bottom_field_pic_order_in_frame_present = reader.read_bit()?;
+
+        let num_slice_groups = reader.read_ue()?.saturating_add(1);
+        if num_slice_groups > 8 {
+            return Err(reader.error("too many slice groups"));
+        }
+        if num_slice_groups > 1 {
+            skip_slice_groups(&mut reader, num_slice_groups)?;
+        }
+
+        let num_ref_idx_l0_default_active = reader.read_ue()?.saturating_add(1);
+        let num_ref_idx_l1_default_active = reader.read_ue()?.saturating_add(1);
+        let weighted_pred = reader.read_bit()?;
+        let weighted_bipred_idc = reader.read_bits(2)? as u8;
+        let pic_init_qp = 26 + reader.read_se()?;
+        let pic_init_qs = 26 + reader.read_se()?;
+        let chroma_qp_index_offset = reader.read_se()?;
+        let deblocking_filter_control_present = reader.read_bit()?;
+        let constrained_intra_pred = reader.read_bit()?;
+        let redundant_pic_cnt_present = reader.read_bit()?;
+
+        let mut transform_8x8_mode = false;
+        let mut pic_scaling_matrix_present = false;
+        let mut second_chroma_qp_index_offset = Some(chroma_qp_index_offset);
+        if reader.more_rbsp_data() {
+            transform_8x8_mode = reader.read_bit()?;
+            pic_scaling_matrix_present = reader.read_bit()?;
+            second_chroma_qp_index_offset = if pic_scaling_matrix_present {
+                None
+            } else {
+                Some(reader.read_se()?)
+            };
+        }
+
+        Ok(Self {
+            id,
+            sps_id,
+            entropy_coding_mode,
+            bottom_field_pic_order_in_frame_present,
+            num_slice_groups,
+            num_ref_idx_l0_default_active,
+            num_ref_idx_l1_default_active,
+            weighted_pred,
+            weighted_bipred_idc,
+            pic_init_qp,
+            pic_init_qs,
+            chroma_qp_index_offset,
+            deblocking_filter_control_present,
+            constrained_intra_pred,
+            redundant_pic_cnt_present,
+            transform_8x8_mode,
+            pic_scaling_matrix_present,
+            second_chroma_qp_index_offset,
+        })
+    }
+}
+
+fn skip_slice_groups(reader: &mut BitReader, num_slice_groups: u32) -> Result<(), Error> {
+    match reader.read_ue()? {
+        0 => {
+            for _ in 0..num_slice_groups {
+                reader.read_ue()?;
+            }
+        }
+        2 => {
+            for _ in 1..num_slice_groups {
+                reader.read_ue()?;
+                reader.read_ue()?;
+            }
+        }
+        3..=5 => {
+            reader.skip_bits(1)?;
+            reader.read_ue()?;
+        }
+        6 => {
+            let bits = u32::BITS - (num_slice_groups - 1).leading_zeros();
+            for _ in 0..=reader.read_ue()? {
+                reader.read_bits(bits)?;
+            }
+        }
+        1 => {}
+        _ => return Err(reader.error("invalid slice group map type")),
+    }
+
+    Ok(())
+}
+
+/// The parameter sets of an H.264 stream, as found in the codec specific data of its track
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AvcParameterSets {
+    pub sps: Vec<AvcSps>,
+    pub pps: Vec<AvcPps>,
+}
+
+impl AvcParameterSets {
+    /// Parses a buffer of parameter sets, either in Annex B format (like `csd-0` and `csd-1`) or as an `avcC` box.
+    ///
+    /// NAL units other than SPS and PPS are skipped
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut sets = Self::default();
+        sets.add(data)?;
+        Ok(sets)
+    }
+
+    /// Parses the `csd-0` and `csd-1` buffers of an H.264 track format
+    pub fn from_format(format: &MediaFormat) -> Result<Self, Error> {
+        let mut sets = Self::default();
+        for buffer in format_csd(format, "parse avc csd")? {
+            sets.add(buffer)?;
+        }
+
+        if sets.sps.is_empty() {
+            return Err(
+                Error::new(MediaStatus::ErrorMalformed, "parse avc csd").with_detail("missing SPS")
+            );
+        }
+        Ok(sets)
+    }
+
+    /// The first SPS, which describes the stream unless it changes resolution
+    pub fn first_sps(&self) -> Option<&AvcSps> {
+        self.sps.first()
+    }
+
+    fn add(&mut self, data: &[u8]) -> Result<(), Error> {
+        let nals = if data.first() == Some(&1) {
+            parse_avcc(data)?
+        } else {
+            split_nal_units(data)
+        };
+
+        for nal in nals {
+            match nal[0] & 0x1F {
+                NAL_SPS => self.sps.push(AvcSps::parse(nal)?),
+                NAL_PPS => self.pps.push(AvcPps::parse(nal)?),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the NAL units of an `AVCDecoderConfigurationRecord`
+fn parse_avcc(data: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let error = |detail| Error::new(MediaStatus::ErrorMalformed, "parse avcC").with_detail(detail);
+    let mut rest = data.get(5..).ok_or_else(|| error("truncated header"))?;
+    let mut nals = vec![];
+
+    for mask in [0x1F, 0xFF] {
+        let (&count, tail) = rest
+            .split_first()
+            .ok_or_else(|| error("truncated header"))?;
+        rest = tail;
+
+        for _ in 0..count & mask {
+            let [high, low, ref tail @ ..] = *rest else {
+                return Err(error("truncated NAL unit"));
+            };
+            let len = u16::from_be_bytes([high, low]) as usize;
+            if len == 0 || tail.len() < len {
+                return Err(error("truncated NAL unit"));
+            }
+
+            nals.push(&tail[..len]);
+            rest = &tail[len..];
+        }
+    }
+
+    Ok(nals)
+}
@@ -0,0 +1,187 @@
+use crate::{
+    BufferInfo, CodecInputBuffer, CodecOutputBuffer, CodecState, Error, Frame, MediaCodec,
+    MediaFormat, NativeWindow,
+};
+
+/// A buffer handed out by a [Codec](Codec) to be filled with input data.
+///
+/// The buffer is queued back to the codec when it gets dropped
+pub trait InputBuffer {
+    /// The size (in bytes) of this buffer
+    fn size(&self) -> usize;
+
+    /// The presentation time of this buffer
+    fn time(&self) -> u64;
+
+    /// The size of data written into this buffer
+    fn write_size(&self) -> usize;
+
+    /// The buffer itself, as a pointer and the number of bytes that can be written to it
+    fn buffer(&self) -> (*mut u8, usize);
+
+    /// Set the presentation time of this buffer
+    fn set_time(&mut self, time: u64);
+
+    /// Set this buffer's flags
+    fn set_flags(&mut self, flags: u32);
+
+    /// Set the size of bytes written to this buffer
+    fn set_write_size(&mut self, write_size: usize);
+}
+
+/// A buffer of processed data handed out by a [Codec](Codec).
+///
+/// The buffer is released back to the codec when it gets dropped
+pub trait OutputBuffer {
+    /// Returns the buffer information
+    fn info(&self) -> &BufferInfo;
+
+    /// The data contained in this buffer, if the codec is not rendering to a surface
+    fn data(&self) -> Option<&[u8]>;
+
+    /// Returns the frame contained in this buffer
+    fn frame(&self) -> Option<Frame<'_>>;
+
+    /// Set whether this buffer should render when it gets dropped
+    fn set_render(&mut self, render: bool);
+}
+
+/// The operations shared by every codec implementation.
+///
+/// `MediaCodec` implements this on top of the NDK, and [PassthroughCodec](crate::PassthroughCodec) is a pure Rust
+/// implementation that works anywhere. Code that's generic over this trait can be tested without a device.
+pub trait Codec {
+    type InputBuffer<'a>: InputBuffer
+    where
+        Self: 'a;
+
+    type OutputBuffer<'a>: OutputBuffer
+    where
+        Self: 'a;
+
+    /// Configures the codec. This must be called before the codec can be started
+    fn configure(
+        &mut self,
+        format: &MediaFormat,
+        surface: Option<NativeWindow>,
+        flags: u32,
+    ) -> Result<(), Error>;
+
+    /// Starts the codec for processing
+    fn start(&mut self) -> Result<(), Error>;
+
+    /// Stops the codec. It has to be configured again before it can be restarted
+    fn stop(&mut self) -> Result<(), Error>;
+
+    /// Discards every pending input and output buffer
+    fn flush(&mut self) -> Result<(), Error>;
+
+    /// Get an input buffer from the codec
+    fn dequeue_input(&mut self) -> Result<Self::InputBuffer<'_>, Error>;
+
+    /// Get an output buffer from the codec
+    fn dequeue_output(&mut self) -> Result<Self::OutputBuffer<'_>, Error>;
+
+    /// Returns the output format of this codec
+    fn output_format(&self) -> Option<MediaFormat>;
+
+    /// Returns the current state of the codec
+    fn state(&self) -> CodecState;
+}
+
+impl InputBuffer for CodecInputBuffer<'_> {
+    fn size(&self) -> usize {
+        self.size()
+    }
+
+    fn time(&self) -> u64 {
+        self.time()
+    }
+
+    fn write_size(&self) -> usize {
+        self.write_size()
+    }
+
+    fn buffer(&self) -> (*mut u8, usize) {
+        self.buffer()
+    }
+
+    fn set_time(&mut self, time: u64) {
+        self.set_time(time);
+    }
+
+    fn set_flags(&mut self, flags: u32) {
+        self.set_flags(flags);
+    }
+
+    fn set_write_size(&mut self, write_size: usize) {
+        self.set_write_size(write_size);
+    }
+}
+
+impl OutputBuffer for CodecOutputBuffer<'_> {
+    fn info(&self) -> &BufferInfo {
+        self.info()
+    }
+
+    fn data(&self) -> Option<&[u8]> {
+        self.data()
+    }
+
+    fn frame(&self) -> Option<Frame<'_>> {
+        self.frame()
+    }
+
+    fn set_render(&mut self, render: bool) {
+        self.set_render(render);
+    }
+}
+
+impl Codec for MediaCodec<'_> {
+    type InputBuffer<'a>
+        = CodecInputBuffer<'a>
+    where
+        Self: 'a;
+
+    type OutputBuffer<'a>
+        = CodecOutputBuffer<'a>
+    where
+        Self: 'a;
+
+    fn configure(
+        &mut self,
+        format: &MediaFormat,
+        surface: Option<NativeWindow>,
+        flags: u32,
+    ) -> Result<(), Error> {
+        self.init(format, surface, flags)
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        self.start()
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        self.stop()
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.flush()
+    }
+
+    fn dequeue_input(&mut self) -> Result<Self::InputBuffer<'_>, Error> {
+        self.dequeue_input()
+    }
+
+    fn dequeue_output(&mut self) -> Result<Self::OutputBuffer<'_>, Error> {
+        self.dequeue_output()
+    }
+
+    fn output_format(&self) -> Option<MediaFormat> {
+        self.output_format()
+    }
+
+    fn state(&self) -> CodecState {
+        self.state()
+    }
+}
@@ -1,23 +1,64 @@
 use log::{debug, warn};
 
 use crate::{
-    AMediaCrypto, AMediaFormat, ANativeWindow, AudioFrame, Frame, MediaFormat, MediaStatus,
-    NativeWindow, SampleFormat, VideoFrame, ENCODING_PCM_16BIT, ENCODING_PCM_FLOAT,
+    keys, AMediaCrypto, AMediaFormat, ANativeWindow, AudioFrame, CryptoInfo, Error, Frame,
+    MediaCrypto, MediaFormat, MediaStatus, NativeWindow, RawVideoFrame, SampleFormat, VideoFrame,
+    ENCODING_PCM_16BIT, ENCODING_PCM_FLOAT,
 };
 use std::{
     ffi::{c_void, CString},
     marker::PhantomData,
     os::raw::c_char,
     ptr::{null_mut, slice_from_raw_parts},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct BufferInfo {
-    offset: i32,
-    size: i32,
-    presentation_time_us: i64,
-    flags: u32,
+    pub(crate) offset: i32,
+    pub(crate) size: i32,
+    pub(crate) presentation_time_us: i64,
+    pub(crate) flags: u32,
+}
+
+impl BufferInfo {
+    /// The start offset of the data in the buffer
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    /// The amount of data (in bytes) in the buffer
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    /// The presentation timestamp of the buffer in microseconds
+    pub fn presentation_time_us(&self) -> i64 {
+        self.presentation_time_us
+    }
+
+    /// The [BufferFlag](BufferFlag)s set on the buffer
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Whether the buffer holds codec specific data (like H.264 SPS/PPS) instead of media data
+    pub fn is_codec_config(&self) -> bool {
+        BufferFlag::CodecConfig.is_contained_in(self.flags as i32)
+    }
+
+    /// Whether the buffer is the last one of the stream
+    pub fn is_end_of_stream(&self) -> bool {
+        BufferFlag::EndOfStream.is_contained_in(self.flags as i32)
+    }
+
+    /// Whether an encoder output buffer holds a key frame
+    pub fn is_key_frame(&self) -> bool {
+        // The key frame flag shares its value with the encode flag
+        BufferFlag::Encode.is_contained_in(self.flags as i32)
+    }
 }
 
 #[repr(C)]
@@ -29,7 +70,7 @@ pub struct AMediaCodecCryptoInfo {
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
-struct AMediaCodec {
+pub(crate) struct AMediaCodec {
     _data: [u8; 0],
     _marker: core::marker::PhantomData<(*mut u8, core::marker::PhantomPinned)>,
 }
@@ -66,7 +107,7 @@ impl TryFrom<BufferFlag> for i32 {
 
 impl BufferFlag {
     pub fn is_contained_in(&self, flag: i32) -> bool {
-        return flag & (*self as i32) > 0;
+        flag & (*self as i32) > 0
     }
 
     pub fn add_to_flag(&self, flag: &mut i32) {
@@ -104,7 +145,7 @@ impl TryFrom<InfoFlag> for i32 {
 
 impl InfoFlag {
     pub fn is_contained_in(&self, flag: i32) -> bool {
-        return flag & (*self as i32) > 0;
+        flag & (*self as i32) > 0
     }
 
     pub fn add_to_flag(&self, flag: &mut i32) {
@@ -112,6 +153,67 @@ impl InfoFlag {
     }
 }
 
+/// How long a dequeue call waits for a buffer to become available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// Return right away
+    NonBlocking,
+    /// Wait until a buffer is available
+    Blocking,
+    /// Wait at most this long
+    Duration(Duration),
+}
+
+impl Timeout {
+    /// The timeout in microseconds, as the NDK expects it
+    fn as_us(&self) -> i64 {
+        match *self {
+            Self::NonBlocking => 0,
+            Self::Blocking => -1,
+            Self::Duration(duration) => duration.as_micros().min(i64::MAX as u128) as i64,
+        }
+    }
+}
+
+impl From<Duration> for Timeout {
+    fn from(duration: Duration) -> Self {
+        Self::Duration(duration)
+    }
+}
+
+/// The outcome of dequeueing a buffer from a codec
+#[derive(Debug)]
+pub enum Dequeued<T> {
+    /// A buffer is available
+    Buffer(T),
+    /// No buffer became available before the timeout
+    TryAgainLater,
+    /// The output format changed. The new format can be read with [output_format](MediaCodec::output_format)
+    OutputFormatChanged,
+    /// The output buffers changed. Buffers are fetched by index, so there's nothing to do about this
+    OutputBuffersChanged,
+}
+
+impl<T> Dequeued<T> {
+    /// Returns the buffer, if one was dequeued
+    pub fn buffer(self) -> Option<T> {
+        match self {
+            Self::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    /// Interprets a negative index returned by the NDK
+    fn from_info(index: isize) -> Result<Self, MediaStatus> {
+        match InfoFlag::try_from(index as i32) {
+            Ok(InfoFlag::TryAgainLater) => Ok(Self::TryAgainLater),
+            Ok(InfoFlag::OutputFormatChanged) => Ok(Self::OutputFormatChanged),
+            Ok(InfoFlag::OutputBuffersChanged) => Ok(Self::OutputBuffersChanged),
+            Err(_) => Err(MediaStatus::try_from(index).unwrap_or(MediaStatus::ErrorUnknown)),
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CryptoInfoMode {
@@ -128,7 +230,7 @@ pub struct CryptoInfoPattern {
     pub skip_blocks: i32,
 }
 
-type _AMediaCodecOnAsyncInputAvailable = extern "C" fn(
+pub(crate) type _AMediaCodecOnAsyncInputAvailable = extern "C" fn(
     // Codec
     *const AMediaCodec,
     // Userdata
@@ -137,7 +239,7 @@ type _AMediaCodecOnAsyncInputAvailable = extern "C" fn(
     index: i32,
 );
 
-type _AMediaCodecOnAsyncOutputAvailable = extern "C" fn(
+pub(crate) type _AMediaCodecOnAsyncOutputAvailable = extern "C" fn(
     // Codec
     *const AMediaCodec,
     // Userdata
@@ -148,7 +250,7 @@ type _AMediaCodecOnAsyncOutputAvailable = extern "C" fn(
     *const BufferInfo,
 );
 
-type _AMediaCodecOnAsyncFormatChanged = extern "C" fn(
+pub(crate) type _AMediaCodecOnAsyncFormatChanged = extern "C" fn(
     // Codec
     *const AMediaCodec,
     // Userdata
@@ -157,7 +259,7 @@ type _AMediaCodecOnAsyncFormatChanged = extern "C" fn(
     *const AMediaFormat,
 );
 
-type _AMediaCodecOnAsyncError = extern "C" fn(
+pub(crate) type _AMediaCodecOnAsyncError = extern "C" fn(
     // Codec
     *const AMediaCodec,
     // Userdata
@@ -171,16 +273,18 @@ type _AMediaCodecOnAsyncError = extern "C" fn(
 );
 
 #[repr(C)]
-struct _AMediaCodecOnAsyncNotifyCallback {
-    on_async_input_available: _AMediaCodecOnAsyncInputAvailable,
-    on_async_output_available: _AMediaCodecOnAsyncOutputAvailable,
-    on_async_format_changed: _AMediaCodecOnAsyncFormatChanged,
-    on_async_error: _AMediaCodecOnAsyncError,
+#[derive(Clone, Copy)]
+pub(crate) struct _AMediaCodecOnAsyncNotifyCallback {
+    pub(crate) on_async_input_available: _AMediaCodecOnAsyncInputAvailable,
+    pub(crate) on_async_output_available: _AMediaCodecOnAsyncOutputAvailable,
+    pub(crate) on_async_format_changed: _AMediaCodecOnAsyncFormatChanged,
+    pub(crate) on_async_error: _AMediaCodecOnAsyncError,
 }
 
 // FFI FUNCTIONS BEGIN
 
-#[link(name = "mediandk")]
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
+#[allow(dead_code)]
 extern "C" {
     /// Create codec by name. Use this if you know the exact codec you want to use.
     /// When configuring, you will need to specify whether to use the codec as an encoder or decoder.
@@ -217,7 +321,7 @@ extern "C" {
     /// Start the codec. A codec must be configured before it can be started, and must be started before buffers can be sent to it.
     /// <hr />
     /// Since: API 21
-    fn AMediaCodec_start(codec: *mut AMediaCodec) -> MediaStatus;
+    pub(crate) fn AMediaCodec_start(codec: *mut AMediaCodec) -> MediaStatus;
 
     /// Stop the codec.
     /// <hr />
@@ -227,7 +331,7 @@ extern "C" {
     /// Flush the codec's input and output. All indices previously returned from calls to `AMediaCodec_dequeueInputBuffer` and `AMediaCodec_dequeueOutpuBuffer` become invalid.
     /// <hr />
     /// Since: API 21
-    fn AMediaCodec_flush(codec: *mut AMediaCodec) -> MediaStatus;
+    pub(crate) fn AMediaCodec_flush(codec: *mut AMediaCodec) -> MediaStatus;
 
     /// Get an input buffer. The specified buffer index must have been previously obtained from dequeueInputBuffer, and not yet queued.
     /// <hr />
@@ -250,7 +354,8 @@ extern "C" {
     /// Get the index of the next available input buffer. An app will typically use this with `getInputBuffer` to get a pointer to the buffer, then copy the data to be encoded or decoded into the buffer before passing it to the codec.
     /// <hr />
     /// Since: API 21
-    fn AMediaCodec_dequeueInputBuffer(codec: *mut AMediaCodec, timeout_us: i64) -> isize;
+    pub(crate) fn AMediaCodec_dequeueInputBuffer(codec: *mut AMediaCodec, timeout_us: i64)
+        -> isize;
 
     /// Send the specified buffer to the codec for processing
     /// <hr />
@@ -279,7 +384,7 @@ extern "C" {
     /// Get the index of the next available buffer of processed data
     /// <hr />
     // Since: API 21
-    fn AMediaCodec_dequeueOutputBuffer(
+    pub(crate) fn AMediaCodec_dequeueOutputBuffer(
         codec: *mut AMediaCodec,
         info: *mut BufferInfo,
         timeout_us: i64,
@@ -416,11 +521,11 @@ extern "C" {
     /// <hr />
     /// Since: API 28
     #[cfg(feature = "api28")]
-    fn AMediaCodec_setAsyncNotifyCallback(
+    pub(crate) fn AMediaCodec_setAsyncNotifyCallback(
         codec: *mut AMediaCodec,
         callback: _AMediaCodecOnAsyncNotifyCallback,
         userdata: *mut c_void,
-    );
+    ) -> MediaStatus;
 
     /// Release the crypto if applicable.
     /// <hr />
@@ -441,17 +546,17 @@ extern "C" {
     /// <hr />
     /// Since: API 28.
     #[cfg(feature = "api28")]
-    fn AMediaCodecActionCode_isRecoverable(action_code: i32) -> bool;
+    pub(crate) fn AMediaCodecActionCode_isRecoverable(action_code: i32) -> bool;
 
     /// Returns true if the codec error is a transient issue perhaps due to resource constraints, and that the method (or encoding/decoding) may be retried at a later time.
     ///
     /// <hr />
     /// Since: API 28.
     #[cfg(feature = "api28")]
-    fn AMediaCodecActionCode_isTransient(action_code: i32) -> bool;
+    pub(crate) fn AMediaCodecActionCode_isTransient(action_code: i32) -> bool;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_new(
+    pub(crate) fn AMediaCodecCryptoInfo_new(
         num_subsamples: i32,
         key: &[u8; 16],
         iv: &[u8; 16],
@@ -461,40 +566,117 @@ extern "C" {
     ) -> *mut AMediaCodecCryptoInfo;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_delete(info: *mut AMediaCodecCryptoInfo) -> MediaStatus;
+    pub(crate) fn AMediaCodecCryptoInfo_delete(info: *mut AMediaCodecCryptoInfo) -> MediaStatus;
 
-    /// Since: API 21
-    fn AMediaCodecCryptoInfo_setPattern(
+    /// Since: API 24
+    #[cfg(feature = "api24")]
+    pub(crate) fn AMediaCodecCryptoInfo_setPattern(
         info: *mut AMediaCodecCryptoInfo,
         pattern: *mut CryptoInfoPattern,
     );
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_getNumSubSamples(info: *mut AMediaCodecCryptoInfo) -> usize;
+    pub(crate) fn AMediaCodecCryptoInfo_getNumSubSamples(info: *mut AMediaCodecCryptoInfo)
+        -> usize;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_getKey(info: *mut AMediaCodecCryptoInfo, dst: *mut u8) -> isize;
+    pub(crate) fn AMediaCodecCryptoInfo_getKey(
+        info: *mut AMediaCodecCryptoInfo,
+        dst: *mut u8,
+    ) -> isize;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_getIV(info: *mut AMediaCodecCryptoInfo, dst: *mut u8) -> isize;
+    pub(crate) fn AMediaCodecCryptoInfo_getIV(
+        info: *mut AMediaCodecCryptoInfo,
+        dst: *mut u8,
+    ) -> isize;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_getMode(info: *mut AMediaCodecCryptoInfo) -> CryptoInfoMode;
+    pub(crate) fn AMediaCodecCryptoInfo_getMode(info: *mut AMediaCodecCryptoInfo)
+        -> CryptoInfoMode;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_getClearBytes(
+    pub(crate) fn AMediaCodecCryptoInfo_getClearBytes(
         info: *mut AMediaCodecCryptoInfo,
         dst: *mut usize,
     ) -> isize;
 
     /// Since: API 21
-    fn AMediaCodecCryptoInfo_getEncryptedBytes(
+    pub(crate) fn AMediaCodecCryptoInfo_getEncryptedBytes(
         info: *mut AMediaCodecCryptoInfo,
         dst: *mut usize,
     ) -> isize;
 }
 // FFI FUNCTIONS END
 
+/// The lifecycle state of a codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecState {
+    /// Created, but not configured yet
+    Uninitialized,
+    /// Configured, and ready to be started
+    Configured,
+    /// Started and processing buffers
+    Executing,
+    /// An input buffer flagged with the end of stream was queued.
+    /// Output buffers can still be dequeued until the codec is flushed or stopped
+    EndOfStream,
+    /// Released. The codec can no longer be used
+    Released,
+    /// The codec ran into an error, and has to be stopped before it can be used again
+    Error,
+}
+
+/// Wraps the statuses returned while doing `operation`
+pub(crate) fn error(operation: &'static str) -> impl Fn(MediaStatus) -> Error {
+    move |status| Error::new(status, operation)
+}
+
+/// The state of a codec, shared with the buffers it hands out
+#[derive(Debug, Clone)]
+pub(crate) struct SharedState(Arc<Mutex<CodecState>>);
+
+impl SharedState {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(CodecState::Uninitialized)))
+    }
+
+    pub(crate) fn get(&self) -> CodecState {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn set(&self, state: CodecState) {
+        *self.0.lock().unwrap_or_else(|err| err.into_inner()) = state;
+    }
+
+    /// Fails `operation` with `ErrorInvalidOperation`, naming the current state, unless the codec is in one of `states`
+    pub(crate) fn expect(
+        &self,
+        operation: &'static str,
+        states: &[CodecState],
+    ) -> Result<(), Error> {
+        let state = self.get();
+        if !states.contains(&state) {
+            return Err(
+                Error::new(MediaStatus::ErrorInvalidOperation, operation).with_codec_state(state)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Moves the codec to the error state if `result` is an error the codec reported
+    pub(crate) fn check<T>(&self, result: Result<T, MediaStatus>) -> Result<T, MediaStatus> {
+        if let Err(status) = result {
+            if status != MediaStatus::ErrorInvalidOperation {
+                self.set(CodecState::Error);
+            }
+        }
+
+        result
+    }
+}
+
 /// This represents a buffer returned by mediacodec's input
 /// This buffer should be filled with input data depending on whether the codec is an encoder or decoder
 #[derive(Debug)]
@@ -505,25 +687,56 @@ pub struct CodecInputBuffer<'a> {
     pub(crate) write_size: usize,
     index: usize,
     codec: *mut AMediaCodec,
+    state: SharedState,
+    queued: bool,
     pub(crate) time: u64,
     pub(crate) flags: u32,
 }
 
 impl CodecInputBuffer<'_> {
     /// Creates a new Codec Input Buffer from the parameters
-    fn new(codec: *mut AMediaCodec, index: usize, buffer: *mut u8, size: usize) -> Self {
+    fn new(
+        codec: *mut AMediaCodec,
+        state: SharedState,
+        index: usize,
+        buffer: *mut u8,
+        size: usize,
+    ) -> Self {
         Self {
             _marker: PhantomData,
             buffer,
             size,
             index,
             codec,
+            state,
+            queued: false,
             write_size: 0,
             time: 0,
             flags: 0,
         }
     }
 
+    /// Wraps the input buffer at `index`.
+    ///
+    /// If the codec doesn't hand out a buffer for that index, the index is returned to the codec
+    pub(crate) unsafe fn from_index(
+        codec: *mut AMediaCodec,
+        state: SharedState,
+        index: usize,
+    ) -> Result<Self, MediaStatus> {
+        let mut out_size = 0;
+        let buffer = AMediaCodec_getInputBuffer(codec, index, &mut out_size);
+
+        if buffer.is_null() {
+            // Return the buffer to the codec, it's not valid
+            AMediaCodec_queueInputBuffer(codec, index, 0, 0, 0, 0);
+            warn!("Got an index with a null input buffer! What is going on here??? Index: {index}");
+            return Err(MediaStatus::ErrorUnknown);
+        }
+
+        Ok(Self::new(codec, state, index, buffer, out_size))
+    }
+
     /// Returns this buffer's index. There's not much you can do with this
     pub fn index(&self) -> usize {
         self.index
@@ -552,6 +765,11 @@ impl CodecInputBuffer<'_> {
         (self.buffer, self.size)
     }
 
+    /// The buffer as a mutable slice, for when copying through the pointer is not worth it
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.buffer, self.size) }
+    }
+
     /// Set the presentation time of this buffer
     pub fn set_time(&mut self, time: u64) {
         self.time = time;
@@ -566,11 +784,43 @@ impl CodecInputBuffer<'_> {
     pub fn set_write_size(&mut self, write_size: usize) {
         self.write_size = write_size;
     }
-}
 
-impl Drop for CodecInputBuffer<'_> {
-    fn drop(&mut self) {
-        unsafe {
+    /// Queues the buffer right away, instead of when it gets dropped, and returns what the codec made of it
+    pub fn queue(mut self) -> Result<(), Error> {
+        self.queued = true;
+        self.queue_input().map_err(error("queue input buffer"))
+    }
+
+    /// Queues the encrypted data in this buffer, described by `info`, to a codec configured with a crypto.
+    ///
+    /// The amount of data comes from the subsamples. If they don't fit in the buffer, the buffer is queued back empty
+    pub fn queue_secure(mut self, info: &CryptoInfo) -> Result<(), Error> {
+        let error = |status| Error::new(status, "queue secure input buffer");
+
+        if info.size() > self.size {
+            debug!("The subsamples cover more than the buffer size");
+            self.write_size = 0;
+            return Err(error(MediaStatus::ErrorInvalidParameter));
+        }
+
+        self.queued = true;
+        let result = info.with_ptr(|crypto_info| unsafe {
+            AMediaCodec_queueSecureInputBuffer(
+                self.codec,
+                self.index,
+                0,
+                crypto_info,
+                self.time,
+                self.flags,
+            )
+            .result()
+        });
+
+        self.on_queued(result.map(|_| ())).map_err(error)
+    }
+
+    fn queue_input(&self) -> Result<(), MediaStatus> {
+        let status = unsafe {
             AMediaCodec_queueInputBuffer(
                 self.codec,
                 self.index,
@@ -578,8 +828,33 @@ impl Drop for CodecInputBuffer<'_> {
                 self.write_size,
                 self.time,
                 self.flags,
-            );
+            )
+        };
+
+        self.on_queued(status.result().map(|_| ()))
+    }
+
+    /// Updates the codec state once the buffer got queued
+    fn on_queued(&self, result: Result<(), MediaStatus>) -> Result<(), MediaStatus> {
+        self.state.check(result)?;
+
+        if BufferFlag::EndOfStream.is_contained_in(self.flags as i32)
+            && self.state.get() == CodecState::Executing
+        {
+            self.state.set(CodecState::EndOfStream);
         }
+
+        Ok(())
+    }
+}
+
+impl Drop for CodecInputBuffer<'_> {
+    fn drop(&mut self) {
+        if self.queued {
+            return;
+        }
+
+        let _ = self.queue_input();
     }
 }
 
@@ -628,6 +903,42 @@ impl CodecOutputBuffer<'_> {
         }
     }
 
+    /// Wraps the output buffer at `index`.
+    ///
+    /// If the buffer can't be read, it is released back to the codec
+    pub(crate) unsafe fn from_index(
+        codec: *mut AMediaCodec,
+        index: usize,
+        info: BufferInfo,
+        using_buffers: bool,
+    ) -> Result<Self, MediaStatus> {
+        let mut out_size = 0;
+        let mut buffer = null_mut();
+        if using_buffers {
+            buffer = AMediaCodec_getOutputBuffer(codec, index, &mut out_size);
+        }
+
+        let format = AMediaCodec_getOutputFormat(codec);
+        if (using_buffers && buffer.is_null()) || format.is_null() {
+            if !format.is_null() {
+                drop(MediaFormat::from_raw(format));
+            }
+
+            AMediaCodec_releaseOutputBuffer(codec, index, false);
+            return Err(MediaStatus::ErrorUnknown);
+        }
+
+        Ok(Self::new(
+            codec,
+            info,
+            index,
+            using_buffers,
+            buffer,
+            out_size,
+            MediaFormat::from_raw(format),
+        ))
+    }
+
     /// Returns the buffer information
     pub fn info(&self) -> &BufferInfo {
         &self.info
@@ -650,8 +961,10 @@ impl CodecOutputBuffer<'_> {
         &self.format
     }
 
-    /// Returns the buffer as a u8 slice
-    fn buffer_slice(&self) -> Option<&[u8]> {
+    /// Returns the data in this buffer as a u8 slice.
+    ///
+    /// Returns None when the codec renders to a surface
+    pub fn data(&self) -> Option<&[u8]> {
         if !self.using_buffers {
             return None;
         }
@@ -660,7 +973,7 @@ impl CodecOutputBuffer<'_> {
             // Return the size of the readable buffer, instead of the buffer size itself.
             // Returning the entire buffer size is useless for the output buffer, as we only need to read data from it
             Some(&*slice_from_raw_parts(
-                (self.buffer as i32 + self.info.offset) as *mut u8,
+                self.buffer.add(self.info.offset as usize),
                 self.info.size as usize,
             ))
         }
@@ -668,10 +981,10 @@ impl CodecOutputBuffer<'_> {
 
     /// Returns the frame contained in this buffer.
     /// Can either be an audio frame or a video frame
-    pub fn frame(&self) -> Option<Frame> {
+    pub fn frame(&self) -> Option<Frame<'_>> {
         // Determine whether this is an audio or video frame.
         // We can use the mime type to do this
-        let mime = self.format.get_string("mime")?;
+        let mime = self.format.get(keys::MIME)?;
         let is_audio: bool;
 
         // We don't know if we might get some weird mime types, so we check for both audio and video explicitly
@@ -686,8 +999,8 @@ impl CodecOutputBuffer<'_> {
 
         if is_audio {
             // Fetch the PCM Encoding
-            let encoding = self.format.get_i32("pcm-encoding")?;
-            let channels = self.format.get_i32("channel-count")?;
+            let encoding = self.format.get(keys::PCM_ENCODING)?;
+            let channels = self.format.get(keys::CHANNEL_COUNT)?;
 
             // Can't have invalid channels!
             if channels <= 0 {
@@ -697,7 +1010,7 @@ impl CodecOutputBuffer<'_> {
 
             match encoding as usize {
                 ENCODING_PCM_16BIT => {
-                    let slice = self.buffer_slice()?;
+                    let slice = self.data()?;
                     let len = slice.len() / std::mem::size_of::<i16>();
 
                     // Make sure this didn't yield a remainder
@@ -713,13 +1026,13 @@ impl CodecOutputBuffer<'_> {
                         &*raw
                     };
 
-                    return Some(Frame::Audio(AudioFrame::new(
+                    Some(Frame::Audio(AudioFrame::new(
                         SampleFormat::S16(buffer),
                         channels as u32,
-                    )));
+                    )))
                 }
                 ENCODING_PCM_FLOAT => {
-                    let slice = self.buffer_slice()?;
+                    let slice = self.data()?;
                     let len = slice.len() / std::mem::size_of::<f32>();
 
                     // Make sure this didn't yield a remainder
@@ -735,14 +1048,14 @@ impl CodecOutputBuffer<'_> {
                         &*raw
                     };
 
-                    return Some(Frame::Audio(AudioFrame::new(
+                    Some(Frame::Audio(AudioFrame::new(
                         SampleFormat::F32(buffer),
                         channels as u32,
-                    )));
+                    )))
                 }
                 _ => {
                     // We only care about PCM-16 and Float types
-                    return None;
+                    None
                 }
             }
         } else {
@@ -750,9 +1063,10 @@ impl CodecOutputBuffer<'_> {
 
             // We have a surface buffer, so return a video frame with surface buffer for it
             if !self.using_buffers {
-                return Some(Frame::Video(VideoFrame::Hardware));
+                Some(Frame::Video(VideoFrame::Hardware))
             } else {
-                unimplemented!();
+                let frame = RawVideoFrame::new(self.data()?, &self.format)?;
+                Some(Frame::Video(VideoFrame::RawFrame(frame)))
             }
         }
     }
@@ -775,14 +1089,174 @@ impl Drop for CodecOutputBuffer<'_> {
 unsafe impl Send for CodecOutputBuffer<'_> {}
 unsafe impl Sync for CodecOutputBuffer<'_> {}
 
+/// Receives the events of a codec running in asynchronous mode. See [set_callback](MediaCodec::set_callback).
+///
+/// Every method is called on a thread owned by the NDK, so none of them should block for long
+#[cfg(feature = "api28")]
+pub trait CodecCallback: Send {
+    /// Called when an input buffer is available. The buffer is queued to the codec when it gets dropped
+    fn on_input_available(&mut self, buffer: CodecInputBuffer<'_>);
+
+    /// Called when an output buffer is available. The buffer is released to the codec when it gets dropped
+    fn on_output_available(&mut self, buffer: CodecOutputBuffer<'_>);
+
+    /// Called when the output format has changed
+    fn on_format_changed(&mut self, _format: &MediaFormat) {}
+
+    /// Called when the codec ran into an error.
+    ///
+    /// The error carries the action code, which tells whether the codec can recover, and the codec's description of it
+    fn on_error(&mut self, error: Error);
+}
+
+/// A surface that can feed several encoders, one at a time
+#[cfg(feature = "api26")]
+#[derive(Debug, Clone)]
+pub struct PersistentSurface {
+    window: NativeWindow,
+}
+
+#[cfg(feature = "api26")]
+impl PersistentSurface {
+    /// The window to render the encoder input into
+    pub fn window(&self) -> &NativeWindow {
+        &self.window
+    }
+
+    /// The window to render the encoder input into, for locking its buffers
+    pub fn window_mut(&mut self) -> &mut NativeWindow {
+        &mut self.window
+    }
+}
+
+/// The userdata handed to the NDK callbacks
+#[cfg(feature = "api28")]
+pub(crate) struct CallbackState {
+    callback: Mutex<Box<dyn CodecCallback>>,
+    using_buffers: std::sync::atomic::AtomicBool,
+    state: SharedState,
+}
+
+#[cfg(feature = "api28")]
+impl CallbackState {
+    unsafe fn from_userdata<'a>(userdata: *const c_void) -> &'a Self {
+        &*(userdata as *const Self)
+    }
+
+    fn with_callback(&self, f: impl FnOnce(&mut dyn CodecCallback)) {
+        let mut callback = self.callback.lock().unwrap_or_else(|err| err.into_inner());
+        f(callback.as_mut());
+    }
+}
+
+#[cfg(feature = "api28")]
+impl std::fmt::Debug for CallbackState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackState")
+            .field("using_buffers", &self.using_buffers)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "api28")]
+extern "C" fn on_async_input_available(
+    codec: *const AMediaCodec,
+    userdata: *const c_void,
+    index: i32,
+) {
+    unsafe {
+        let state = CallbackState::from_userdata(userdata);
+
+        let codec = codec as *mut AMediaCodec;
+        match CodecInputBuffer::from_index(codec, state.state.clone(), index as usize) {
+            Ok(buffer) => state.with_callback(|callback| callback.on_input_available(buffer)),
+            Err(status) => warn!("Could not get input buffer {index}: {status:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "api28")]
+extern "C" fn on_async_output_available(
+    codec: *const AMediaCodec,
+    userdata: *const c_void,
+    index: i32,
+    info: *const BufferInfo,
+) {
+    unsafe {
+        let state = CallbackState::from_userdata(userdata);
+        let using_buffers = state
+            .using_buffers
+            .load(std::sync::atomic::Ordering::Acquire);
+
+        match CodecOutputBuffer::from_index(
+            codec as *mut AMediaCodec,
+            index as usize,
+            *info,
+            using_buffers,
+        ) {
+            Ok(buffer) => state.with_callback(|callback| callback.on_output_available(buffer)),
+            Err(status) => warn!("Could not get output buffer {index}: {status:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "api28")]
+extern "C" fn on_async_format_changed(
+    _codec: *const AMediaCodec,
+    userdata: *const c_void,
+    format: *const AMediaFormat,
+) {
+    unsafe {
+        let state = CallbackState::from_userdata(userdata);
+
+        if format.is_null() {
+            return;
+        }
+
+        // The NDK hands the format over to the callback, so it is deleted once we're done with it
+        let format = MediaFormat::from_raw(format as *mut AMediaFormat);
+        state.with_callback(|callback| callback.on_format_changed(&format));
+    }
+}
+
+#[cfg(feature = "api28")]
+extern "C" fn on_async_error(
+    _codec: *const AMediaCodec,
+    userdata: *const c_void,
+    error: i32,
+    action_code: i32,
+    detail: *const c_char,
+) {
+    unsafe {
+        let state = CallbackState::from_userdata(userdata);
+        let error = MediaStatus::try_from(error as isize).unwrap_or(MediaStatus::ErrorUnknown);
+        state.state.set(CodecState::Error);
+        let mut error = Error::new(error, "process buffers").with_action_code(action_code);
+        if !detail.is_null() {
+            error = error.with_detail(&std::ffi::CStr::from_ptr(detail).to_string_lossy());
+        }
+
+        state.with_callback(|callback| callback.on_error(error));
+    }
+}
+
 /// The MediaCodec structure itself.
 ///
 /// Represents either a decoder or an encoder
 #[derive(Debug)]
 pub struct MediaCodec<'a> {
-    inner: *mut AMediaCodec,
+    pub(crate) inner: *mut AMediaCodec,
     _marker: PhantomData<&'a *const u8>,
-    using_buffers: bool,
+    pub(crate) using_buffers: bool,
+    pub(crate) state: SharedState,
+    /// Whether the encoder takes its input from a surface instead of buffers
+    #[cfg(feature = "api26")]
+    input_surface: bool,
+    #[cfg(feature = "api28")]
+    pub(crate) callback: Option<Box<CallbackState>>,
+    #[cfg(feature = "async")]
+    pub(crate) driver: Option<crate::async_codec::AsyncDriver>,
 }
 
 impl<'a> MediaCodec<'a> {
@@ -792,49 +1266,51 @@ impl<'a> MediaCodec<'a> {
             inner: ptr,
             _marker: PhantomData,
             using_buffers: false,
+            state: SharedState::new(),
+            #[cfg(feature = "api26")]
+            input_surface: false,
+            #[cfg(feature = "api28")]
+            callback: None,
+            #[cfg(feature = "async")]
+            driver: None,
         }
     }
 
-    /// Creates a codec using its name
-    pub fn new(name: &str) -> Option<Self> {
-        unsafe {
-            let name = CString::new(name).unwrap();
-            let codec = AMediaCodec_createCodecByName(name.as_ptr());
+    /// Creates a codec with `create`, failing with `ErrorUnsupported` when no codec matched
+    fn create(
+        name: &str,
+        create: unsafe extern "C" fn(*const c_char) -> *mut AMediaCodec,
+        error: impl Fn(MediaStatus) -> Error,
+    ) -> Result<Self, Error> {
+        let name = CString::new(name).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+        let codec = unsafe { create(name.as_ptr()) };
+
+        if codec.is_null() {
+            return Err(error(MediaStatus::ErrorUnsupported));
+        }
 
-            if codec.is_null() {
-                return None;
-            }
+        Ok(Self::from_ptr(codec))
+    }
 
-            Some(Self::from_ptr(codec))
-        }
+    /// Creates a codec using its name
+    pub fn new(name: &str) -> Result<Self, Error> {
+        Self::create(name, AMediaCodec_createCodecByName, |status| {
+            Error::new(status, "create codec").with_detail(name)
+        })
     }
 
     /// Creates a decoder using a specific mime type
-    pub fn create_decoder(mime_type: &str) -> Option<Self> {
-        unsafe {
-            let mime_type = CString::new(mime_type).unwrap();
-            let codec = AMediaCodec_createDecoderByType(mime_type.as_ptr());
-
-            if codec.is_null() {
-                return None;
-            }
-
-            Some(Self::from_ptr(codec))
-        }
+    pub fn create_decoder(mime_type: &str) -> Result<Self, Error> {
+        Self::create(mime_type, AMediaCodec_createDecoderByType, |status| {
+            Error::new(status, "create decoder").with_mime_type(mime_type)
+        })
     }
 
     /// Creates an encoder using a specific mime type
-    pub fn create_encoder(mime_type: &str) -> Option<Self> {
-        unsafe {
-            let mime_type = CString::new(mime_type).unwrap();
-            let codec = AMediaCodec_createEncoderByType(mime_type.as_ptr());
-
-            if codec.is_null() {
-                return None;
-            }
-
-            Some(Self::from_ptr(codec))
-        }
+    pub fn create_encoder(mime_type: &str) -> Result<Self, Error> {
+        Self::create(mime_type, AMediaCodec_createEncoderByType, |status| {
+            Error::new(status, "create encoder").with_mime_type(mime_type)
+        })
     }
 
     /// Initializes the codec with the parameters. This must be called before you can start the codec
@@ -843,47 +1319,189 @@ impl<'a> MediaCodec<'a> {
         format: &MediaFormat,
         surface: Option<NativeWindow>,
         flags: u32,
-    ) -> Result<(), MediaStatus> {
+    ) -> Result<(), Error> {
+        self.configure_with(format, surface, null_mut(), flags)
+    }
+
+    /// Initializes a decoder for encrypted content. Its input buffers then have to be queued with
+    /// [queue_secure](CodecInputBuffer::queue_secure)
+    pub fn init_with_crypto(
+        &mut self,
+        format: &MediaFormat,
+        surface: Option<NativeWindow>,
+        crypto: &'a MediaCrypto,
+        flags: u32,
+    ) -> Result<(), Error> {
+        self.configure_with(format, surface, crypto.inner, flags)
+    }
+
+    fn configure_with(
+        &mut self,
+        format: &MediaFormat,
+        surface: Option<NativeWindow>,
+        crypto: *mut AMediaCrypto,
+        flags: u32,
+    ) -> Result<(), Error> {
+        self.state
+            .expect("configure", &[CodecState::Uninitialized])?;
+
+        #[cfg(feature = "api26")]
+        {
+            self.input_surface = false;
+        }
+
         unsafe {
             // configure
 
-            let surface = if surface.is_some() {
-                self.using_buffers = false;
-                surface.unwrap().inner
-            } else {
-                self.using_buffers = true;
-                null_mut()
+            // Keep the window alive until the codec has taken its own reference
+            let window = match surface {
+                Some(ref surface) => {
+                    self.using_buffers = false;
+                    surface.inner
+                }
+                None => {
+                    self.using_buffers = true;
+                    null_mut()
+                }
             };
 
-            AMediaCodec_configure(self.inner, format.inner, surface, null_mut(), flags)
+            #[cfg(feature = "api28")]
+            if let Some(ref state) = self.callback {
+                state
+                    .using_buffers
+                    .store(self.using_buffers, std::sync::atomic::Ordering::Release);
+            }
+
+            AMediaCodec_configure(self.inner, format.inner, window, crypto, flags)
                 .result()
-                .map(|_value| ())
+                .map_err(error("configure"))?;
         }
+
+        self.state.set(CodecState::Configured);
+        Ok(())
+    }
+
+    /// Returns the current state of the codec
+    pub fn state(&self) -> CodecState {
+        self.state.get()
     }
 
     /// Starts the codec for processing.
     ///
-    /// This must be called only after the codec has been initialized
-    pub fn start(&mut self) -> Result<(), MediaStatus> {
-        unsafe { AMediaCodec_start(self.inner).result().map(|_value| ()) }
+    /// This must be called only after the codec has been initialized.
+    /// In asynchronous mode, this also resumes a codec that was flushed
+    pub fn start(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "api28")]
+        let resuming = self.callback.is_some()
+            && matches!(
+                self.state.get(),
+                CodecState::Executing | CodecState::EndOfStream
+            );
+        #[cfg(not(feature = "api28"))]
+        let resuming = false;
+
+        if !resuming {
+            self.state.expect("start", &[CodecState::Configured])?;
+        }
+
+        unsafe { AMediaCodec_start(self.inner).result() }.map_err(error("start"))?;
+
+        self.state.set(CodecState::Executing);
+
+        #[cfg(feature = "async")]
+        if let Some(ref mut driver) = self.driver {
+            driver.resume(self.inner);
+        }
+
+        Ok(())
     }
 
+    /// Stops the codec. It has to be initialized again before it can be restarted.
+    ///
     /// **WARNING**
     ///
     /// Make sure you have released all pending buffers before calling this function
-    pub fn stop(&mut self) -> Result<(), MediaStatus> {
-        unsafe { AMediaCodec_stop(self.inner).result().map(|_| ()) }
+    pub fn stop(&mut self) -> Result<(), Error> {
+        if self.state.get() == CodecState::Uninitialized {
+            return Ok(());
+        }
+
+        self.state.expect(
+            "stop",
+            &[
+                CodecState::Configured,
+                CodecState::Executing,
+                CodecState::EndOfStream,
+                CodecState::Error,
+            ],
+        )?;
+
+        #[cfg(feature = "async")]
+        if let Some(ref mut driver) = self.driver {
+            driver.shutdown();
+            if let Err(status) = driver.run_reset(|| unsafe { AMediaCodec_stop(self.inner) }) {
+                driver.resume(self.inner);
+                return Err(Error::new(status, "stop"));
+            }
+            self.state.set(CodecState::Uninitialized);
+            return Ok(());
+        }
+
+        unsafe { AMediaCodec_stop(self.inner).result() }.map_err(error("stop"))?;
+
+        self.state.set(CodecState::Uninitialized);
+        Ok(())
     }
 
+    /// Discards every pending input and output buffer, and leaves the end of stream.
+    ///
     /// **WARNING**
     ///
     /// Make sure you have released all pending buffers before calling this function
-    pub fn flush(&mut self) -> Result<(), MediaStatus> {
-        unsafe { AMediaCodec_flush(self.inner).result().map(|_| ()) }
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.state
+            .expect("flush", &[CodecState::Executing, CodecState::EndOfStream])?;
+
+        #[cfg(feature = "async")]
+        if let Some(ref driver) = self.driver {
+            let result = driver.flush(self.inner);
+            self.state.check(result).map_err(error("flush"))?;
+            self.state.set(CodecState::Executing);
+            return Ok(());
+        }
+
+        let result = unsafe { AMediaCodec_flush(self.inner).result() };
+        self.state.check(result).map_err(error("flush"))?;
+
+        self.state.set(CodecState::Executing);
+        Ok(())
+    }
+
+    /// Frees the codec right away, instead of when it gets dropped. The codec can't be used afterwards
+    pub fn release(&mut self) -> Result<(), Error> {
+        if self.state.get() == CodecState::Released {
+            return Err(Error::new(MediaStatus::ErrorInvalidOperation, "release")
+                .with_codec_state(CodecState::Released));
+        }
+
+        #[cfg(feature = "async")]
+        if let Some(ref mut driver) = self.driver {
+            driver.shutdown();
+        }
+
+        let status = unsafe { AMediaCodec_delete(self.inner) };
+        self.inner = null_mut();
+        self.state.set(CodecState::Released);
+
+        status.result().map(|_| ()).map_err(error("release"))
     }
 
     /// Returns the output format of this codec (if we can find one)
     pub fn output_format(&self) -> Option<MediaFormat> {
+        if self.state.get() == CodecState::Released {
+            return None;
+        }
+
         unsafe {
             let format = AMediaCodec_getOutputFormat(self.inner);
             if format.is_null() {
@@ -895,84 +1513,378 @@ impl<'a> MediaCodec<'a> {
     }
 
     /// Sets the codec output surface. This will only work if the codec has been initialized with an output surface
-    /// before starting
-    pub fn set_output_surface(&mut self, window: NativeWindow) -> bool {
+    pub fn set_output_surface(&mut self, window: NativeWindow) -> Result<(), Error> {
+        self.state.expect(
+            "set output surface",
+            &[
+                CodecState::Configured,
+                CodecState::Executing,
+                CodecState::EndOfStream,
+            ],
+        )?;
+
         if self.using_buffers {
-            return false;
+            debug!("The codec was initialized without an output surface");
+            return Err(Error::new(
+                MediaStatus::ErrorInvalidOperation,
+                "set output surface",
+            ));
         }
 
-        unsafe {
-            AMediaCodec_setOutputSurface(self.inner, window.inner);
-            true
-        }
+        unsafe { AMediaCodec_setOutputSurface(self.inner, window.inner) }
+            .result()
+            .map(|_| ())
+            .map_err(error("set output surface"))
     }
 
-    /// Get an input buffer from mediacodec
-    pub fn dequeue_input(&mut self) -> Result<CodecInputBuffer, MediaStatus> {
-        unsafe {
-            // 100us wait time is not too much, right?
-            let index = AMediaCodec_dequeueInputBuffer(self.inner, 100);
+    /// Creates the surface an encoder takes its input from, in place of input buffers.
+    ///
+    /// This must be called after the encoder is initialized, and before it's started
+    #[cfg(feature = "api26")]
+    pub fn create_input_surface(&mut self) -> Result<NativeWindow, Error> {
+        self.state
+            .expect("create input surface", &[CodecState::Configured])?;
 
-            if index >= 0 {
-                let mut out_size = 0;
-                let buffer = AMediaCodec_getInputBuffer(self.inner, index as usize, &mut out_size);
-
-                if buffer.is_null() {
-                    // Return the buffer to the codec, it's not valid
-                    AMediaCodec_queueInputBuffer(self.inner, index as usize, 0, 0, 0, 0);
-                    warn!("Got an index with a null input buffer! What is going on here??? Index: {index}");
-                    return Err(MediaStatus::ErrorUnknown);
-                }
+        let mut window = null_mut();
+        unsafe { AMediaCodec_createInputSurface(self.inner, &mut window).result() }
+            .map_err(error("create input surface"))?;
 
-                let buf = CodecInputBuffer::new(self.inner, index as usize, buffer, out_size);
+        self.input_surface = true;
+        Ok(NativeWindow::from_raw(window))
+    }
 
-                return Ok(buf);
-            }
+    /// Creates a surface that can feed several encoders, one at a time.
+    ///
+    /// Use [set_input_surface](MediaCodec::set_input_surface) to hand it to an encoder
+    #[cfg(feature = "api26")]
+    pub fn create_persistent_input_surface() -> Result<PersistentSurface, Error> {
+        let mut window = null_mut();
+        unsafe { AMediaCodec_createPersistentInputSurface(&mut window).result() }
+            .map_err(error("create persistent input surface"))?;
+
+        Ok(PersistentSurface {
+            window: NativeWindow::from_raw(window),
+        })
+    }
 
-            Err(MediaStatus::try_from(index).unwrap_or(MediaStatus::ErrorUnknown))
-        }
+    /// Makes the encoder take its input from `surface`, in place of input buffers.
+    ///
+    /// This must be called after the encoder is initialized, and before it's started
+    #[cfg(feature = "api26")]
+    pub fn set_input_surface(&mut self, surface: &PersistentSurface) -> Result<(), Error> {
+        self.state
+            .expect("set input surface", &[CodecState::Configured])?;
+
+        unsafe { AMediaCodec_setInputSurface(self.inner, surface.window.inner).result() }
+            .map_err(error("set input surface"))?;
+
+        self.input_surface = true;
+        Ok(())
     }
 
-    /// Get an output buffer from mediacodec
-    pub fn dequeue_output(&mut self) -> Result<CodecOutputBuffer, MediaStatus> {
-        unsafe {
-            let mut info = BufferInfo::default();
-            let index = AMediaCodec_dequeueOutputBuffer(self.inner, &mut info, 100);
-            let mut out_size = 0;
+    /// Changes parameters of the running codec, without restarting it.
+    ///
+    /// Some of the changes may silently fail to apply
+    #[cfg(feature = "api26")]
+    pub fn set_parameters(&mut self, parameters: &MediaFormat) -> Result<(), Error> {
+        self.state.expect(
+            "set parameters",
+            &[CodecState::Executing, CodecState::EndOfStream],
+        )?;
+
+        unsafe { AMediaCodec_setParameters(self.inner, parameters.inner) }
+            .result()
+            .map(|_| ())
+            .map_err(error("set parameters"))
+    }
 
-            if index >= 0 {
-                let mut buffer = null_mut();
-                if self.using_buffers {
-                    buffer = AMediaCodec_getOutputBuffer(self.inner, index as usize, &mut out_size);
+    /// Sets the parameters filled in by `f`
+    #[cfg(feature = "api26")]
+    fn set_parameters_with(&mut self, f: impl FnOnce(&mut MediaFormat)) -> Result<(), Error> {
+        let mut parameters = MediaFormat::new()
+            .ok_or_else(|| Error::new(MediaStatus::ErrorUnknown, "set parameters"))?;
+        f(&mut parameters);
+
+        self.set_parameters(&parameters)
+    }
+
+    /// Asks the encoder to produce a sync frame soon
+    #[cfg(feature = "api26")]
+    pub fn request_sync_frame(&mut self) -> Result<(), Error> {
+        self.set_parameters_with(|parameters| {
+            parameters.set(keys::REQUEST_SYNC_FRAME, 0);
+        })
+    }
+
+    /// Changes the target bitrate (in bits per second) of a video encoder
+    #[cfg(feature = "api26")]
+    pub fn set_video_bitrate(&mut self, bitrate: i32) -> Result<(), Error> {
+        self.set_parameters_with(|parameters| {
+            parameters.set(keys::VIDEO_BITRATE, bitrate);
+        })
+    }
+
+    /// Makes an encoder reading from an input surface drop its input frames until it's resumed
+    #[cfg(feature = "api26")]
+    pub fn suspend_input(&mut self, suspend: bool) -> Result<(), Error> {
+        self.set_parameters_with(|parameters| {
+            parameters.set(keys::SUSPEND, suspend as i32);
+        })
+    }
+
+    /// Resumes a suspended encoder at `time_us`. Input frames before that timestamp are dropped
+    #[cfg(feature = "api29")]
+    pub fn drop_frames_before(&mut self, time_us: i64) -> Result<(), Error> {
+        self.set_parameters_with(|parameters| {
+            parameters.set(keys::SUSPEND, 0);
+            parameters.set(keys::SUSPEND_TIME, time_us);
+        })
+    }
 
-                    if buffer.is_null() {
-                        AMediaCodec_releaseOutputBuffer(self.inner, index as usize, false);
-                        return Err(MediaStatus::ErrorUnknown);
+    /// Signals the end of the input stream.
+    ///
+    /// An encoder reading from an input surface is told directly. Otherwise, this waits up to `timeout` for an input
+    /// buffer and queues it empty, flagged with the end of stream, or fails with [MediaStatus::ErrorWouldBlock]
+    pub fn signal_end_of_stream(&mut self, timeout: impl Into<Timeout>) -> Result<(), Error> {
+        #[cfg(feature = "api26")]
+        if self.input_surface {
+            self.state
+                .expect("signal end of stream", &[CodecState::Executing])?;
+
+            let result = unsafe { AMediaCodec_signalEndOfInputStream(self.inner).result() };
+            self.state
+                .check(result)
+                .map_err(error("signal end of stream"))?;
+
+            self.state.set(CodecState::EndOfStream);
+            return Ok(());
+        }
+
+        let mut buffer = self
+            .dequeue_input_timeout(timeout)?
+            .buffer()
+            .ok_or_else(|| Error::new(MediaStatus::ErrorWouldBlock, "signal end of stream"))?;
+        buffer.set_flags(BufferFlag::EndOfStream as u32);
+
+        buffer.queue()
+    }
+
+    /// Pulls the remaining output buffers, handing each one to `f`, up to the one flagged with the end of stream.
+    ///
+    /// The end of stream has to be queued first, through [signal_end_of_stream](MediaCodec::signal_end_of_stream) or
+    /// [MediaExtractor::read_next](crate::MediaExtractor::read_next).
+    /// Fails with [MediaStatus::ErrorWouldBlock] when no buffer comes out within `timeout`, after which draining can be
+    /// resumed
+    pub fn drain(
+        &mut self,
+        timeout: impl Into<Timeout>,
+        mut f: impl FnMut(CodecOutputBuffer<'_>),
+    ) -> Result<(), Error> {
+        self.state.expect("drain", &[CodecState::EndOfStream])?;
+        let timeout = timeout.into();
+
+        loop {
+            match self.dequeue_output_timeout(timeout)? {
+                Dequeued::Buffer(buffer) => {
+                    let end_of_stream = buffer.info().is_end_of_stream();
+                    f(buffer);
+
+                    if end_of_stream {
+                        return Ok(());
                     }
                 }
+                Dequeued::TryAgainLater => {
+                    return Err(Error::new(MediaStatus::ErrorWouldBlock, "drain"))
+                }
+                Dequeued::OutputFormatChanged | Dequeued::OutputBuffersChanged => {}
+            }
+        }
+    }
+
+    /// Switches the codec to asynchronous mode, where `callback` gets notified of every available buffer, instead of
+    /// polling with [dequeue_input](MediaCodec::dequeue_input) and [dequeue_output](MediaCodec::dequeue_output).
+    ///
+    /// This must be called before the codec is initialized, and fails once `enable_async` (`async` feature)
+    /// took over the notifications.
+    /// In asynchronous mode, the codec has to be started again after a flush to resume receiving input buffers
+    #[cfg(feature = "api28")]
+    pub fn set_callback(&mut self, callback: impl CodecCallback + 'static) -> Result<(), Error> {
+        self.state
+            .expect("set callback", &[CodecState::Uninitialized])?;
+
+        #[cfg(feature = "async")]
+        if self.driver.is_some() {
+            return Err(Error::new(
+                MediaStatus::ErrorInvalidOperation,
+                "set callback",
+            ));
+        }
+
+        let state = Box::new(CallbackState {
+            callback: Mutex::new(Box::new(callback)),
+            using_buffers: std::sync::atomic::AtomicBool::new(self.using_buffers),
+            state: self.state.clone(),
+        });
+
+        let callbacks = _AMediaCodecOnAsyncNotifyCallback {
+            on_async_input_available,
+            on_async_output_available,
+            on_async_format_changed,
+            on_async_error,
+        };
 
-                let codec_buffer = CodecOutputBuffer::new(
+        unsafe {
+            AMediaCodec_setAsyncNotifyCallback(
+                self.inner,
+                callbacks,
+                &*state as *const CallbackState as *mut c_void,
+            )
+            .result()
+            .map_err(error("set callback"))?;
+        }
+
+        // The codec no longer uses the previous callback, if there was one
+        self.callback = Some(state);
+        Ok(())
+    }
+
+    /// Get an input buffer from mediacodec, waiting up to 100µs for one.
+    ///
+    /// Fails with `ErrorWouldBlock` when no buffer is available. Use
+    /// [dequeue_input_timeout](MediaCodec::dequeue_input_timeout) to pick the timeout
+    pub fn dequeue_input(&mut self) -> Result<CodecInputBuffer<'_>, Error> {
+        // 100us wait time is not too much, right?
+        match self.dequeue_input_timeout(Duration::from_micros(100))? {
+            Dequeued::Buffer(buffer) => Ok(buffer),
+            _ => Err(Error::new(
+                MediaStatus::ErrorWouldBlock,
+                "dequeue input buffer",
+            )),
+        }
+    }
+
+    /// Get an output buffer from mediacodec, waiting up to 100µs for one.
+    ///
+    /// Fails with `ErrorWouldBlock` when no buffer is available, including when the output format changed. Use
+    /// [dequeue_output_timeout](MediaCodec::dequeue_output_timeout) to tell those apart
+    pub fn dequeue_output(&mut self) -> Result<CodecOutputBuffer<'_>, Error> {
+        match self.dequeue_output_timeout(Duration::from_micros(100))? {
+            Dequeued::Buffer(buffer) => Ok(buffer),
+            _ => Err(Error::new(
+                MediaStatus::ErrorWouldBlock,
+                "dequeue output buffer",
+            )),
+        }
+    }
+
+    /// Get an input buffer from mediacodec, waiting for one as long as `timeout` says
+    pub fn dequeue_input_timeout(
+        &mut self,
+        timeout: impl Into<Timeout>,
+    ) -> Result<Dequeued<CodecInputBuffer<'_>>, Error> {
+        let error = error("dequeue input buffer");
+
+        if self.state.get() == CodecState::EndOfStream {
+            return Err(
+                error(MediaStatus::ErrorEndOfStream).with_codec_state(CodecState::EndOfStream)
+            );
+        }
+        self.state
+            .expect("dequeue input buffer", &[CodecState::Executing])?;
+
+        #[cfg(feature = "api26")]
+        if self.input_surface {
+            debug!("The encoder takes its input from a surface");
+            return Err(error(MediaStatus::ErrorInvalidOperation));
+        }
+
+        unsafe {
+            let index = AMediaCodec_dequeueInputBuffer(self.inner, timeout.into().as_us());
+
+            if index >= 0 {
+                return CodecInputBuffer::from_index(
                     self.inner,
-                    info,
+                    self.state.clone(),
                     index as usize,
-                    self.using_buffers,
-                    buffer,
-                    out_size,
-                    self.output_format().unwrap(),
-                );
+                )
+                .map(Dequeued::Buffer)
+                .map_err(error);
+            }
+
+            self.state.check(Dequeued::from_info(index)).map_err(error)
+        }
+    }
 
-                return Ok(codec_buffer);
+    /// Get an output buffer from mediacodec, waiting for one as long as `timeout` says
+    pub fn dequeue_output_timeout(
+        &mut self,
+        timeout: impl Into<Timeout>,
+    ) -> Result<Dequeued<CodecOutputBuffer<'_>>, Error> {
+        match self.dequeue_output_index(timeout)? {
+            Dequeued::Buffer((index, info)) => {
+                self.output_buffer_at(index, info).map(Dequeued::Buffer)
             }
+            Dequeued::TryAgainLater => Ok(Dequeued::TryAgainLater),
+            Dequeued::OutputFormatChanged => Ok(Dequeued::OutputFormatChanged),
+            Dequeued::OutputBuffersChanged => Ok(Dequeued::OutputBuffersChanged),
+        }
+    }
 
-            Err(MediaStatus::ErrorUnknown)
+    /// Dequeues the index of an output buffer, without wrapping it.
+    ///
+    /// The buffer has to be released with [release_output_index](MediaCodec::release_output_index) or wrapped with
+    /// [output_buffer_at](MediaCodec::output_buffer_at)
+    pub(crate) fn dequeue_output_index(
+        &mut self,
+        timeout: impl Into<Timeout>,
+    ) -> Result<Dequeued<(usize, BufferInfo)>, Error> {
+        self.state.expect(
+            "dequeue output buffer",
+            &[CodecState::Executing, CodecState::EndOfStream],
+        )?;
+
+        let mut info = BufferInfo::default();
+        let index = unsafe {
+            AMediaCodec_dequeueOutputBuffer(self.inner, &mut info, timeout.into().as_us())
+        };
+
+        if index >= 0 {
+            return Ok(Dequeued::Buffer((index as usize, info)));
         }
+
+        self.state
+            .check(Dequeued::from_info(index))
+            .map_err(error("dequeue output buffer"))
+    }
+
+    /// Hands back a buffer dequeued with [dequeue_output_index](MediaCodec::dequeue_output_index), without rendering it
+    pub(crate) fn release_output_index(&mut self, index: usize) {
+        unsafe { AMediaCodec_releaseOutputBuffer(self.inner, index, false) };
+    }
+
+    /// Wraps a buffer dequeued with [dequeue_output_index](MediaCodec::dequeue_output_index)
+    pub(crate) fn output_buffer_at(
+        &mut self,
+        index: usize,
+        info: BufferInfo,
+    ) -> Result<CodecOutputBuffer<'_>, Error> {
+        unsafe { CodecOutputBuffer::from_index(self.inner, index, info, self.using_buffers) }
+            .map_err(error("dequeue output buffer"))
     }
 }
 
 impl<'a> Drop for MediaCodec<'a> {
     fn drop(&mut self) {
-        unsafe {
-            AMediaCodec_delete(self.inner);
+        // The polling thread must be gone before the codec is
+        #[cfg(feature = "async")]
+        if let Some(ref mut driver) = self.driver {
+            driver.shutdown();
+        }
+
+        if self.state.get() != CodecState::Released {
+            unsafe {
+                AMediaCodec_delete(self.inner);
+            }
         }
     }
 }
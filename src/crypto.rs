@@ -1,5 +1,230 @@
+use std::{ffi::CString, os::raw::c_char};
+
+use crate::{
+    AMediaCodecCryptoInfo, AMediaCodecCryptoInfo_delete, AMediaCodecCryptoInfo_getClearBytes,
+    AMediaCodecCryptoInfo_getEncryptedBytes, AMediaCodecCryptoInfo_getIV,
+    AMediaCodecCryptoInfo_getKey, AMediaCodecCryptoInfo_getMode,
+    AMediaCodecCryptoInfo_getNumSubSamples, AMediaCodecCryptoInfo_new, CryptoInfoMode,
+    CryptoInfoPattern, DrmSession, Error, MediaStatus,
+};
+
 #[repr(C)]
 pub struct AMediaCrypto {
     _data: [u8; 0],
     _marker: core::marker::PhantomData<(*mut u8, core::marker::PhantomPinned)>,
 }
+
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
+#[allow(dead_code)]
+extern "C" {
+    /// Query if the crypto scheme identified by its UUID is supported on this device
+    ///
+    /// Since: API 21
+    fn AMediaCrypto_isCryptoSchemeSupported(uuid: *const u8) -> bool;
+
+    /// Query if the given mime type needs a secure decoder to decrypt its content
+    ///
+    /// Since: API 21
+    fn AMediaCrypto_requiresSecureDecoderComponent(mime: *const c_char) -> bool;
+
+    /// Create a crypto object for the scheme identified by its UUID. The init data is the session id, opened by a MediaDrm of the same scheme
+    ///
+    /// Since: API 21
+    fn AMediaCrypto_new(
+        uuid: *const u8,
+        init_data: *const u8,
+        init_size: usize,
+    ) -> *mut AMediaCrypto;
+
+    /// Delete a crypto object
+    ///
+    /// Since: API 21
+    fn AMediaCrypto_delete(crypto: *mut AMediaCrypto);
+}
+
+/// The Type-Safe wrapper for `AMediaCrypto`.
+///
+/// Decrypts the secure input buffers of a codec, using the keys of a [MediaDrm](crate::MediaDrm) session.
+/// Hand it to [MediaCodec::init_with_crypto](crate::MediaCodec::init_with_crypto)
+#[derive(Debug)]
+pub struct MediaCrypto {
+    pub(crate) inner: *mut AMediaCrypto,
+}
+
+impl MediaCrypto {
+    /// Returns true if the crypto scheme identified by `uuid` is supported on this device
+    pub fn is_scheme_supported(uuid: &[u8; 16]) -> bool {
+        unsafe { AMediaCrypto_isCryptoSchemeSupported(uuid.as_ptr()) }
+    }
+
+    /// Returns true if content of `mime_type` can only be decrypted by a secure decoder
+    pub fn requires_secure_decoder_component(mime_type: &str) -> bool {
+        let Ok(mime_type) = CString::new(mime_type) else {
+            return false;
+        };
+
+        unsafe { AMediaCrypto_requiresSecureDecoderComponent(mime_type.as_ptr()) }
+    }
+
+    /// Creates a crypto object for the scheme identified by `uuid`, decrypting with the keys of `session`
+    pub fn new(uuid: &[u8; 16], session: &DrmSession) -> Result<Self, Error> {
+        let id = session.id();
+        let inner = unsafe { AMediaCrypto_new(uuid.as_ptr(), id.as_ptr(), id.len()) };
+
+        if inner.is_null() {
+            return Err(Error::new(MediaStatus::ErrorUnsupported, "create crypto"));
+        }
+
+        Ok(Self { inner })
+    }
+}
+
+unsafe impl Send for MediaCrypto {}
+unsafe impl Sync for MediaCrypto {}
+
+impl Drop for MediaCrypto {
+    fn drop(&mut self) {
+        unsafe { AMediaCrypto_delete(self.inner) };
+    }
+}
+
+/// A run of clear bytes followed by a run of encrypted bytes, inside an encrypted buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubSample {
+    pub clear_bytes: usize,
+    pub encrypted_bytes: usize,
+}
+
+impl SubSample {
+    pub fn new(clear_bytes: usize, encrypted_bytes: usize) -> Self {
+        Self {
+            clear_bytes,
+            encrypted_bytes,
+        }
+    }
+}
+
+/// Describes how the data in an input buffer is encrypted.
+///
+/// Use [CryptoInfoMode::AesCtr](CryptoInfoMode::AesCtr) for `cenc` content, and
+/// [CryptoInfoMode::AesCbc](CryptoInfoMode::AesCbc) along with a pattern for `cbcs` content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptoInfo {
+    pub mode: CryptoInfoMode,
+    pub key: [u8; 16],
+    pub iv: [u8; 16],
+    pub subsamples: Vec<SubSample>,
+    /// The pattern of encrypted and skipped blocks. None encrypts every block
+    pub pattern: Option<CryptoInfoPattern>,
+}
+
+impl CryptoInfo {
+    pub fn new(
+        mode: CryptoInfoMode,
+        key: [u8; 16],
+        iv: [u8; 16],
+        subsamples: Vec<SubSample>,
+    ) -> Self {
+        Self {
+            mode,
+            key,
+            iv,
+            subsamples,
+            pattern: None,
+        }
+    }
+
+    /// Sets the pattern of encrypted and skipped blocks
+    pub fn with_pattern(mut self, pattern: CryptoInfoPattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// The number of bytes covered by the subsamples
+    pub fn size(&self) -> usize {
+        self.subsamples
+            .iter()
+            .map(|subsample| subsample.clear_bytes + subsample.encrypted_bytes)
+            .sum()
+    }
+
+    /// Reads an `AMediaCodecCryptoInfo` back. The NDK has no getter for the pattern, so it's left out
+    pub(crate) unsafe fn from_ptr(info: *mut AMediaCodecCryptoInfo) -> Result<Self, MediaStatus> {
+        let count = AMediaCodecCryptoInfo_getNumSubSamples(info);
+        let mut key = [0; 16];
+        let mut iv = [0; 16];
+        let mut clear_bytes = vec![0; count];
+        let mut encrypted_bytes = vec![0; count];
+
+        MediaStatus::make_result(AMediaCodecCryptoInfo_getKey(info, key.as_mut_ptr()))?;
+        MediaStatus::make_result(AMediaCodecCryptoInfo_getIV(info, iv.as_mut_ptr()))?;
+        MediaStatus::make_result(AMediaCodecCryptoInfo_getClearBytes(
+            info,
+            clear_bytes.as_mut_ptr(),
+        ))?;
+        MediaStatus::make_result(AMediaCodecCryptoInfo_getEncryptedBytes(
+            info,
+            encrypted_bytes.as_mut_ptr(),
+        ))?;
+
+        let subsamples = clear_bytes
+            .into_iter()
+            .zip(encrypted_bytes)
+            .map(|(clear, encrypted)| SubSample::new(clear, encrypted))
+            .collect();
+
+        Ok(Self::new(
+            AMediaCodecCryptoInfo_getMode(info),
+            key,
+            iv,
+            subsamples,
+        ))
+    }
+
+    /// Hands an `AMediaCodecCryptoInfo` describing this info to `f`, and frees it afterwards
+    pub(crate) fn with_ptr<T>(
+        &self,
+        f: impl FnOnce(*mut AMediaCodecCryptoInfo) -> Result<T, MediaStatus>,
+    ) -> Result<T, MediaStatus> {
+        let mut clear_bytes: Vec<usize> = self.subsamples.iter().map(|s| s.clear_bytes).collect();
+        let mut encrypted_bytes: Vec<usize> =
+            self.subsamples.iter().map(|s| s.encrypted_bytes).collect();
+
+        let info = unsafe {
+            AMediaCodecCryptoInfo_new(
+                self.subsamples.len() as i32,
+                &self.key,
+                &self.iv,
+                self.mode,
+                clear_bytes.as_mut_ptr(),
+                encrypted_bytes.as_mut_ptr(),
+            )
+        };
+        if info.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        let result = self.apply_pattern(info).and_then(|_| f(info));
+
+        unsafe { AMediaCodecCryptoInfo_delete(info) };
+        result
+    }
+
+    #[cfg(feature = "api24")]
+    fn apply_pattern(&self, info: *mut AMediaCodecCryptoInfo) -> Result<(), MediaStatus> {
+        if let Some(mut pattern) = self.pattern {
+            unsafe { crate::AMediaCodecCryptoInfo_setPattern(info, &mut pattern) };
+        }
+
+        Ok(())
+    }
+
+    /// Patterns need API 24
+    #[cfg(not(feature = "api24"))]
+    fn apply_pattern(&self, _info: *mut AMediaCodecCryptoInfo) -> Result<(), MediaStatus> {
+        match self.pattern {
+            Some(_) => Err(MediaStatus::ErrorUnsupported),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use crate::{
+    keys, ColorRange, ColorStandard, ColorTransfer, Error, MediaFormat, MediaStatus, Rect,
+};
+
+/// Removes the `0x03` bytes the encoder inserted after every `00 00` pair, turning a NAL unit back into its raw
+/// bytes (RBSP)
+pub fn remove_emulation_prevention(nal: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+
+    for &byte in nal {
+        if zeros >= 2 && byte == 0x03 {
+            zeros = 0;
+            continue;
+        }
+
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        data.push(byte);
+    }
+
+    data
+}
+
+/// Splits an Annex B stream (`00 00 01` or `00 00 00 01` start codes) into its NAL units.
+///
+/// Data without a start code is returned as a single NAL unit
+pub fn split_nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let starts = (0..data.len().saturating_sub(2))
+        .filter(|&index| data[index..index + 3] == [0, 0, 1])
+        .collect::<Vec<_>>();
+    if starts.is_empty() {
+        return if data.is_empty() { vec![] } else { vec![data] };
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, &start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(data.len());
+            // Trailing zeros belong to the next start code
+            let nal = &data[start + 3..end];
+            let len = nal.iter().rposition(|&byte| byte != 0).map_or(0, |i| i + 1);
+            &nal[..len]
+        })
+        .filter(|nal| !nal.is_empty())
+        .collect()
+}
+
+/// Reads the bits of a parameter set, most significant bit first.
+///
+/// Every error is [MediaStatus::ErrorMalformed], tagged with the operation given to [BitReader::new]
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    operation: &'static str,
+}
+
+impl<'a> BitReader<'a> {
+    /// Reads `data`, which must have its emulation prevention bytes removed already
+    pub fn new(data: &'a [u8], operation: &'static str) -> Self {
+        Self {
+            data,
+            position: 0,
+            operation,
+        }
+    }
+
+    /// An error for the current operation, explained by `detail`
+    pub fn error(&self, detail: &str) -> Error {
+        Error::new(MediaStatus::ErrorMalformed, self.operation).with_detail(detail)
+    }
+
+    /// The number of bits read so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of bits left to read
+    pub fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    /// Reads a single bit, a flag of the parameter set
+    pub fn read_bit(&mut self) -> Result<bool, Error> {
+        let byte = self
+            .data
+            .get(self.position / 8)
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit == 1)
+    }
+
+    /// Reads an unsigned value of up to 32 bits
+    pub fn read_bits(&mut self, count: u32) -> Result<u32, Error> {
+        debug_assert!(count <= 32);
+        if self.remaining() < count as usize {
+            return Err(self.error("unexpected end of data"));
+        }
+
+        let mut value = 0u64;
+        for _ in 0..count {
+            value = value << 1 | self.read_bit()? as u64;
+        }
+        Ok(value as u32)
+    }
+
+    /// Skips `count` bits, like fields nothing reads
+    pub fn skip_bits(&mut self, count: usize) -> Result<(), Error> {
+        if self.remaining() < count {
+            return Err(self.error("unexpected end of data"));
+        }
+
+        self.position += count;
+        Ok(())
+    }
+
+    /// Reads an unsigned exp-Golomb value, `ue(v)`
+    pub fn read_ue(&mut self) -> Result<u32, Error> {
+        let mut zeros = 0;
+        while !self.read_bit()? {
+            zeros += 1;
+            if zeros > 31 {
+                return Err(self.error("exp-Golomb value too large"));
+            }
+        }
+
+        let value = (1u64 << zeros) - 1 + self.read_bits(zeros)? as u64;
+        u32::try_from(value).map_err(|_| self.error("exp-Golomb value too large"))
+    }
+
+    /// Reads a signed exp-Golomb value, `se(v)`
+    pub fn read_se(&mut self) -> Result<i32, Error> {
+        let value = self.read_ue()? as i64;
+        Ok(if value % 2 == 1 {
+            (value + 1) / 2
+        } else {
+            -(value / 2)
+        } as i32)
+    }
+
+    /// Whether there's more data before the trailing bits that end the parameter set, `more_rbsp_data()`
+    pub fn more_rbsp_data(&self) -> bool {
+        let Some(last) = self.data.iter().rposition(|&byte| byte != 0) else {
+            return false;
+        };
+        let stop_bit = last * 8 + 7 - self.data[last].trailing_zeros() as usize;
+        self.position < stop_bit
+    }
+}
+
+/// The subsampling of the chroma planes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromaFormat {
+    Monochrome,
+    Yuv420,
+    Yuv422,
+    Yuv444,
+}
+
+impl ChromaFormat {
+    pub(crate) fn from_idc(idc: u32) -> Option<Self> {
+        match idc {
+            0 => Some(Self::Monochrome),
+            1 => Some(Self::Yuv420),
+            2 => Some(Self::Yuv422),
+            3 => Some(Self::Yuv444),
+            _ => None,
+        }
+    }
+
+    /// The horizontal and vertical subsampling factors, `SubWidthC` and `SubHeightC`
+    pub(crate) fn subsampling(&self) -> (u32, u32) {
+        match self {
+            Self::Monochrome | Self::Yuv444 => (1, 1),
+            Self::Yuv420 => (2, 2),
+            Self::Yuv422 => (2, 1),
+        }
+    }
+}
+
+/// The pixels cropped from each edge of the coded frame, in luma samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameCrop {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl FrameCrop {
+    /// The visible part of a `width`x`height` frame, with inclusive edges like the `crop` format key
+    pub(crate) fn display_rect(&self, width: u32, height: u32) -> Rect {
+        Rect {
+            left: self.left as i32,
+            top: self.top as i32,
+            right: width as i32 - self.right as i32 - 1,
+            bottom: height as i32 - self.bottom as i32 - 1,
+        }
+    }
+}
+
+/// The `colour_primaries`, `transfer_characteristics` and `matrix_coeffs` of a video, as numbered by ITU-T H.273
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorDescription {
+    pub primaries: u8,
+    pub transfer: u8,
+    pub matrix: u8,
+}
+
+impl ColorDescription {
+    /// The standard to configure a codec with, if Android has one for these primaries
+    pub fn color_standard(&self) -> Option<ColorStandard> {
+        match self.primaries {
+            1 => Some(ColorStandard::Bt709),
+            5 => Some(ColorStandard::Bt601Pal),
+            6 | 7 => Some(ColorStandard::Bt601Ntsc),
+            9 => Some(ColorStandard::Bt2020),
+            _ => None,
+        }
+    }
+
+    /// The transfer to configure a codec with, if Android has one for these characteristics
+    pub fn color_transfer(&self) -> Option<ColorTransfer> {
+        match self.transfer {
+            1 | 6 | 14 | 15 => Some(ColorTransfer::SdrVideo),
+            8 => Some(ColorTransfer::Linear),
+            16 => Some(ColorTransfer::St2084),
+            18 => Some(ColorTransfer::Hlg),
+            _ => None,
+        }
+    }
+
+    /// Whether the transfer is PQ (HDR10) or HLG
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer, 16 | 18)
+    }
+}
+
+/// The `num_units_in_tick` and `time_scale` of a video
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    pub num_units_in_tick: u32,
+    pub time_scale: u32,
+}
+
+/// The video usability information of a sequence parameter set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VuiParameters {
+    /// The width and height of a pixel
+    pub sample_aspect_ratio: Option<(u16, u16)>,
+    /// `video_format`: 0 for component, 1 for PAL, 2 for NTSC, up to 5 for unspecified
+    pub video_format: Option<u8>,
+    pub full_range: bool,
+    pub color: Option<ColorDescription>,
+    pub timing: Option<Timing>,
+}
+
+impl VuiParameters {
+    /// The range to configure a codec with, limited unless `video_full_range_flag` is set
+    pub fn color_range(&self) -> ColorRange {
+        if self.full_range {
+            ColorRange::Full
+        } else {
+            ColorRange::Limited
+        }
+    }
+
+    /// Reads the start of the VUI, up to the chroma location, which H.264 and H.265 share
+    pub(crate) fn parse_common(reader: &mut BitReader) -> Result<Self, Error> {
+        let mut vui = Self::default();
+
+        if reader.read_bit()? {
+            const RATIOS: [(u16, u16); 17] = [
+                (0, 0),
+                (1, 1),
+                (12, 11),
+                (10, 11),
+                (16, 11),
+                (40, 33),
+                (24, 11),
+                (20, 11),
+                (32, 11),
+                (80, 33),
+                (18, 11),
+                (15, 11),
+                (64, 33),
+                (160, 99),
+                (4, 3),
+                (3, 2),
+                (2, 1),
+            ];
+
+            let idc = reader.read_bits(8)? as usize;
+            vui.sample_aspect_ratio = match idc {
+                255 => Some((reader.read_bits(16)? as u16, reader.read_bits(16)? as u16)),
+                1..=16 => Some(RATIOS[idc]),
+                _ => None,
+            };
+        }
+
+        // overscan_info_present_flag
+        if reader.read_bit()? {
+            reader.skip_bits(1)?;
+        }
+
+        if reader.read_bit()? {
+            vui.video_format = Some(reader.read_bits(3)? as u8);
+            vui.full_range = reader.read_bit()?;
+            if reader.read_bit()? {
+                vui.color = Some(ColorDescription {
+                    primaries: reader.read_bits(8)? as u8,
+                    transfer: reader.read_bits(8)? as u8,
+                    matrix: reader.read_bits(8)? as u8,
+                });
+            }
+        }
+
+        // chroma_loc_info_present_flag
+        if reader.read_bit()? {
+            reader.read_ue()?;
+            reader.read_ue()?;
+        }
+
+        Ok(vui)
+    }
+}
+
+/// Reads the `csd-*` buffers of a track format, in order
+pub(crate) fn format_csd<'a>(
+    format: &'a MediaFormat,
+    operation: &'static str,
+) -> Result<Vec<&'a [u8]>, Error> {
+    let buffers = [keys::CSD_0, keys::CSD_1, keys::CSD_2]
+        .iter()
+        .map_while(|key| format.get_buffer(key.name()))
+        .collect::<Vec<_>>();
+    if buffers.is_empty() {
+        return Err(Error::new(MediaStatus::ErrorMalformed, operation).with_detail("missing csd-0"));
+    }
+
+    Ok(buffers)
+}
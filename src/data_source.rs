@@ -0,0 +1,210 @@
+use std::os::raw::c_void;
+
+#[cfg(feature = "api28")]
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::Mutex,
+};
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct AMediaDataSource {
+    _data: [u8; 0],
+    _marker: core::marker::PhantomData<(*mut u8, core::marker::PhantomPinned)>,
+}
+
+/// Reads `size` bytes at `offset` into `buffer`. Returns the number of bytes read, 0 at the end of the data, or -1 on errors
+pub(crate) type AMediaDataSourceReadAt = unsafe extern "C" fn(
+    userdata: *mut c_void,
+    offset: i64,
+    buffer: *mut c_void,
+    size: usize,
+) -> isize;
+
+/// Returns the size of the data, or -1 when unknown
+pub(crate) type AMediaDataSourceGetSize = unsafe extern "C" fn(userdata: *mut c_void) -> isize;
+
+/// Called when the data source is no longer needed
+pub(crate) type AMediaDataSourceClose = unsafe extern "C" fn(userdata: *mut c_void);
+
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
+#[allow(dead_code)]
+extern "C" {
+    /// Create a new media data source. The callbacks have to be set before it can be used
+    ///
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaDataSource_new() -> *mut AMediaDataSource;
+
+    /// Delete a media data source
+    ///
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaDataSource_delete(source: *mut AMediaDataSource);
+
+    /// Set the userdata handed to every callback
+    ///
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaDataSource_setUserdata(source: *mut AMediaDataSource, userdata: *mut c_void);
+
+    /// Set the callback reading the data
+    ///
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaDataSource_setReadAt(source: *mut AMediaDataSource, read_at: AMediaDataSourceReadAt);
+
+    /// Set the callback returning the size of the data
+    ///
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaDataSource_setGetSize(
+        source: *mut AMediaDataSource,
+        get_size: AMediaDataSourceGetSize,
+    );
+
+    /// Set the callback invoked when the data source gets closed
+    ///
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaDataSource_setClose(source: *mut AMediaDataSource, close: AMediaDataSourceClose);
+}
+
+/// Media data that lives somewhere else than a file or URL, like in memory or inside an archive.
+///
+/// Open it with [MediaExtractor::from_data_source](crate::MediaExtractor::from_data_source).
+/// Any `Read + Seek` can be used directly with [MediaExtractor::from_reader](crate::MediaExtractor::from_reader).
+///
+/// Every method is called on a thread owned by the NDK
+#[cfg(feature = "api28")]
+pub trait DataSource: Send {
+    /// Reads data at `offset` into `buffer`, returning the number of bytes read. Returns 0 past the end of the data
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> io::Result<usize>;
+
+    /// The size of the data, if known
+    fn size(&mut self) -> Option<u64>;
+
+    /// Called once the extractor is done with the data
+    fn close(&mut self) {}
+}
+
+/// Adapts a `Read + Seek` to a [DataSource](DataSource)
+#[cfg(feature = "api28")]
+pub(crate) struct ReaderSource<R>(pub(crate) R);
+
+#[cfg(feature = "api28")]
+impl<R: Read + Seek + Send> DataSource for ReaderSource<R> {
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> io::Result<usize> {
+        self.0.seek(SeekFrom::Start(offset))?;
+
+        // Fill as much of the buffer as possible, as short reads don't mean the data ended
+        let mut count = 0;
+        while count < buffer.len() {
+            match self.0.read(&mut buffer[count..]) {
+                Ok(0) => break,
+                Ok(read) => count += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(count)
+    }
+
+    fn size(&mut self) -> Option<u64> {
+        self.0.seek(SeekFrom::End(0)).ok()
+    }
+}
+
+/// An `AMediaDataSource` forwarding its callbacks to a [DataSource](DataSource).
+///
+/// Has to outlive the extractor using it
+#[cfg(feature = "api28")]
+pub(crate) struct CustomDataSource {
+    pub(crate) inner: *mut AMediaDataSource,
+    _source: Box<Mutex<Box<dyn DataSource>>>,
+}
+
+#[cfg(feature = "api28")]
+impl CustomDataSource {
+    pub(crate) fn new(source: Box<dyn DataSource>) -> Option<Self> {
+        let inner = unsafe { AMediaDataSource_new() };
+        if inner.is_null() {
+            return None;
+        }
+
+        let source = Box::new(Mutex::new(source));
+        unsafe {
+            AMediaDataSource_setUserdata(inner, &*source as *const _ as *mut c_void);
+            AMediaDataSource_setReadAt(inner, read_at);
+            AMediaDataSource_setGetSize(inner, get_size);
+            AMediaDataSource_setClose(inner, close);
+        }
+
+        Some(Self {
+            inner,
+            _source: source,
+        })
+    }
+}
+
+#[cfg(feature = "api28")]
+impl std::fmt::Debug for CustomDataSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomDataSource")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "api28")]
+impl Drop for CustomDataSource {
+    fn drop(&mut self) {
+        unsafe { AMediaDataSource_delete(self.inner) };
+    }
+}
+
+#[cfg(feature = "api28")]
+unsafe fn source<'a>(userdata: *mut c_void) -> std::sync::MutexGuard<'a, Box<dyn DataSource>> {
+    let source = &*(userdata as *const Mutex<Box<dyn DataSource>>);
+    source.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(feature = "api28")]
+unsafe extern "C" fn read_at(
+    userdata: *mut c_void,
+    offset: i64,
+    buffer: *mut c_void,
+    size: usize,
+) -> isize {
+    if offset < 0 || buffer.is_null() {
+        return -1;
+    }
+
+    let buffer = std::slice::from_raw_parts_mut(buffer as *mut u8, size);
+    match source(userdata).read_at(offset as u64, buffer) {
+        Ok(count) => count as isize,
+        Err(err) => {
+            log::debug!("Failed to read the data source: {err}");
+            -1
+        }
+    }
+}
+
+#[cfg(feature = "api28")]
+unsafe extern "C" fn get_size(userdata: *mut c_void) -> isize {
+    match source(userdata).size() {
+        Some(size) => size.try_into().unwrap_or(-1),
+        None => -1,
+    }
+}
+
+#[cfg(feature = "api28")]
+unsafe extern "C" fn close(userdata: *mut c_void) {
+    source(userdata).close();
+}
+
+#[cfg(feature = "api28")]
+unsafe impl Send for CustomDataSource {}
+#[cfg(feature = "api28")]
+unsafe impl Sync for CustomDataSource {}
@@ -0,0 +1,655 @@
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    ptr::null,
+    sync::{Arc, Mutex},
+};
+
+use log::debug;
+
+use crate::{Error, MediaStatus};
+
+/// The UUID of the ClearKey DRM scheme
+pub const CLEARKEY_UUID: [u8; 16] = [
+    0xe2, 0x71, 0x9d, 0x58, 0xa9, 0x85, 0xb3, 0xc9, 0x78, 0x1a, 0xb0, 0x30, 0xaf, 0x78, 0xd3, 0x0e,
+];
+
+/// The UUID of the Widevine DRM scheme
+pub const WIDEVINE_UUID: [u8; 16] = [
+    0xed, 0xef, 0x8b, 0xa9, 0x79, 0xd6, 0x4a, 0xce, 0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d, 0x21, 0xed,
+];
+
+#[repr(C)]
+#[derive(Debug)]
+pub(crate) struct AMediaDrm {
+    _data: [u8; 0],
+    _marker: core::marker::PhantomData<(*mut u8, core::marker::PhantomPinned)>,
+}
+
+/// A byte array owned by the NDK. Session ids, scopes and key set ids are all byte arrays
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AMediaDrmByteArray {
+    pub(crate) ptr: *const u8,
+    pub(crate) length: usize,
+}
+
+impl AMediaDrmByteArray {
+    fn empty() -> Self {
+        Self {
+            ptr: null(),
+            length: 0,
+        }
+    }
+
+    fn from_slice(data: &[u8]) -> Self {
+        Self {
+            ptr: data.as_ptr(),
+            length: data.len(),
+        }
+    }
+
+    unsafe fn to_vec(self) -> Vec<u8> {
+        if self.ptr.is_null() {
+            return vec![];
+        }
+
+        std::slice::from_raw_parts(self.ptr, self.length).to_vec()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AMediaDrmKeyValue {
+    pub(crate) key: *const c_char,
+    pub(crate) value: *const c_char,
+}
+
+pub(crate) type AMediaDrmEventListener = extern "C" fn(
+    drm: *mut AMediaDrm,
+    session_id: *const AMediaDrmByteArray,
+    event_type: i32,
+    extra: i32,
+    data: *const u8,
+    data_size: usize,
+);
+
+/// The kind of keys to request
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    /// Keys that only last as long as the session
+    Streaming = 1,
+    /// Keys that get stored on the device, for offline playback
+    Offline = 2,
+    /// Releases offline keys
+    Release = 3,
+}
+
+/// The kind of event a [MediaDrm](MediaDrm) reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrmEventType {
+    /// The device has to be provisioned before keys can be requested
+    ProvisionRequired,
+    /// The session needs keys
+    KeyRequired,
+    /// The session keys expired
+    KeyExpired,
+    /// An event defined by the DRM vendor
+    VendorDefined,
+    /// The session was reclaimed by the resource manager
+    SessionReclaimed,
+    Other(i32),
+}
+
+impl From<i32> for DrmEventType {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => Self::ProvisionRequired,
+            2 => Self::KeyRequired,
+            3 => Self::KeyExpired,
+            4 => Self::VendorDefined,
+            5 => Self::SessionReclaimed,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<DrmEventType> for i32 {
+    fn from(value: DrmEventType) -> Self {
+        match value {
+            DrmEventType::ProvisionRequired => 1,
+            DrmEventType::KeyRequired => 2,
+            DrmEventType::KeyExpired => 3,
+            DrmEventType::VendorDefined => 4,
+            DrmEventType::SessionReclaimed => 5,
+            DrmEventType::Other(value) => value,
+        }
+    }
+}
+
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
+#[allow(dead_code)]
+extern "C" {
+    /// Query if the given scheme identified by its UUID is supported on this device, and whether the drm plugin is able to handle the media container format specified by mimeType.
+    ///
+    /// Since: API 21
+    fn AMediaDrm_isCryptoSchemeSupported(uuid: *const u8, mime_type: *const c_char) -> bool;
+
+    /// Create a MediaDrm instance from a UUID
+    ///
+    /// Since: API 21
+    fn AMediaDrm_createByUUID(uuid: *const u8) -> *mut AMediaDrm;
+
+    /// Release a MediaDrm object
+    ///
+    /// Since: API 21
+    fn AMediaDrm_release(drm: *mut AMediaDrm);
+
+    /// Register a callback to be invoked when an event occurs
+    ///
+    /// Since: API 21
+    fn AMediaDrm_setOnEventListener(
+        drm: *mut AMediaDrm,
+        listener: AMediaDrmEventListener,
+    ) -> MediaStatus;
+
+    /// Open a new session with the MediaDrm object. The session id stays owned by the MediaDrm object
+    ///
+    /// Since: API 21
+    fn AMediaDrm_openSession(
+        drm: *mut AMediaDrm,
+        session_id: *mut AMediaDrmByteArray,
+    ) -> MediaStatus;
+
+    /// Close a session on the MediaDrm object that was previously opened with `AMediaDrm_openSession`
+    ///
+    /// Since: API 21
+    fn AMediaDrm_closeSession(
+        drm: *mut AMediaDrm,
+        session_id: *const AMediaDrmByteArray,
+    ) -> MediaStatus;
+
+    /// A key request/response exchange occurs between the app and a license server to obtain or release keys used to decrypt encrypted content.
+    ///
+    /// The request stays owned by the MediaDrm object, and is valid until the next call to this function
+    ///
+    /// Since: API 21
+    fn AMediaDrm_getKeyRequest(
+        drm: *mut AMediaDrm,
+        scope: *const AMediaDrmByteArray,
+        init: *const u8,
+        init_size: usize,
+        mime_type: *const c_char,
+        key_type: KeyType,
+        optional_parameters: *const AMediaDrmKeyValue,
+        num_optional_parameters: usize,
+        key_request: *mut *const u8,
+        key_request_size: *mut usize,
+    ) -> MediaStatus;
+
+    /// A key response is received from the license server by the app, then it is provided to the DRM engine plugin.
+    ///
+    /// Since: API 21
+    fn AMediaDrm_provideKeyResponse(
+        drm: *mut AMediaDrm,
+        scope: *const AMediaDrmByteArray,
+        response: *const u8,
+        response_size: usize,
+        key_set_id: *mut AMediaDrmByteArray,
+    ) -> MediaStatus;
+
+    /// Restore persisted offline keys into a new session
+    ///
+    /// Since: API 21
+    fn AMediaDrm_restoreKeys(
+        drm: *mut AMediaDrm,
+        session_id: *const AMediaDrmByteArray,
+        key_set_id: *const AMediaDrmByteArray,
+    ) -> MediaStatus;
+
+    /// Remove the current keys from a session
+    ///
+    /// Since: API 21
+    fn AMediaDrm_removeKeys(
+        drm: *mut AMediaDrm,
+        key_set_id: *const AMediaDrmByteArray,
+    ) -> MediaStatus;
+
+    /// A provision request/response exchange occurs between the app and a provisioning server to retrieve a device certificate.
+    ///
+    /// The request and server url stay owned by the MediaDrm object
+    ///
+    /// Since: API 21
+    fn AMediaDrm_getProvisionRequest(
+        drm: *mut AMediaDrm,
+        provision_request: *mut *const u8,
+        provision_request_size: *mut usize,
+        server_url: *mut *const c_char,
+    ) -> MediaStatus;
+
+    /// After a provision response is received by the app, it is provided to the DRM engine plugin
+    ///
+    /// Since: API 21
+    fn AMediaDrm_provideProvisionResponse(
+        drm: *mut AMediaDrm,
+        response: *const u8,
+        response_size: usize,
+    ) -> MediaStatus;
+
+    /// Read a DRM engine plugin String property value. The value stays owned by the MediaDrm object
+    ///
+    /// Since: API 21
+    fn AMediaDrm_getPropertyString(
+        drm: *mut AMediaDrm,
+        property_name: *const c_char,
+        property_value: *mut *const c_char,
+    ) -> MediaStatus;
+
+    /// Read a DRM engine plugin byte array property value. The value stays owned by the MediaDrm object
+    ///
+    /// Since: API 21
+    fn AMediaDrm_getPropertyByteArray(
+        drm: *mut AMediaDrm,
+        property_name: *const c_char,
+        property_value: *mut AMediaDrmByteArray,
+    ) -> MediaStatus;
+
+    /// Set a DRM engine plugin String property value
+    ///
+    /// Since: API 21
+    fn AMediaDrm_setPropertyString(
+        drm: *mut AMediaDrm,
+        property_name: *const c_char,
+        value: *const c_char,
+    ) -> MediaStatus;
+
+    /// Set a DRM engine plugin byte array property value
+    ///
+    /// Since: API 21
+    fn AMediaDrm_setPropertyByteArray(
+        drm: *mut AMediaDrm,
+        property_name: *const c_char,
+        value: *const u8,
+        value_size: usize,
+    ) -> MediaStatus;
+}
+
+/// An event reported by a [MediaDrm](MediaDrm)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrmEvent {
+    pub event_type: DrmEventType,
+    /// The session the event is about, if any
+    pub session: Option<DrmSession>,
+    pub extra: i32,
+    pub data: Vec<u8>,
+}
+
+type Listener = Arc<Mutex<Box<dyn FnMut(DrmEvent) + Send>>>;
+
+/// The event listeners, keyed by the `AMediaDrm` they listen to.
+///
+/// The NDK listener gets no userdata, so this is how an event finds its way back to its closure
+static LISTENERS: Mutex<Vec<(usize, Listener)>> = Mutex::new(Vec::new());
+
+fn listener(drm: *mut AMediaDrm) -> Option<Listener> {
+    let listeners = LISTENERS.lock().unwrap_or_else(|err| err.into_inner());
+    listeners
+        .iter()
+        .find(|(key, _)| *key == drm as usize)
+        .map(|(_, listener)| listener.clone())
+}
+
+fn remove_listener(drm: *mut AMediaDrm) {
+    let mut listeners = LISTENERS.lock().unwrap_or_else(|err| err.into_inner());
+    listeners.retain(|(key, _)| *key != drm as usize);
+}
+
+extern "C" fn on_event(
+    drm: *mut AMediaDrm,
+    session_id: *const AMediaDrmByteArray,
+    event_type: i32,
+    extra: i32,
+    data: *const u8,
+    data_size: usize,
+) {
+    // Looked up first, so the listener is free to replace itself
+    let Some(listener) = listener(drm) else {
+        return;
+    };
+
+    let event = unsafe {
+        DrmEvent {
+            event_type: DrmEventType::from(event_type),
+            session: session_id
+                .as_ref()
+                .map(|session| DrmSession(session.to_vec())),
+            extra,
+            data: AMediaDrmByteArray {
+                ptr: data,
+                length: data_size,
+            }
+            .to_vec(),
+        }
+    };
+
+    let mut listener = listener.lock().unwrap_or_else(|err| err.into_inner());
+    listener(event);
+}
+
+/// The id of a session opened on a [MediaDrm](MediaDrm)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DrmSession(Vec<u8>);
+
+impl DrmSession {
+    /// The raw session id
+    pub fn id(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn as_byte_array(&self) -> AMediaDrmByteArray {
+        AMediaDrmByteArray::from_slice(&self.0)
+    }
+}
+
+/// A request to hand over to a provisioning server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvisionRequest {
+    pub data: Vec<u8>,
+    pub server_url: String,
+}
+
+/// The Type-Safe wrapper for `AMediaDrm`.
+///
+/// Obtains the keys needed to decrypt protected media, which a [MediaCrypto](crate::MediaCrypto) then hands to the codec
+#[derive(Debug)]
+pub struct MediaDrm {
+    inner: *mut AMediaDrm,
+}
+
+impl MediaDrm {
+    /// Returns true if the DRM scheme identified by `uuid` is supported on this device.
+    ///
+    /// With a mime type, this also checks whether the scheme can handle that container format
+    pub fn is_scheme_supported(uuid: &[u8; 16], mime_type: Option<&str>) -> bool {
+        let Ok(mime_type) = mime_type.map(CString::new).transpose() else {
+            return false;
+        };
+
+        unsafe {
+            AMediaDrm_isCryptoSchemeSupported(
+                uuid.as_ptr(),
+                mime_type.as_ref().map_or(null(), |mime| mime.as_ptr()),
+            )
+        }
+    }
+
+    /// Creates a MediaDrm for the DRM scheme identified by `uuid`
+    pub fn new(uuid: &[u8; 16]) -> Result<Self, Error> {
+        let inner = unsafe { AMediaDrm_createByUUID(uuid.as_ptr()) };
+
+        if inner.is_null() {
+            return Err(Error::new(MediaStatus::ErrorUnsupported, "create drm"));
+        }
+
+        Ok(Self { inner })
+    }
+
+    /// Calls `listener` for every event this MediaDrm reports. Replaces the previous listener.
+    ///
+    /// The listener gets called on a thread owned by the NDK
+    pub fn set_event_listener(
+        &mut self,
+        listener: impl FnMut(DrmEvent) + Send + 'static,
+    ) -> Result<(), Error> {
+        remove_listener(self.inner);
+        LISTENERS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push((
+                self.inner as usize,
+                Arc::new(Mutex::new(Box::new(listener))),
+            ));
+
+        let result = unsafe { AMediaDrm_setOnEventListener(self.inner, on_event) }.result();
+        if result.is_err() {
+            remove_listener(self.inner);
+        }
+
+        result
+            .map(|_| ())
+            .map_err(|status| Error::new(status, "set drm event listener"))
+    }
+
+    /// Opens a new session
+    pub fn open_session(&mut self) -> Result<DrmSession, Error> {
+        let mut session = AMediaDrmByteArray::empty();
+        unsafe {
+            AMediaDrm_openSession(self.inner, &mut session)
+                .result()
+                .map_err(|status| Error::new(status, "open drm session"))?;
+            Ok(DrmSession(session.to_vec()))
+        }
+    }
+
+    /// Closes a session opened with [open_session](MediaDrm::open_session)
+    pub fn close_session(&mut self, session: &DrmSession) -> Result<(), Error> {
+        unsafe { AMediaDrm_closeSession(self.inner, &session.as_byte_array()) }
+            .result()
+            .map(|_| ())
+            .map_err(|status| Error::new(status, "close drm session"))
+    }
+
+    /// Builds the request to send to the license server, to get the keys for `session`.
+    ///
+    /// `init_data` is the scheme specific data from the content (like the PSSH box), and `parameters` are optional
+    /// key/value pairs for the license server
+    pub fn key_request(
+        &mut self,
+        session: &DrmSession,
+        init_data: &[u8],
+        mime_type: &str,
+        key_type: KeyType,
+        parameters: &[(&str, &str)],
+    ) -> Result<Vec<u8>, Error> {
+        let error = |status| Error::new(status, "get drm key request").with_mime_type(mime_type);
+
+        let mime =
+            CString::new(mime_type).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+        let parameters = parameters
+            .iter()
+            .map(|(key, value)| Ok((CString::new(*key)?, CString::new(*value)?)))
+            .collect::<Result<Vec<_>, std::ffi::NulError>>()
+            .map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+        let key_values: Vec<AMediaDrmKeyValue> = parameters
+            .iter()
+            .map(|(key, value)| AMediaDrmKeyValue {
+                key: key.as_ptr(),
+                value: value.as_ptr(),
+            })
+            .collect();
+
+        let mut request = null();
+        let mut request_size = 0;
+        unsafe {
+            AMediaDrm_getKeyRequest(
+                self.inner,
+                &session.as_byte_array(),
+                init_data.as_ptr(),
+                init_data.len(),
+                mime.as_ptr(),
+                key_type,
+                key_values.as_ptr(),
+                key_values.len(),
+                &mut request,
+                &mut request_size,
+            )
+            .result()
+            .map_err(error)?;
+
+            Ok(AMediaDrmByteArray {
+                ptr: request,
+                length: request_size,
+            }
+            .to_vec())
+        }
+    }
+
+    /// Hands the license server response over to the DRM engine.
+    ///
+    /// Returns the key set id of offline keys, which is empty for streaming keys
+    pub fn provide_key_response(
+        &mut self,
+        session: &DrmSession,
+        response: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut key_set_id = AMediaDrmByteArray::empty();
+        unsafe {
+            AMediaDrm_provideKeyResponse(
+                self.inner,
+                &session.as_byte_array(),
+                response.as_ptr(),
+                response.len(),
+                &mut key_set_id,
+            )
+            .result()
+            .map_err(|status| Error::new(status, "provide drm key response"))?;
+
+            Ok(key_set_id.to_vec())
+        }
+    }
+
+    /// Restores offline keys, stored under `key_set_id`, into `session`
+    pub fn restore_keys(&mut self, session: &DrmSession, key_set_id: &[u8]) -> Result<(), Error> {
+        unsafe {
+            AMediaDrm_restoreKeys(
+                self.inner,
+                &session.as_byte_array(),
+                &AMediaDrmByteArray::from_slice(key_set_id),
+            )
+        }
+        .result()
+        .map(|_| ())
+        .map_err(|status| Error::new(status, "restore drm keys"))
+    }
+
+    /// Removes the keys of `session`
+    pub fn remove_keys(&mut self, session: &DrmSession) -> Result<(), Error> {
+        unsafe { AMediaDrm_removeKeys(self.inner, &session.as_byte_array()) }
+            .result()
+            .map(|_| ())
+            .map_err(|status| Error::new(status, "remove drm keys"))
+    }
+
+    /// Builds the request to send to the provisioning server, to get a device certificate
+    pub fn provision_request(&mut self) -> Result<ProvisionRequest, Error> {
+        let mut request = null();
+        let mut request_size = 0;
+        let mut server_url = null();
+
+        unsafe {
+            AMediaDrm_getProvisionRequest(
+                self.inner,
+                &mut request,
+                &mut request_size,
+                &mut server_url,
+            )
+            .result()
+            .map_err(|status| Error::new(status, "get drm provision request"))?;
+
+            Ok(ProvisionRequest {
+                data: AMediaDrmByteArray {
+                    ptr: request,
+                    length: request_size,
+                }
+                .to_vec(),
+                server_url: if server_url.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(server_url).to_string_lossy().to_string()
+                },
+            })
+        }
+    }
+
+    /// Hands the provisioning server response over to the DRM engine
+    pub fn provide_provision_response(&mut self, response: &[u8]) -> Result<(), Error> {
+        unsafe { AMediaDrm_provideProvisionResponse(self.inner, response.as_ptr(), response.len()) }
+            .result()
+            .map(|_| ())
+            .map_err(|status| Error::new(status, "provide drm provision response"))
+    }
+
+    /// Reads a string property of the DRM engine, like `"vendor"`, `"version"` or `"securityLevel"`
+    pub fn property_string(&self, name: &str) -> Result<String, Error> {
+        let error = |status| Error::new(status, "get drm property").with_detail(name);
+
+        let name = CString::new(name).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+        let mut value = null();
+
+        unsafe {
+            AMediaDrm_getPropertyString(self.inner, name.as_ptr(), &mut value)
+                .result()
+                .map_err(error)?;
+
+            if value.is_null() {
+                debug!("The DRM engine returned no value");
+                return Err(error(MediaStatus::ErrorUnknown));
+            }
+
+            Ok(CStr::from_ptr(value).to_string_lossy().to_string())
+        }
+    }
+
+    /// Reads a byte array property of the DRM engine, like `"deviceUniqueId"`
+    pub fn property_bytes(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let error = |status| Error::new(status, "get drm property").with_detail(name);
+
+        let name = CString::new(name).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+        let mut value = AMediaDrmByteArray::empty();
+
+        unsafe {
+            AMediaDrm_getPropertyByteArray(self.inner, name.as_ptr(), &mut value)
+                .result()
+                .map_err(error)?;
+            Ok(value.to_vec())
+        }
+    }
+
+    /// Sets a string property of the DRM engine
+    pub fn set_property_string(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let error = |status| Error::new(status, "set drm property").with_detail(name);
+
+        let name = CString::new(name).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+        let value = CString::new(value).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+
+        unsafe { AMediaDrm_setPropertyString(self.inner, name.as_ptr(), value.as_ptr()) }
+            .result()
+            .map(|_| ())
+            .map_err(error)
+    }
+
+    /// Sets a byte array property of the DRM engine
+    pub fn set_property_bytes(&mut self, name: &str, value: &[u8]) -> Result<(), Error> {
+        let error = |status| Error::new(status, "set drm property").with_detail(name);
+
+        let name = CString::new(name).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
+
+        unsafe {
+            AMediaDrm_setPropertyByteArray(self.inner, name.as_ptr(), value.as_ptr(), value.len())
+        }
+        .result()
+        .map(|_| ())
+        .map_err(error)
+    }
+}
+
+unsafe impl Send for MediaDrm {}
+unsafe impl Sync for MediaDrm {}
+
+impl Drop for MediaDrm {
+    fn drop(&mut self) {
+        unsafe { AMediaDrm_release(self.inner) };
+        remove_listener(self.inner);
+    }
+}
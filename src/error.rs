@@ -1,3 +1,7 @@
+use std::fmt::{Display, Formatter};
+
+use crate::CodecState;
+
 #[repr(C)]
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum MediaStatus {
@@ -88,17 +92,17 @@ impl MediaStatus {
         Err(*self)
     }
 
-    fn is_ok(&self) -> bool {
+    pub fn is_ok(&self) -> bool {
         let mut valuez = Self::values();
         // Remove the Ok. Now, the rest are errors
         valuez.remove(0);
 
         // If we get none, there were no errors
-        return valuez.iter().find(|&&x| *self == x).is_none();
+        valuez.iter().find(|&&x| *self == x).is_none()
     }
 
-    fn is_err(&self) -> bool {
-        return !self.is_ok();
+    pub fn is_err(&self) -> bool {
+        !self.is_ok()
     }
 }
 
@@ -112,6 +116,203 @@ impl TryFrom<isize> for MediaStatus {
             }
         }
 
-        return Err("Not Found");
+        Err("Not Found")
+    }
+}
+
+impl Display for MediaStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::MediaStatus::*;
+        let message = match self {
+            Ok => "no error",
+            ErrorInsufficientResource => "insufficient resources",
+            ErrorReclaimed => "resources were reclaimed",
+            ErrorUnknown => "unknown error",
+            ErrorMalformed => "malformed data",
+            ErrorUnsupported => "unsupported",
+            ErrorInvalidObject => "invalid object",
+            ErrorInvalidParameter => "invalid parameter",
+            ErrorInvalidOperation => "invalid operation",
+            ErrorEndOfStream => "end of stream",
+            ErrorIO => "I/O error",
+            ErrorWouldBlock => "operation would block",
+            DRMErrorBase => "DRM error",
+            DRMNotProvisioned => "DRM not provisioned",
+            DRMResourceBusy => "DRM resource busy",
+            DRMDeviceRevoked => "DRM device revoked",
+            DRMShortBuffer => "DRM buffer too short",
+            DRMSessionNotOpened => "DRM session not opened",
+            DRMTamperDetected => "DRM tampering detected",
+            DRMVerifyFailed => "DRM verification failed",
+            DRMNeedKey => "DRM key needed",
+            DRMLicenseExpired => "DRM license expired",
+            ImgReaderErrorBase => "image reader error",
+            ImgReaderNoBufferAvailable => "no image buffer available",
+            ImgReaderMaxImagesAcquired => "maximum number of images acquired",
+            ImgReaderCannotLockImage => "cannot lock image",
+            ImgReaderCannotUnlockImage => "cannot unlock image",
+            ImgReaderImageNotLocked => "image not locked",
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+impl std::error::Error for MediaStatus {}
+
+/// `ACTION_CODE_TRANSIENT`, used to classify action codes before API 28
+#[cfg(not(feature = "api28"))]
+const ACTION_CODE_TRANSIENT: i32 = 1;
+
+/// `ACTION_CODE_RECOVERABLE`, used to classify action codes before API 28
+#[cfg(not(feature = "api28"))]
+const ACTION_CODE_RECOVERABLE: i32 = 2;
+
+/// An error from the NDK, along with what we were doing when it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    status: MediaStatus,
+    operation: &'static str,
+    mime_type: Option<String>,
+    track_index: Option<usize>,
+    action_code: Option<i32>,
+    codec_state: Option<CodecState>,
+    detail: Option<String>,
+}
+
+impl Error {
+    /// Creates an error for `status`, returned while doing `operation`
+    pub fn new(status: MediaStatus, operation: &'static str) -> Self {
+        Self {
+            status,
+            operation,
+            mime_type: None,
+            track_index: None,
+            action_code: None,
+            codec_state: None,
+            detail: None,
+        }
+    }
+
+    /// Records the mime type of the codec or track involved
+    pub fn with_mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    /// Records the index of the track involved
+    pub fn with_track_index(mut self, index: usize) -> Self {
+        self.track_index = Some(index);
+        self
+    }
+
+    /// Records the action code a codec reported along with the error
+    pub fn with_action_code(mut self, action_code: i32) -> Self {
+        self.action_code = Some(action_code);
+        self
+    }
+
+    /// Records the state the codec was in, when the operation isn't allowed in that state
+    pub fn with_codec_state(mut self, state: CodecState) -> Self {
+        self.codec_state = Some(state);
+        self
+    }
+
+    /// Records the description a codec gave of the error
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    /// The status returned by the NDK
+    pub fn status(&self) -> MediaStatus {
+        self.status
+    }
+
+    /// What we were doing when the error happened
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    /// The mime type of the codec or track involved
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+
+    /// The index of the track involved
+    pub fn track_index(&self) -> Option<usize> {
+        self.track_index
+    }
+
+    /// The action code the codec reported
+    pub fn action_code(&self) -> Option<i32> {
+        self.action_code
+    }
+
+    /// The state the codec was in, if the operation failed because of it
+    pub fn codec_state(&self) -> Option<CodecState> {
+        self.codec_state
+    }
+
+    /// The description the codec gave of the error
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// Returns true if the codec can keep going after being stopped, configured and started again
+    pub fn is_recoverable(&self) -> bool {
+        let Some(action_code) = self.action_code else {
+            return false;
+        };
+
+        #[cfg(feature = "api28")]
+        return unsafe { crate::AMediaCodecActionCode_isRecoverable(action_code) };
+        #[cfg(not(feature = "api28"))]
+        return action_code == ACTION_CODE_RECOVERABLE;
+    }
+
+    /// Returns true if the operation can simply be retried later
+    pub fn is_transient(&self) -> bool {
+        let Some(action_code) = self.action_code else {
+            return false;
+        };
+
+        #[cfg(feature = "api28")]
+        return unsafe { crate::AMediaCodecActionCode_isTransient(action_code) };
+        #[cfg(not(feature = "api28"))]
+        return action_code == ACTION_CODE_TRANSIENT;
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed: {}", self.operation, self.status)?;
+
+        if let Some(ref mime_type) = self.mime_type {
+            write!(f, " (mime type {mime_type})")?;
+        }
+
+        if let Some(index) = self.track_index {
+            write!(f, " (track {index})")?;
+        }
+
+        if let Some(state) = self.codec_state {
+            write!(f, " (codec is {state:?})")?;
+        }
+
+        if let Some(ref detail) = self.detail {
+            write!(f, ": {detail}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The status is already part of the message, so it isn't reported again as the source
+impl std::error::Error for Error {}
+
+impl From<Error> for MediaStatus {
+    fn from(error: Error) -> Self {
+        error.status
     }
 }
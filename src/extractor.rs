@@ -1,21 +1,41 @@
 use std::{
-    ffi::{CStr, CString},
-    marker::PhantomData,
-    os::raw::c_char,
+    ffi::CString,
+    os::raw::{c_char, c_void},
 };
 
 use log::{debug, info};
 
-use crate::{AMediaFormat, CodecInputBuffer, MediaFormat, MediaStatus};
+use crate::{
+    codec_flags, AMediaCodecCryptoInfo, AMediaCodecCryptoInfo_delete, AMediaFormat, BufferFlag,
+    CryptoInfo, Error, InputBuffer, MediaFormat, MediaStatus, Packet, Packets, SampleFlags,
+};
+
+/// One entry of `PsshInfo`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PsshEntry {
+    pub(crate) uuid: [u8; 16],
+    pub(crate) datalen: usize,
+    pub(crate) data: *mut c_void,
+}
+
+/// The PSSH boxes of the media. The entries follow the count
+#[repr(C)]
+#[derive(Debug)]
+pub(crate) struct PsshInfo {
+    pub(crate) numentries: usize,
+    pub(crate) entries: [PsshEntry; 0],
+}
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
-struct AMediaExtractor {
+pub(crate) struct AMediaExtractor {
     _data: [u8; 0],
     _marker: core::marker::PhantomData<(*mut u8, core::marker::PhantomPinned)>,
 }
 
-#[link(name = "mediandk")]
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
+#[allow(dead_code)]
 extern "C" {
     /// Since: API 21
     fn AMediaExtractor_new() -> *mut AMediaExtractor;
@@ -37,6 +57,13 @@ extern "C" {
         location: *const c_char,
     ) -> isize;
 
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaExtractor_setDataSourceCustom(
+        extractor: *mut AMediaExtractor,
+        source: *mut crate::AMediaDataSource,
+    ) -> MediaStatus;
+
     /// Since: API 21
     fn AMediaExtractor_getTrackCount(extractor: *mut AMediaExtractor) -> usize;
 
@@ -59,6 +86,10 @@ extern "C" {
         capacity: usize,
     ) -> isize;
 
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaExtractor_getSampleSize(extractor: *mut AMediaExtractor) -> isize;
+
     /// Since: API 21
     fn AMediaExtractor_getSampleFlags(extractor: *mut AMediaExtractor) -> u32;
 
@@ -70,13 +101,64 @@ extern "C" {
 
     /// Since: API 21
     fn AMediaExtractor_advance(extractor: *mut AMediaExtractor) -> bool;
+
+    /// Since: API 21
+    fn AMediaExtractor_seekTo(
+        extractor: *mut AMediaExtractor,
+        seek_pos_us: i64,
+        mode: SeekMode,
+    ) -> MediaStatus;
+
+    /// Since: API 21
+    fn AMediaExtractor_getSampleCryptoInfo(
+        extractor: *mut AMediaExtractor,
+    ) -> *mut AMediaCodecCryptoInfo;
+
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaExtractor_getCachedDuration(extractor: *mut AMediaExtractor) -> i64;
+
+    /// Since: API 21
+    fn AMediaExtractor_getPsshInfo(extractor: *mut AMediaExtractor) -> *mut PsshInfo;
+
+    /// Since: API 28
+    #[cfg(feature = "api28")]
+    fn AMediaExtractor_getFileFormat(extractor: *mut AMediaExtractor) -> *mut AMediaFormat;
+}
+
+/// The DRM init data of one scheme, read from a PSSH box by [pssh_info](MediaExtractor::pssh_info)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrmInitData {
+    /// The UUID of the DRM scheme, like [WIDEVINE_UUID](crate::WIDEVINE_UUID)
+    pub uuid: [u8; 16],
+    /// The init data, to pass to [MediaDrm::key_request](crate::MediaDrm::key_request)
+    pub data: Vec<u8>,
 }
 
+/// Where [seek_to](MediaExtractor::seek_to) lands, relative to the requested time
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekMode {
+    /// The last sync sample at or before the time
+    PreviousSync = 0,
+    /// The first sync sample at or after the time
+    NextSync = 1,
+    /// The sync sample closest to the time
+    ClosestSync = 2,
+}
+
+/// The biggest packet [read_packet](MediaExtractor::read_packet) looks for, when the sample size can't be queried
+#[cfg(not(feature = "api28"))]
+const MAX_PACKET_SIZE: usize = 64 * 1024 * 1024;
+
 /// MediaExtractor is a demuxer that opens a file or resource and demuxes the data to hand over to MediaCodec
 #[derive(Debug)]
 pub struct MediaExtractor {
     inner: *mut AMediaExtractor,
     has_next: bool,
+    /// Dropped after the extractor, which reads from it until it gets deleted
+    #[cfg(feature = "api28")]
+    source: Option<crate::CustomDataSource>,
 }
 
 impl MediaExtractor {
@@ -85,18 +167,22 @@ impl MediaExtractor {
         Self {
             inner: unsafe { AMediaExtractor_new() },
             has_next: false,
+            #[cfg(feature = "api28")]
+            source: None,
         }
     }
 
     /// Creates a MediaExtractor with data source set to a specific URL
-    pub fn from_url(path: &str) -> Result<Self, MediaStatus> {
+    pub fn from_url(path: &str) -> Result<Self, Error> {
+        let error = |status| Error::new(status, "set data source").with_detail(path);
+
         unsafe {
             let mut me = Self::new();
 
-            let path = CString::new(path).unwrap();
+            let path = CString::new(path).map_err(|_| error(MediaStatus::ErrorInvalidParameter))?;
 
             let result = AMediaExtractor_setDataSource(me.inner, path.as_ptr());
-            MediaStatus::make_result(result)?;
+            MediaStatus::make_result(result).map_err(error)?;
 
             me.has_next = true;
 
@@ -104,6 +190,57 @@ impl MediaExtractor {
         }
     }
 
+    /// Creates a MediaExtractor reading `length` bytes of the file descriptor `fd`, starting at `offset`.
+    ///
+    /// The extractor duplicates the descriptor, so `fd` can be closed afterwards
+    pub fn from_fd(fd: i32, offset: u64, length: u64) -> Result<Self, Error> {
+        let mut me = Self::new();
+
+        let result = unsafe { AMediaExtractor_setDataSourceFd(me.inner, fd, offset, length) };
+        MediaStatus::make_result(result).map_err(|status| {
+            Error::new(status, "set data source").with_detail(&format!("fd {fd}"))
+        })?;
+
+        me.has_next = true;
+        Ok(me)
+    }
+
+    /// Creates a MediaExtractor reading `length` bytes of `file`, starting at `offset`.
+    ///
+    /// Useful for media stored uncompressed inside a bigger file, like an asset or an archive
+    #[cfg(unix)]
+    pub fn from_file(file: &std::fs::File, offset: u64, length: u64) -> Result<Self, Error> {
+        use std::os::unix::io::AsRawFd;
+
+        Self::from_fd(file.as_raw_fd(), offset, length)
+    }
+
+    /// Creates a MediaExtractor reading from any `Read + Seek`, like an in-memory `Cursor`
+    #[cfg(feature = "api28")]
+    pub fn from_reader(
+        reader: impl std::io::Read + std::io::Seek + Send + 'static,
+    ) -> Result<Self, Error> {
+        Self::from_data_source(crate::ReaderSource(reader))
+    }
+
+    /// Creates a MediaExtractor reading from a custom [DataSource](crate::DataSource)
+    #[cfg(feature = "api28")]
+    pub fn from_data_source(source: impl crate::DataSource + 'static) -> Result<Self, Error> {
+        let error = |status| Error::new(status, "set data source");
+
+        let mut me = Self::new();
+        let source = crate::CustomDataSource::new(Box::new(source))
+            .ok_or_else(|| error(MediaStatus::ErrorInsufficientResource))?;
+
+        unsafe { AMediaExtractor_setDataSourceCustom(me.inner, source.inner) }
+            .result()
+            .map_err(error)?;
+
+        me.source = Some(source);
+        me.has_next = true;
+        Ok(me)
+    }
+
     /// Returns the number of tracks found by MediaExtractor
     pub fn track_count(&self) -> usize {
         unsafe { AMediaExtractor_getTrackCount(self.inner) }
@@ -129,23 +266,105 @@ impl MediaExtractor {
         }
     }
 
-    /// Select this track to be demuxed by MediaExtractor
-    pub fn select_track(&mut self, index: usize) {
+    /// Returns the MediaFormat describing the whole container, holding metadata like the duration or the title
+    #[cfg(feature = "api28")]
+    pub fn file_format(&self) -> Option<MediaFormat> {
+        let format = unsafe { AMediaExtractor_getFileFormat(self.inner) };
+        if format.is_null() {
+            debug!("No file format available");
+            return None;
+        }
+
+        Some(MediaFormat::from_raw(format))
+    }
+
+    /// Returns the container metadata, with typed accessors for the common keys
+    #[cfg(feature = "api28")]
+    pub fn metadata(&self) -> Option<crate::FileMetadata> {
+        let format = self.file_format()?;
+        let rotation = (0..self.track_count())
+            .filter_map(|index| self.track_format(index))
+            .filter(|format| format.is_video())
+            .find_map(|format| format.get(crate::keys::ROTATION));
+
+        Some(crate::FileMetadata::new(format).with_track_rotation(rotation))
+    }
+
+    /// Returns the DRM init data of every scheme the media is protected with. Empty for clear content
+    pub fn pssh_info(&self) -> Vec<DrmInitData> {
         unsafe {
-            AMediaExtractor_selectTrack(self.inner, index);
+            // Owned by the extractor
+            let info = AMediaExtractor_getPsshInfo(self.inner);
+            if info.is_null() {
+                return vec![];
+            }
+
+            let entries = std::slice::from_raw_parts((*info).entries.as_ptr(), (*info).numentries);
+            entries
+                .iter()
+                .map(|entry| DrmInitData {
+                    uuid: entry.uuid,
+                    data: if entry.data.is_null() {
+                        vec![]
+                    } else {
+                        std::slice::from_raw_parts(entry.data as *const u8, entry.datalen).to_vec()
+                    },
+                })
+                .collect()
         }
     }
 
+    /// Select this track to be demuxed by MediaExtractor
+    pub fn select_track(&mut self, index: usize) -> Result<(), Error> {
+        let result = unsafe { AMediaExtractor_selectTrack(self.inner, index) };
+
+        MediaStatus::make_result(result)
+            .map(|_| ())
+            .map_err(|status| Error::new(status, "select track").with_track_index(index))
+    }
+
     /// Unselect this track to be demuxed by MediaExtractor
-    pub fn unselect_track(&mut self, index: usize) {
+    pub fn unselect_track(&mut self, index: usize) -> Result<(), Error> {
+        let result = unsafe { AMediaExtractor_unselectTrack(self.inner, index) };
+
+        MediaStatus::make_result(result)
+            .map(|_| ())
+            .map_err(|status| Error::new(status, "unselect track").with_track_index(index))
+    }
+
+    /// Returns the sample flags for the current packet to be returned
+    pub fn sample_flags(&self) -> SampleFlags {
+        SampleFlags::from_bits(unsafe { AMediaExtractor_getSampleFlags(self.inner) })
+    }
+
+    /// Returns how the current packet is encrypted, or None when it's in the clear.
+    ///
+    /// The pattern isn't exposed by the NDK, so `cbcs` content needs it set from the track format
+    pub fn sample_crypto_info(&self) -> Option<CryptoInfo> {
         unsafe {
-            AMediaExtractor_unselectTrack(self.inner, index);
+            let info = AMediaExtractor_getSampleCryptoInfo(self.inner);
+            if info.is_null() {
+                return None;
+            }
+
+            let result = CryptoInfo::from_ptr(info);
+            AMediaCodecCryptoInfo_delete(info);
+
+            result
+                .map_err(|status| debug!("Failed to read the sample crypto info: {status}"))
+                .ok()
         }
     }
 
-    /// Returns the sample flags for the current packet to be returned
-    pub fn sample_flags(&self) -> u32 {
-        unsafe { AMediaExtractor_getSampleFlags(self.inner) }
+    /// Returns how much media is buffered ahead of the current position, for network sources.
+    ///
+    /// None when the source isn't cached, like a local file
+    #[cfg(feature = "api28")]
+    pub fn cached_duration(&self) -> Option<std::time::Duration> {
+        let duration = unsafe { AMediaExtractor_getCachedDuration(self.inner) };
+        u64::try_from(duration)
+            .ok()
+            .map(std::time::Duration::from_micros)
     }
 
     /// Returns the time for the current packet to be returned
@@ -153,33 +372,176 @@ impl MediaExtractor {
         unsafe { AMediaExtractor_getSampleTime(self.inner) }
     }
 
-    /// Read a packet into `buffer` and advance the extractor
-    /// Returns true if there's still more data to read
-    pub fn read_next(&mut self, buffer: &mut CodecInputBuffer) -> bool {
-        unsafe {
-            if !self.has_next {
-                return false;
+    /// Reads the current packet into `buffer` and advances the extractor.
+    ///
+    /// Once every packet has been read, `buffer` gets flagged with the end of stream instead, so the codec can output
+    /// its last frames. Returns false when that happened, or an error when the packet couldn't be read, like when it
+    /// doesn't fit in `buffer`
+    pub fn read_next(&mut self, buffer: &mut impl InputBuffer) -> Result<bool, Error> {
+        let index = self.track_index();
+        if index >= 0 {
+            let (data, size) = buffer.buffer();
+            let count = unsafe { AMediaExtractor_readSampleData(self.inner, data, size) };
+
+            if count < 0 {
+                // The buffer still gets queued, so it mustn't hold a partial packet
+                buffer.set_write_size(0);
+                return Err(sample_error(count, index as usize, size));
             }
 
-            let count = AMediaExtractor_readSampleData(self.inner, buffer.buffer, buffer.size);
-            if count > 0 {
-                buffer.set_write_size(count as usize);
-                buffer.set_time(self.sample_time() as u64);
+            buffer.set_write_size(count as usize);
+            buffer.set_time(self.sample_time() as u64);
+            buffer.set_flags(codec_flags(self.sample_flags()));
+
+            unsafe { AMediaExtractor_advance(self.inner) };
+            self.has_next = true;
+            return Ok(true);
+        }
+
+        buffer.set_write_size(0);
+        buffer.set_flags(BufferFlag::EndOfStream as u32);
+        self.has_next = false;
+        Ok(false)
+    }
+
+    /// Returns the size of the current packet, or None when there's none
+    #[cfg(feature = "api28")]
+    pub fn sample_size(&self) -> Option<usize> {
+        let size = unsafe { AMediaExtractor_getSampleSize(self.inner) };
+        usize::try_from(size).ok()
+    }
+
+    /// Reads the current packet and advances the extractor.
+    ///
+    /// Returns None once every packet of the selected tracks has been read, or an error when the packet couldn't
+    /// be read. The extractor doesn't advance past a packet that failed to read
+    pub fn read_packet(&mut self) -> Result<Option<Packet<'static>>, Error> {
+        let mut data = vec![];
+        Ok(self.read_packet_into(&mut data)?.map(Packet::into_owned))
+    }
+
+    /// Reads the current packet into `data` and advances the extractor.
+    ///
+    /// The packet borrows `data`, so the same allocation can be reused for every packet
+    pub fn read_packet_into<'b>(
+        &mut self,
+        data: &'b mut Vec<u8>,
+    ) -> Result<Option<Packet<'b>>, Error> {
+        let Ok(track_index) = usize::try_from(self.track_index()) else {
+            return Ok(None);
+        };
+        let time = self.sample_time();
+        let flags = self.sample_flags();
+
+        self.read_sample(track_index, data)?;
+        self.advance();
+
+        Ok(Some(Packet::new(track_index, time, flags, &data[..])))
+    }
+
+    /// Reads the current sample into `data`, resizing it to fit
+    #[cfg(feature = "api28")]
+    fn read_sample(&mut self, track_index: usize, data: &mut Vec<u8>) -> Result<(), Error> {
+        let size = self.sample_size().ok_or_else(|| {
+            Error::new(MediaStatus::ErrorMalformed, "read sample").with_track_index(track_index)
+        })?;
+
+        let count = self.read_sample_data(data, size);
+        if count < 0 {
+            return Err(sample_error(count, track_index, size));
+        }
+
+        Ok(())
+    }
+
+    /// Without the sample size, the buffer starts at the track's max input size and only grows while the sample
+    /// doesn't fit
+    #[cfg(not(feature = "api28"))]
+    fn read_sample(&mut self, track_index: usize, data: &mut Vec<u8>) -> Result<(), Error> {
+        let max_size = self
+            .track_format(track_index)
+            .and_then(|format| format.get(crate::keys::MAX_INPUT_SIZE))
+            .and_then(|size| usize::try_from(size).ok());
+        let mut capacity = max_size.unwrap_or(64 * 1024).max(data.capacity());
+
+        loop {
+            let count = self.read_sample_data(data, capacity);
+            if count >= 0 {
+                return Ok(());
             }
-            buffer.set_flags(self.sample_flags());
 
-            self.has_next = AMediaExtractor_advance(self.inner);
+            // Only -1 means the sample is bigger than the buffer, anything else is a real error
+            let too_small = MediaStatus::try_from(count).is_err()
+                && max_size.is_none_or(|size| size > capacity);
+            if !too_small || capacity >= MAX_PACKET_SIZE {
+                return Err(sample_error(count, track_index, capacity));
+            }
+            capacity = (capacity * 2).min(MAX_PACKET_SIZE);
+        }
+    }
 
-            self.has_next
+    /// Reads the current sample into the spare capacity of `data`, without zero-filling it first.
+    /// Returns the byte count, or the negative error code
+    fn read_sample_data(&mut self, data: &mut Vec<u8>, capacity: usize) -> isize {
+        data.clear();
+        data.reserve(capacity);
+
+        let count =
+            unsafe { AMediaExtractor_readSampleData(self.inner, data.as_mut_ptr(), capacity) };
+        if count >= 0 {
+            // The extractor initialized that many bytes
+            unsafe { data.set_len((count as usize).min(capacity)) };
         }
+
+        count
     }
 
-    /// Returns whether MediaExtractor still has packets to read
+    /// Returns an iterator over the packets of the selected tracks
+    pub fn packets(&mut self) -> Packets<'_> {
+        Packets {
+            extractor: self,
+            done: false,
+        }
+    }
+
+    /// Skips the current packet without reading it.
+    /// Returns true if there's still more data to read
+    pub fn advance(&mut self) -> bool {
+        if self.has_next {
+            self.has_next = unsafe { AMediaExtractor_advance(self.inner) };
+        }
+
+        self.has_next
+    }
+
+    /// Moves every selected track to a sync sample near `time_us`, picked according to `mode`.
+    ///
+    /// Decoders fed from this extractor have to be flushed afterwards
+    pub fn seek_to(&mut self, time_us: i64, mode: SeekMode) -> Result<(), Error> {
+        unsafe { AMediaExtractor_seekTo(self.inner, time_us, mode) }
+            .result()
+            .map_err(|status| Error::new(status, "seek").with_detail(&format!("to {time_us}us")))?;
+
+        // Seeking back from the end makes packets available again, and seeking past it leaves none
+        self.has_next = self.track_index() >= 0;
+        Ok(())
+    }
+
+    /// Returns whether MediaExtractor still has packets to read, or has yet to flag the end of stream through
+    /// [read_next](MediaExtractor::read_next)
     pub fn has_next(&self) -> bool {
         self.has_next
     }
 }
 
+/// The error for a sample that couldn't be read into a buffer of `size` bytes
+fn sample_error(count: isize, track_index: usize, size: usize) -> Error {
+    let status = MediaStatus::try_from(count).unwrap_or(MediaStatus::ErrorIO);
+    Error::new(status, "read sample")
+        .with_track_index(track_index)
+        .with_detail(&format!("into a buffer of {size} bytes"))
+}
+
 impl Drop for MediaExtractor {
     fn drop(&mut self) {
         unsafe {
@@ -1,11 +1,18 @@
 use std::{
     ffi::{c_void, CStr, CString},
+    fmt::Display,
+    marker::PhantomData,
     os::raw::c_char,
     ptr::null_mut,
 };
 
+#[cfg(feature = "api29")]
 use log::debug;
 
+use crate::FormatMap;
+#[cfg(feature = "api29")]
+use crate::MediaStatus;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct AMediaFormat {
@@ -13,7 +20,30 @@ pub struct AMediaFormat {
     _marker: core::marker::PhantomData<(*mut u8, core::marker::PhantomPinned)>,
 }
 
-#[link(name = "mediandk")]
+/// A rectangle, with inclusive coordinates like the `crop-*` entries of a format
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    /// The width of the rectangle
+    pub fn width(&self) -> i32 {
+        self.right - self.left + 1
+    }
+
+    /// The height of the rectangle
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top + 1
+    }
+}
+
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
+#[allow(dead_code)]
 extern "C" {
     /// Available since API level 21.
     fn AMediaFormat_new() -> *mut AMediaFormat;
@@ -129,6 +159,144 @@ extern "C" {
     fn AMediaFormat_copy(to: *mut AMediaFormat, from: *mut AMediaFormat) -> isize;
 }
 
+/// The name of a format entry, along with the type of its value. See the [keys](crate::keys) module
+pub struct Key<T> {
+    name: &'static str,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Key<T> {
+    /// Creates a key for entries not covered by the [keys](crate::keys) module
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _value: PhantomData,
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for Key<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Key<T> {}
+
+impl<T> PartialEq for Key<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<T> Eq for Key<T> {}
+
+impl<T> std::fmt::Debug for Key<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Key({:?})", self.name)
+    }
+}
+
+impl<T> Display for Key<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// A type that can be stored in a MediaFormat, through [MediaFormat::get] and [MediaFormat::set]
+pub trait FormatType: Sized {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self>;
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool;
+}
+
+impl FormatType for i32 {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_i32(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_i32(name, self)
+    }
+}
+
+impl FormatType for i64 {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_i64(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_i64(name, self)
+    }
+}
+
+impl FormatType for f32 {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_f32(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_f32(name, self)
+    }
+}
+
+#[cfg(feature = "api28")]
+impl FormatType for f64 {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_f64(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_f64(name, self)
+    }
+}
+
+#[cfg(feature = "api28")]
+impl FormatType for usize {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_size(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_size(name, self)
+    }
+}
+
+#[cfg(feature = "api28")]
+impl FormatType for Rect {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_rect(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_rect(name, self)
+    }
+}
+
+impl FormatType for Vec<u8> {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_buffer(name).map(<[u8]>::to_vec)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_buffer(name, &self)
+    }
+}
+
+impl FormatType for String {
+    fn get_from(format: &MediaFormat, name: &str) -> Option<Self> {
+        format.get_string(name)
+    }
+
+    fn set_in(self, format: &mut MediaFormat, name: &str) -> bool {
+        format.set_string(name, &self)
+    }
+}
+
 /// This structure stores data in key-value pairs for use in MediaCodec and other places in the NDK
 #[derive(Debug)]
 pub struct MediaFormat {
@@ -154,9 +322,40 @@ impl MediaFormat {
         }
     }
 
+    /// Copies every entry into a new MediaFormat.
+    ///
+    /// Returns None if the new format couldn't be created
+    pub fn try_clone(&self) -> Option<Self> {
+        let mut format = MediaFormat::new()?;
+
+        #[cfg(feature = "api29")]
+        {
+            let result = unsafe { AMediaFormat_copy(format.inner, self.inner) };
+            if MediaStatus::make_result(result).is_ok() {
+                return Some(format);
+            }
+            debug!("Failed to copy a MediaFormat, copying its entries instead");
+        }
+
+        // Only fails for entries that couldn't be read in the first place
+        let _ = FormatMap::from(self).write_to(&mut format);
+        Some(format)
+    }
+
+    /// Get the value of a typed key, like `format.get(keys::CHANNEL_COUNT)`
+    pub fn get<T: FormatType>(&self, key: Key<T>) -> Option<T> {
+        T::get_from(self, key.name)
+    }
+
+    /// Set the value of a typed key, like `format.set(keys::MIME, "audio/mp4a-latm")`
+    pub fn set<T: FormatType>(&mut self, key: Key<T>, value: impl Into<T>) -> bool {
+        value.into().set_in(self, key.name)
+    }
+
     /// Set a 32-bit integer value
     pub fn set_i32(&mut self, name: &str, value: i32) -> bool {
-        unsafe { AMediaFormat_setInt32(self.inner, name.as_ptr().cast(), value) }
+        let name = CString::new(name).unwrap();
+        unsafe { AMediaFormat_setInt32(self.inner, name.as_ptr(), value) }
     }
 
     /// Get a 32-bit integer value
@@ -218,7 +417,7 @@ impl MediaFormat {
 
     /// Convenience function to check whether the mime type is audio
     pub fn is_audio(&self) -> bool {
-        if let Some(mime) = self.get_string("mime") {
+        if let Some(mime) = self.get(crate::keys::MIME) {
             return mime.contains("audio");
         }
 
@@ -227,7 +426,7 @@ impl MediaFormat {
 
     /// Convenience function to check whether the mime type is video
     pub fn is_video(&self) -> bool {
-        if let Some(mime) = self.get_string("mime") {
+        if let Some(mime) = self.get(crate::keys::MIME) {
             return mime.contains("video");
         }
 
@@ -279,6 +478,100 @@ impl MediaFormat {
         value
     }
 
+    /// Set a size value
+    #[cfg(feature = "api28")]
+    pub fn set_size(&mut self, name: &str, value: usize) -> bool {
+        let name = CString::new(name).unwrap();
+        unsafe { AMediaFormat_setSize(self.inner, name.as_ptr(), value) }
+    }
+
+    /// Get a size value
+    pub fn get_size(&self, name: &str) -> Option<usize> {
+        let mut value = None;
+
+        unsafe {
+            let mut v = 0;
+            let name = CString::new(name).unwrap();
+            if AMediaFormat_getSize(self.inner, name.as_ptr(), &mut v) {
+                value = Some(v);
+            }
+        }
+
+        value
+    }
+
+    /// Set a rectangle value, like the `crop` entry
+    #[cfg(feature = "api28")]
+    pub fn set_rect(&mut self, name: &str, value: Rect) -> bool {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            AMediaFormat_setRect(
+                self.inner,
+                name.as_ptr(),
+                value.left,
+                value.top,
+                value.right,
+                value.bottom,
+            )
+        }
+    }
+
+    /// Get a rectangle value
+    #[cfg(feature = "api28")]
+    pub fn get_rect(&self, name: &str) -> Option<Rect> {
+        let mut value = None;
+
+        unsafe {
+            let mut v = Rect::default();
+            let name = CString::new(name).unwrap();
+            if AMediaFormat_getRect(
+                self.inner,
+                name.as_ptr(),
+                &mut v.left,
+                &mut v.top,
+                &mut v.right,
+                &mut v.bottom,
+            ) {
+                value = Some(v);
+            }
+        }
+
+        value
+    }
+
+    /// Set a buffer value, like the codec specific data in `csd-0`. The data gets copied
+    pub fn set_buffer(&mut self, name: &str, value: &[u8]) -> bool {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            AMediaFormat_setBuffer(
+                self.inner,
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+            )
+        }
+    }
+
+    /// Get a buffer value. The data is owned by the format
+    pub fn get_buffer(&self, name: &str) -> Option<&[u8]> {
+        let mut value = None;
+
+        unsafe {
+            let mut data = null_mut();
+            let mut size = 0;
+            let name = CString::new(name).unwrap();
+            if AMediaFormat_getBuffer(self.inner, name.as_ptr(), &mut data, &mut size) {
+                value = Some(if data.is_null() || size == 0 {
+                    &[][..]
+                } else {
+                    std::slice::from_raw_parts(data as *const u8, size)
+                });
+            }
+        }
+
+        value
+    }
+
     /// Clear the entire buffer
     #[cfg(feature = "api29")]
     pub fn clear(&mut self) {
@@ -288,14 +581,25 @@ impl MediaFormat {
     }
 }
 
-impl ToString for MediaFormat {
-    fn to_string(&self) -> String {
+impl Clone for MediaFormat {
+    /// Copies every entry, see [try_clone](MediaFormat::try_clone).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new format couldn't be created
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Failed to create a MediaFormat")
+    }
+}
+
+impl Display for MediaFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         unsafe {
             let value = AMediaFormat_toString(self.inner);
             if !value.is_null() {
-                CStr::from_ptr(value).to_string_lossy().to_string()
+                write!(f, "{}", CStr::from_ptr(value).to_string_lossy())
             } else {
-                String::new()
+                Ok(())
             }
         }
     }
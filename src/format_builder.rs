@@ -0,0 +1,396 @@
+use crate::{keys, ColorFormat, Error, Key, MediaFormat, MediaStatus};
+
+/// How an encoder spends its bitrate, as stored under `bitrate-mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitrateMode {
+    /// `BITRATE_MODE_CQ`. Ignores the bitrate, and keeps the quality constant
+    ConstantQuality = 0,
+    /// `BITRATE_MODE_VBR`
+    Variable = 1,
+    /// `BITRATE_MODE_CBR`
+    Constant = 2,
+    /// `BITRATE_MODE_CBR_FD`. Like `Constant`, dropping frames to stay within the bitrate
+    ConstantWithFrameDrops = 3,
+}
+
+/// The color primaries and matrix of a video, as stored under `color-standard`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorStandard {
+    /// `COLOR_STANDARD_BT709`
+    Bt709 = 1,
+    /// `COLOR_STANDARD_BT601_PAL`
+    Bt601Pal = 2,
+    /// `COLOR_STANDARD_BT601_NTSC`
+    Bt601Ntsc = 4,
+    /// `COLOR_STANDARD_BT2020`
+    Bt2020 = 6,
+}
+
+/// The range of the samples of a video, as stored under `color-range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRange {
+    /// `COLOR_RANGE_FULL`
+    Full = 1,
+    /// `COLOR_RANGE_LIMITED`
+    Limited = 2,
+}
+
+/// The transfer function of a video, as stored under `color-transfer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorTransfer {
+    /// `COLOR_TRANSFER_LINEAR`
+    Linear = 1,
+    /// `COLOR_TRANSFER_SDR_VIDEO`
+    SdrVideo = 3,
+    /// `COLOR_TRANSFER_ST2084`, for HDR10
+    St2084 = 6,
+    /// `COLOR_TRANSFER_HLG`
+    Hlg = 7,
+}
+
+/// The AAC object type, as stored under `aac-profile`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AacProfile {
+    /// `AACObjectLC`
+    Lc = 2,
+    /// `AACObjectHE`
+    He = 5,
+    /// `AACObjectLD`
+    Ld = 23,
+    /// `AACObjectHE_PS`
+    HeV2 = 29,
+    /// `AACObjectELD`
+    Eld = 39,
+    /// `AACObjectXHE`
+    Xhe = 42,
+}
+
+/// The sample format of raw audio, as stored under `pcm-encoding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcmEncoding {
+    /// `ENCODING_PCM_16BIT`
+    I16 = 2,
+    /// `ENCODING_PCM_8BIT`
+    U8 = 3,
+    /// `ENCODING_PCM_FLOAT`
+    F32 = 4,
+    /// `ENCODING_PCM_24BIT_PACKED`
+    I24Packed = 21,
+    /// `ENCODING_PCM_32BIT`
+    I32 = 22,
+}
+
+fn invalid(detail: &str) -> Error {
+    Error::new(MediaStatus::ErrorInvalidParameter, "build format").with_detail(detail)
+}
+
+/// Sets a rate as an integer when it's a whole number, since that's what most codecs look for
+fn set_rate(format: &mut MediaFormat, key: Key<i32>, value: f32) {
+    if value.fract() == 0.0 && value.abs() <= i32::MAX as f32 {
+        format.set(key, value as i32);
+    } else {
+        format.set_f32(key.name(), value);
+    }
+}
+
+/// Builds the MediaFormat of a video codec.
+///
+/// Only the mime type and the size are required. Everything else is left for the codec to pick when not set
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoFormatBuilder {
+    mime_type: String,
+    width: i32,
+    height: i32,
+    bitrate: Option<i32>,
+    bitrate_mode: Option<BitrateMode>,
+    frame_rate: Option<f32>,
+    i_frame_interval: Option<f32>,
+    color_format: Option<ColorFormat>,
+    profile: Option<(i32, Option<i32>)>,
+    color_standard: Option<ColorStandard>,
+    color_range: Option<ColorRange>,
+    color_transfer: Option<ColorTransfer>,
+}
+
+impl VideoFormatBuilder {
+    pub fn new(mime_type: &str, width: i32, height: i32) -> Self {
+        Self {
+            mime_type: mime_type.to_string(),
+            width,
+            height,
+            bitrate: None,
+            bitrate_mode: None,
+            frame_rate: None,
+            i_frame_interval: None,
+            color_format: None,
+            profile: None,
+            color_standard: None,
+            color_range: None,
+            color_transfer: None,
+        }
+    }
+
+    /// In bits per second
+    pub fn bitrate(mut self, bitrate: i32) -> Self {
+        self.bitrate = Some(bitrate);
+        self
+    }
+
+    pub fn bitrate_mode(mut self, mode: BitrateMode) -> Self {
+        self.bitrate_mode = Some(mode);
+        self
+    }
+
+    /// In frames per second
+    pub fn frame_rate(mut self, frame_rate: f32) -> Self {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// In seconds. 0 makes every frame a key frame, and a negative value only makes the first one a key frame
+    pub fn i_frame_interval(mut self, interval: f32) -> Self {
+        self.i_frame_interval = Some(interval);
+        self
+    }
+
+    /// The layout of the raw frames. Encoders fed from a surface need `Other(COLOR_FormatSurface)`
+    pub fn color_format(mut self, format: ColorFormat) -> Self {
+        self.color_format = Some(format);
+        self
+    }
+
+    /// The codec specific profile (like `AVCProfileHigh`), and optionally its level (like `AVCLevel41`)
+    pub fn profile(mut self, profile: i32, level: Option<i32>) -> Self {
+        self.profile = Some((profile, level));
+        self
+    }
+
+    pub fn color_standard(mut self, standard: ColorStandard) -> Self {
+        self.color_standard = Some(standard);
+        self
+    }
+
+    pub fn color_range(mut self, range: ColorRange) -> Self {
+        self.color_range = Some(range);
+        self
+    }
+
+    pub fn color_transfer(mut self, transfer: ColorTransfer) -> Self {
+        self.color_transfer = Some(transfer);
+        self
+    }
+
+    /// Checks the values without building the format
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.mime_type.starts_with("video/") {
+            return Err(invalid(&format!(
+                "{} is not a video mime type",
+                self.mime_type
+            )));
+        }
+        if self.width <= 0 || self.height <= 0 {
+            return Err(invalid(&format!(
+                "invalid size {}x{}",
+                self.width, self.height
+            )));
+        }
+        if let Some(bitrate) = self.bitrate {
+            if bitrate <= 0 {
+                return Err(invalid(&format!("invalid bitrate {bitrate}")));
+            }
+        }
+        if matches!(
+            self.bitrate_mode,
+            Some(
+                BitrateMode::Variable | BitrateMode::Constant | BitrateMode::ConstantWithFrameDrops
+            )
+        ) && self.bitrate.is_none()
+        {
+            return Err(invalid("the bitrate mode needs a bitrate"));
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            if !frame_rate.is_finite() || frame_rate <= 0.0 {
+                return Err(invalid(&format!("invalid frame rate {frame_rate}")));
+            }
+        }
+        if let Some(interval) = self.i_frame_interval {
+            if !interval.is_finite() {
+                return Err(invalid(&format!("invalid I-frame interval {interval}")));
+            }
+        }
+        if let Some((profile, level)) = self.profile {
+            if profile <= 0 || level.is_some_and(|level| level <= 0) {
+                return Err(invalid(&format!(
+                    "invalid profile {profile} and level {level:?}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the values, and creates a format ready for [MediaCodec::init](crate::MediaCodec::init)
+    pub fn build(&self) -> Result<MediaFormat, Error> {
+        self.validate()?;
+
+        let mut format = MediaFormat::new()
+            .ok_or_else(|| Error::new(MediaStatus::ErrorInsufficientResource, "create format"))?;
+        format.set(keys::MIME, self.mime_type.as_str());
+        format.set(keys::WIDTH, self.width);
+        format.set(keys::HEIGHT, self.height);
+
+        if let Some(bitrate) = self.bitrate {
+            format.set(keys::BIT_RATE, bitrate);
+        }
+        if let Some(mode) = self.bitrate_mode {
+            format.set(keys::BITRATE_MODE, mode as i32);
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            set_rate(&mut format, keys::FRAME_RATE, frame_rate);
+        }
+        if let Some(interval) = self.i_frame_interval {
+            set_rate(&mut format, keys::I_FRAME_INTERVAL, interval);
+        }
+        if let Some(color_format) = self.color_format {
+            format.set(keys::COLOR_FORMAT, color_format.value());
+        }
+        if let Some((profile, level)) = self.profile {
+            format.set(keys::PROFILE, profile);
+            if let Some(level) = level {
+                format.set(keys::LEVEL, level);
+            }
+        }
+        if let Some(standard) = self.color_standard {
+            format.set(keys::COLOR_STANDARD, standard as i32);
+        }
+        if let Some(range) = self.color_range {
+            format.set(keys::COLOR_RANGE, range as i32);
+        }
+        if let Some(transfer) = self.color_transfer {
+            format.set(keys::COLOR_TRANSFER, transfer as i32);
+        }
+
+        Ok(format)
+    }
+}
+
+/// Builds the MediaFormat of an audio codec.
+///
+/// Only the mime type, the sample rate and the channel count are required
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormatBuilder {
+    mime_type: String,
+    sample_rate: i32,
+    channel_count: i32,
+    channel_mask: Option<i32>,
+    aac_profile: Option<AacProfile>,
+    bitrate: Option<i32>,
+    pcm_encoding: Option<PcmEncoding>,
+}
+
+impl AudioFormatBuilder {
+    pub fn new(mime_type: &str, sample_rate: i32, channel_count: i32) -> Self {
+        Self {
+            mime_type: mime_type.to_string(),
+            sample_rate,
+            channel_count,
+            channel_mask: None,
+            aac_profile: None,
+            bitrate: None,
+            pcm_encoding: None,
+        }
+    }
+
+    /// The channel positions, like `CHANNEL_OUT_STEREO`. It needs one bit per channel
+    pub fn channel_mask(mut self, mask: i32) -> Self {
+        self.channel_mask = Some(mask);
+        self
+    }
+
+    /// Only valid for AAC (`audio/mp4a-latm`)
+    pub fn aac_profile(mut self, profile: AacProfile) -> Self {
+        self.aac_profile = Some(profile);
+        self
+    }
+
+    /// In bits per second
+    pub fn bitrate(mut self, bitrate: i32) -> Self {
+        self.bitrate = Some(bitrate);
+        self
+    }
+
+    /// The format of the raw samples, fed to an encoder or requested from a decoder
+    pub fn pcm_encoding(mut self, encoding: PcmEncoding) -> Self {
+        self.pcm_encoding = Some(encoding);
+        self
+    }
+
+    /// Checks the values without building the format
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.mime_type.starts_with("audio/") {
+            return Err(invalid(&format!(
+                "{} is not an audio mime type",
+                self.mime_type
+            )));
+        }
+        if self.sample_rate <= 0 {
+            return Err(invalid(&format!(
+                "invalid sample rate {}",
+                self.sample_rate
+            )));
+        }
+        if self.channel_count <= 0 {
+            return Err(invalid(&format!(
+                "invalid channel count {}",
+                self.channel_count
+            )));
+        }
+        if let Some(mask) = self.channel_mask {
+            if mask.count_ones() as i32 != self.channel_count {
+                return Err(invalid(&format!(
+                    "the channel mask {mask:#x} doesn't have {} channels",
+                    self.channel_count
+                )));
+            }
+        }
+        if self.aac_profile.is_some() && self.mime_type != "audio/mp4a-latm" {
+            return Err(invalid(&format!(
+                "{} doesn't take an AAC profile",
+                self.mime_type
+            )));
+        }
+        if let Some(bitrate) = self.bitrate {
+            if bitrate <= 0 {
+                return Err(invalid(&format!("invalid bitrate {bitrate}")));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the values, and creates a format ready for [MediaCodec::init](crate::MediaCodec::init)
+    pub fn build(&self) -> Result<MediaFormat, Error> {
+        self.validate()?;
+
+        let mut format = MediaFormat::new()
+            .ok_or_else(|| Error::new(MediaStatus::ErrorInsufficientResource, "create format"))?;
+        format.set(keys::MIME, self.mime_type.as_str());
+        format.set(keys::SAMPLE_RATE, self.sample_rate);
+        format.set(keys::CHANNEL_COUNT, self.channel_count);
+
+        if let Some(mask) = self.channel_mask {
+            format.set(keys::CHANNEL_MASK, mask);
+        }
+        if let Some(profile) = self.aac_profile {
+            format.set(keys::AAC_PROFILE, profile as i32);
+        }
+        if let Some(bitrate) = self.bitrate {
+            format.set(keys::BIT_RATE, bitrate);
+        }
+        if let Some(encoding) = self.pcm_encoding {
+            format.set(keys::PCM_ENCODING, encoding as i32);
+        }
+
+        Ok(format)
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{FormatMap, FormatValue, Rect};
+
+/// A value of a [FormatDescription]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DescribedValue {
+    Value(FormatValue),
+    /// A buffer, whose content isn't part of the description
+    Data,
+    /// A type the parser doesn't know, kept as it was printed
+    Unknown(String),
+}
+
+/// The entries of a format, parsed from its [to_string](ToString::to_string) output, like
+/// `mime: string(video/avc), width: int32(1920), csd-0: data`.
+///
+/// Unlike [FormatMap], this doesn't need the format itself, so it works on formats written to logs. Entries keep the
+/// order they were printed in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatDescription {
+    entries: Vec<(String, DescribedValue)>,
+}
+
+/// An entry that differs between two descriptions. See [FormatDescription::diff]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormatDifference<'a> {
+    pub name: &'a str,
+    /// The value in the first description, if it has the entry
+    pub left: Option<&'a DescribedValue>,
+    /// The value in the second description, if it has the entry
+    pub right: Option<&'a DescribedValue>,
+}
+
+/// Why a description couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset in the description where parsing failed
+    pub offset: usize,
+    pub reason: &'static str,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at offset {}", self.reason, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl FormatDescription {
+    pub fn get(&self, name: &str) -> Option<&DescribedValue> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the entries, in the order they were printed
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &DescribedValue)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Returns the entries that were added, removed or changed in `other`.
+    ///
+    /// Buffers compare equal, since their content isn't known
+    pub fn diff<'a>(&'a self, other: &'a Self) -> Vec<FormatDifference<'a>> {
+        let changed = self.iter().filter_map(|(name, left)| {
+            let right = other.get(name);
+            (right != Some(left)).then_some(FormatDifference {
+                name,
+                left: Some(left),
+                right,
+            })
+        });
+        let added = other
+            .iter()
+            .filter(|(name, _)| self.get(name).is_none())
+            .map(|(name, right)| FormatDifference {
+                name,
+                left: None,
+                right: Some(right),
+            });
+
+        changed.chain(added).collect()
+    }
+
+    /// Returns the entries with a known value. Buffers and unknown types are left out
+    pub fn to_format_map(&self) -> FormatMap {
+        self.entries
+            .iter()
+            .filter_map(|(name, value)| match value {
+                DescribedValue::Value(value) => Some((name.as_str(), value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl FromStr for FormatDescription {
+    type Err = ParseError;
+
+    /// Parses a description.
+    ///
+    /// The end of a string is only known by what follows it, so a string holding something like
+    /// `), width: int32(1920)` is cut there
+    fn from_str(description: &str) -> Result<Self, ParseError> {
+        parse_description(description, |_| None)
+    }
+}
+
+/// Parses `description`, in a single pass.
+///
+/// `known_string` returns the actual value of a string entry when it's known, which tells exactly where it ends
+pub(crate) fn parse_description(
+    description: &str,
+    known_string: impl Fn(&str) -> Option<String>,
+) -> Result<FormatDescription, ParseError> {
+    let input = description.trim_end();
+    let mut position = input.len() - input.trim_start().len();
+    let mut entries = vec![];
+
+    while position < input.len() {
+        if !entries.is_empty() {
+            if !input[position..].starts_with(", ") {
+                return Err(ParseError {
+                    offset: position,
+                    reason: "expected `, ` between entries",
+                });
+            }
+            position += 2;
+        }
+
+        let rest = &input[position..];
+        let name_end = rest.find(": ").ok_or(ParseError {
+            offset: position,
+            reason: "expected `name: value`",
+        })?;
+        if name_end == 0 {
+            return Err(ParseError {
+                offset: position,
+                reason: "empty entry name",
+            });
+        }
+
+        let name = &rest[..name_end];
+        let (value, end) = parse_value(input, position + name_end + 2, name, &known_string)?;
+        entries.push((name.to_string(), value));
+        position = end;
+    }
+
+    Ok(FormatDescription { entries })
+}
+
+/// Parses the value of `name` starting at `start`, along with the offset right after it
+fn parse_value(
+    input: &str,
+    start: usize,
+    name: &str,
+    known_string: &impl Fn(&str) -> Option<String>,
+) -> Result<(DescribedValue, usize), ParseError> {
+    let rest = &input[start..];
+    let kind_end = type_name_len(rest);
+    let kind = &rest[..kind_end];
+    if kind.is_empty() {
+        return Err(ParseError {
+            offset: start,
+            reason: "expected a value type",
+        });
+    }
+
+    if !rest[kind_end..].starts_with('(') {
+        let value = match kind {
+            "data" => DescribedValue::Data,
+            _ => DescribedValue::Unknown(kind.to_string()),
+        };
+        return Ok((value, start + kind_end));
+    }
+
+    let open = start + kind_end + 1;
+    let unterminated = ParseError {
+        offset: start,
+        reason: "unterminated value",
+    };
+
+    match kind {
+        "int32" | "int64" | "float" | "double" | "size_t" | "Rect" => {
+            // Numbers can't hold parentheses
+            let close = open + input[open..].find(')').ok_or(unterminated)?;
+            let value = parse_number(kind, &input[open..close], open)?;
+            Ok((DescribedValue::Value(value), close + 1))
+        }
+        "string" => {
+            let known_end = known_string(name)
+                .filter(|value| input[open..].starts_with(&format!("{value})")))
+                .map(|value| open + value.len());
+            let close = match known_end {
+                Some(close) => close,
+                None => find_value_end(input, open).ok_or(unterminated)?,
+            };
+            let value = FormatValue::String(input[open..close].to_string());
+            Ok((DescribedValue::Value(value), close + 1))
+        }
+        _ => {
+            let close = find_value_end(input, open).ok_or(unterminated)?;
+            let raw = input[start..=close].to_string();
+            Ok((DescribedValue::Unknown(raw), close + 1))
+        }
+    }
+}
+
+/// Finds the `)` closing a value that can hold anything, starting at `open`: the first one followed by the end of
+/// the description or by what looks like the next entry
+fn find_value_end(input: &str, open: usize) -> Option<usize> {
+    input[open..]
+        .match_indices(')')
+        .map(|(index, _)| open + index)
+        .find(|&close| {
+            let tail = &input[close + 1..];
+            tail.is_empty() || entry_follows(tail)
+        })
+}
+
+/// Whether `tail` starts with `, name: type`, where numeric values must also be complete.
+///
+/// Only looks up to the next `)`, which keeps parsing linear
+fn entry_follows(tail: &str) -> bool {
+    let Some(rest) = tail.strip_prefix(", ") else {
+        return false;
+    };
+    let name_len = rest
+        .find(|c: char| matches!(c, ':' | ',' | '(' | ')') || c.is_whitespace())
+        .unwrap_or(rest.len());
+    if name_len == 0 || !rest[name_len..].starts_with(": ") {
+        return false;
+    }
+
+    let value = &rest[name_len + 2..];
+    let kind_len = type_name_len(value);
+    let (kind, after_kind) = value.split_at(kind_len);
+    if kind.is_empty() {
+        return false;
+    }
+
+    let ends_entry = |text: &str| text.is_empty() || text.starts_with(", ");
+    match after_kind.strip_prefix('(') {
+        None => ends_entry(after_kind),
+        Some(inner) => match kind {
+            "int32" | "int64" | "float" | "double" | "size_t" | "Rect" => {
+                inner.find(')').is_some_and(|close| {
+                    parse_number(kind, &inner[..close], 0).is_ok()
+                        && ends_entry(&inner[close + 1..])
+                })
+            }
+            _ => true,
+        },
+    }
+}
+
+/// Parses the text between the parentheses of a numeric value, which starts at `offset`
+fn parse_number(kind: &str, text: &str, offset: usize) -> Result<FormatValue, ParseError> {
+    let invalid = ParseError {
+        offset,
+        reason: "invalid value",
+    };
+
+    let value = match kind {
+        "int32" => FormatValue::I32(text.parse().map_err(|_| invalid.clone())?),
+        "int64" => FormatValue::I64(text.parse().map_err(|_| invalid.clone())?),
+        "float" => FormatValue::F32(text.parse().map_err(|_| invalid.clone())?),
+        "double" => FormatValue::F64(text.parse().map_err(|_| invalid.clone())?),
+        "size_t" => FormatValue::Size(text.parse().map_err(|_| invalid.clone())?),
+        _ => {
+            let values = text
+                .split(',')
+                .map(|value| value.trim().parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid.clone())?;
+            let [left, top, right, bottom] = values[..] else {
+                return Err(ParseError {
+                    offset,
+                    reason: "expected 4 rectangle coordinates",
+                });
+            };
+
+            FormatValue::Rect(Rect {
+                left,
+                top,
+                right,
+                bottom,
+            })
+        }
+    };
+
+    Ok(value)
+}
+
+fn type_name_len(value: &str) -> usize {
+    value
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(value.len())
+}
+
+/// Prints the description the way `AMediaFormat_toString` does
+impl Display for FormatDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, (name, value)) in self.entries.iter().enumerate() {
+            if index != 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{name}: ")?;
+            match value {
+                DescribedValue::Value(FormatValue::I32(value)) => write!(f, "int32({value})")?,
+                DescribedValue::Value(FormatValue::I64(value)) => write!(f, "int64({value})")?,
+                DescribedValue::Value(FormatValue::F32(value)) => write!(f, "float({value:.6})")?,
+                DescribedValue::Value(FormatValue::F64(value)) => write!(f, "double({value:.6})")?,
+                DescribedValue::Value(FormatValue::Size(value)) => write!(f, "size_t({value})")?,
+                DescribedValue::Value(FormatValue::Rect(rect)) => write!(
+                    f,
+                    "Rect({}, {}, {}, {})",
+                    rect.left, rect.top, rect.right, rect.bottom
+                )?,
+                DescribedValue::Value(FormatValue::String(value)) => write!(f, "string({value})")?,
+                DescribedValue::Value(FormatValue::Buffer(_)) | DescribedValue::Data => {
+                    f.write_str("data")?
+                }
+                DescribedValue::Unknown(raw) => f.write_str(raw)?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+use log::debug;
+
+use crate::format_description::parse_description;
+use crate::{DescribedValue, Error, MediaFormat, MediaStatus, Rect};
+
+/// A single value of a [FormatMap]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Size(usize),
+    Rect(Rect),
+    String(String),
+    Buffer(Vec<u8>),
+}
+
+impl From<i32> for FormatValue {
+    fn from(value: i32) -> Self {
+        Self::I32(value)
+    }
+}
+
+impl From<i64> for FormatValue {
+    fn from(value: i64) -> Self {
+        Self::I64(value)
+    }
+}
+
+impl From<f32> for FormatValue {
+    fn from(value: f32) -> Self {
+        Self::F32(value)
+    }
+}
+
+impl From<f64> for FormatValue {
+    fn from(value: f64) -> Self {
+        Self::F64(value)
+    }
+}
+
+impl From<usize> for FormatValue {
+    fn from(value: usize) -> Self {
+        Self::Size(value)
+    }
+}
+
+impl From<Rect> for FormatValue {
+    fn from(value: Rect) -> Self {
+        Self::Rect(value)
+    }
+}
+
+impl From<String> for FormatValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for FormatValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for FormatValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Buffer(value)
+    }
+}
+
+impl From<&[u8]> for FormatValue {
+    fn from(value: &[u8]) -> Self {
+        Self::Buffer(value.to_vec())
+    }
+}
+
+/// The entries of a [MediaFormat], in plain Rust. Entries keep their insertion order, like they do in the NDK.
+///
+/// Converts losslessly to and from a MediaFormat, and serializes to a map with the `serde` feature
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatMap {
+    entries: Vec<(String, FormatValue)>,
+}
+
+impl FormatMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of `name`, returning the previous one. New entries go last
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<FormatValue>,
+    ) -> Option<FormatValue> {
+        let name = name.into();
+        let value = value.into();
+
+        match self.entries.iter_mut().find(|(key, _)| *key == name) {
+            Some(entry) => Some(std::mem::replace(&mut entry.1, value)),
+            None => {
+                self.entries.push((name, value));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FormatValue> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<FormatValue> {
+        let index = self.entries.iter().position(|(key, _)| key == name)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the entries, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FormatValue)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Sets every entry on `format`. Doubles, sizes and rectangles need API 28
+    pub fn write_to(&self, format: &mut MediaFormat) -> Result<(), Error> {
+        for (name, value) in &self.entries {
+            match value {
+                FormatValue::I32(value) => format.set_i32(name, *value),
+                FormatValue::I64(value) => format.set_i64(name, *value),
+                FormatValue::F32(value) => format.set_f32(name, *value),
+                #[cfg(feature = "api28")]
+                FormatValue::F64(value) => format.set_f64(name, *value),
+                #[cfg(feature = "api28")]
+                FormatValue::Size(value) => format.set_size(name, *value),
+                #[cfg(feature = "api28")]
+                FormatValue::Rect(value) => format.set_rect(name, *value),
+                FormatValue::String(value) => format.set_string(name, value),
+                FormatValue::Buffer(value) => format.set_buffer(name, value),
+                #[cfg(not(feature = "api28"))]
+                FormatValue::F64(_) | FormatValue::Size(_) | FormatValue::Rect(_) => {
+                    return Err(
+                        Error::new(MediaStatus::ErrorUnsupported, "set format entry")
+                            .with_detail(name),
+                    );
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Creates a MediaFormat holding every entry
+    pub fn to_media_format(&self) -> Result<MediaFormat, Error> {
+        let mut format = MediaFormat::new()
+            .ok_or_else(|| Error::new(MediaStatus::ErrorInsufficientResource, "create format"))?;
+        self.write_to(&mut format)?;
+        Ok(format)
+    }
+}
+
+impl From<&MediaFormat> for FormatMap {
+    /// Reads every entry of `format`.
+    ///
+    /// The NDK can't list the entries of a format, so they're found by parsing its
+    /// [description](crate::FormatDescription), and read back through the getters. Doubles, sizes and rectangles
+    /// can only be read with API 28
+    fn from(format: &MediaFormat) -> Self {
+        let mut map = FormatMap::new();
+        // Strings are read first, so those holding separators still end in the right place
+        let description =
+            match parse_description(&format.to_string(), |name| format.get_string(name)) {
+                Ok(description) => description,
+                Err(error) => {
+                    debug!("Failed to parse the format description: {error}");
+                    return map;
+                }
+            };
+
+        for (name, value) in description.iter() {
+            let entry = match value {
+                DescribedValue::Value(value) => match value {
+                    FormatValue::I32(_) => format.get_i32(name).map(FormatValue::I32),
+                    FormatValue::I64(_) => format.get_i64(name).map(FormatValue::I64),
+                    FormatValue::F32(_) => format.get_f32(name).map(FormatValue::F32),
+                    #[cfg(feature = "api28")]
+                    FormatValue::F64(_) => format.get_f64(name).map(FormatValue::F64),
+                    #[cfg(feature = "api28")]
+                    FormatValue::Size(_) => format.get_size(name).map(FormatValue::Size),
+                    #[cfg(feature = "api28")]
+                    FormatValue::Rect(_) => format.get_rect(name).map(FormatValue::Rect),
+                    FormatValue::String(_) => format.get_string(name).map(FormatValue::String),
+                    _ => None,
+                },
+                DescribedValue::Data => format.get_buffer(name).map(FormatValue::from),
+                DescribedValue::Unknown(_) => None,
+            };
+
+            match entry {
+                Some(entry) => {
+                    map.insert(name, entry);
+                }
+                None => debug!("Failed to read the format entry {name}: {value:?}"),
+            }
+        }
+
+        map
+    }
+}
+
+impl TryFrom<&FormatMap> for MediaFormat {
+    type Error = Error;
+
+    fn try_from(map: &FormatMap) -> Result<Self, Error> {
+        map.to_media_format()
+    }
+}
+
+impl<K: Into<String>, V: Into<FormatValue>> FromIterator<(K, V)> for FormatMap {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = FormatMap::new();
+        for (name, value) in iter {
+            map.insert(name, value);
+        }
+        map
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FormatMap {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        for (name, value) in &self.entries {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FormatMap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = FormatMap;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of format entries")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<FormatMap, A::Error> {
+                let mut map = FormatMap::new();
+                while let Some((name, value)) = access.next_entry::<String, FormatValue>()? {
+                    map.insert(name, value);
+                }
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
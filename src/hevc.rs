@@ -0,0 +1,695 @@
+use crate::{
+    format_csd, remove_emulation_prevention, split_nal_units, BitReader, ChromaFormat, Error,
+    FrameCrop, MediaFormat, MediaStatus, Rect, Timing, VuiParameters,
+};
+
+const NAL_VPS: u8 = 32;
+const NAL_SPS: u8 = 33;
+const NAL_PPS: u8 = 34;
+
+/// `general_profile_idc` of the Main profile
+pub const HEVC_PROFILE_MAIN: u8 = 1;
+/// `general_profile_idc` of the Main 10 profile
+pub const HEVC_PROFILE_MAIN_10: u8 = 2;
+/// `general_profile_idc` of the Main Still Picture profile
+pub const HEVC_PROFILE_MAIN_STILL_PICTURE: u8 = 3;
+/// `general_profile_idc` of the format range extensions profiles (4:2:2, 4:4:4, 12 bits...)
+pub const HEVC_PROFILE_RANGE_EXTENSIONS: u8 = 4;
+
+fn nal_type(nal: &[u8]) -> Option<u8> {
+    nal.first().map(|header| (header >> 1) & 0x3F)
+}
+
+/// Returns the RBSP of `nal` if it's a NAL unit of type `nal_type`, without its two byte header
+fn nal_payload(nal: &[u8], expected: u8, operation: &'static str) -> Result<Vec<u8>, Error> {
+    match nal.get(2..) {
+        Some(payload) if nal_type(nal) == Some(expected) => {
+            Ok(remove_emulation_prevention(payload))
+        }
+        _ => Err(Error::new(MediaStatus::ErrorMalformed, operation)
+            .with_detail(&format!("expected a NAL unit of type {expected}"))),
+    }
+}
+
+/// The general profile, tier and level of an H.265 stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HevcProfileTierLevel {
+    pub profile_space: u8,
+    pub high_tier: bool,
+    pub profile_idc: u8,
+    /// `general_profile_compatibility_flag[j]` is bit `31 - j`
+    pub compatibility_flags: u32,
+    /// The 48 bits starting with `general_progressive_source_flag`
+    pub constraint_flags: u64,
+    /// 30 times the level number
+    pub level_idc: u8,
+}
+
+impl HevcProfileTierLevel {
+    /// Reads the general fields, laid out the same in parameter sets and `hvcC` boxes
+    fn parse_general(reader: &mut BitReader) -> Result<Self, Error> {
+        Ok(Self {
+            profile_space: reader.read_bits(2)? as u8,
+            high_tier: reader.read_bit()?,
+            profile_idc: reader.read_bits(5)? as u8,
+            compatibility_flags: reader.read_bits(32)?,
+            constraint_flags: (reader.read_bits(32)? as u64) << 16 | reader.read_bits(16)? as u64,
+            level_idc: reader.read_bits(8)? as u8,
+        })
+    }
+
+    /// Reads a `profile_tier_level()` with its general profile, skipping the sub-layers
+    fn parse(reader: &mut BitReader, max_sub_layers_minus1: u32) -> Result<Self, Error> {
+        let general = Self::parse_general(reader)?;
+
+        let mut present = [(false, false); 7];
+        for layer in present.iter_mut().take(max_sub_layers_minus1 as usize) {
+            *layer = (reader.read_bit()?, reader.read_bit()?);
+        }
+        if max_sub_layers_minus1 > 0 {
+            reader.skip_bits(2 * (8 - max_sub_layers_minus1 as usize))?;
+        }
+        for (profile_present, level_present) in
+            present.into_iter().take(max_sub_layers_minus1 as usize)
+        {
+            if profile_present {
+                reader.skip_bits(88)?;
+            }
+            if level_present {
+                reader.skip_bits(8)?;
+            }
+        }
+
+        Ok(general)
+    }
+
+    /// The profile the stream conforms to. Streams may leave `profile_idc` at 0 and only set a compatibility flag
+    pub fn profile(&self) -> u8 {
+        if self.profile_idc != 0 {
+            return self.profile_idc;
+        }
+        (1..32)
+            .find(|&profile| self.is_compatible_with(profile))
+            .unwrap_or(0)
+    }
+
+    /// Whether a decoder for `profile` can decode the stream
+    pub fn is_compatible_with(&self, profile: u8) -> bool {
+        self.profile_idc == profile
+            || (profile < 32 && self.compatibility_flags & (1 << (31 - profile)) != 0)
+    }
+
+    pub fn progressive_source(&self) -> bool {
+        self.constraint_flags & 1 << 47 != 0
+    }
+
+    pub fn interlaced_source(&self) -> bool {
+        self.constraint_flags & 1 << 46 != 0
+    }
+
+    /// The level as a `MediaCodecInfo.CodecProfileLevel` value (like `HEVCMainTierLevel51`), for the `level` format
+    /// key
+    pub fn codec_level(&self) -> Option<i32> {
+        const LEVELS: [u8; 13] = [30, 60, 63, 90, 93, 120, 123, 150, 153, 156, 180, 183, 186];
+
+        let index = LEVELS.iter().position(|&level| level == self.level_idc)?;
+        Some(1 << (2 * index + self.high_tier as usize))
+    }
+}
+
+/// An H.265 video parameter set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcVps {
+    pub id: u8,
+    pub max_layers: u8,
+    pub max_sub_layers: u8,
+    pub temporal_id_nesting: bool,
+    pub profile_tier_level: HevcProfileTierLevel,
+}
+
+impl HevcVps {
+    /// Parses a NAL unit holding a VPS, header included but without a start code
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let data = nal_payload(nal, NAL_VPS, "parse VPS")?;
+        let mut reader = BitReader::new(&data, "parse VPS");
+
+        let id = reader.read_bits(4)? as u8;
+        // vps_base_layer_internal_flag, vps_base_layer_available_flag
+        reader.skip_bits(2)?;
+        let max_layers = reader.read_bits(6)? as u8 + 1;
+        let max_sub_layers_minus1 = reader.read_bits(3)?;
+        let temporal_id_nesting = reader.read_bit()?;
+        // vps_reserved_0xffff_16bits
+        reader.skip_bits(16)?;
+        let profile_tier_level = HevcProfileTierLevel::parse(&mut reader, max_sub_layers_minus1)?;
+
+        Ok(Self {
+            id,
+            max_layers,
+            max_sub_layers: max_sub_layers_minus1 as u8 + 1,
+            temporal_id_nesting,
+            profile_tier_level,
+        })
+    }
+}
+
+/// An H.265 sequence parameter set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcSps {
+    pub vps_id: u8,
+    pub max_sub_layers: u8,
+    pub profile_tier_level: HevcProfileTierLevel,
+    pub id: u32,
+    pub chroma_format: ChromaFormat,
+    pub separate_colour_plane: bool,
+    /// The size of the decoded frames
+    pub coded_width: u32,
+    pub coded_height: u32,
+    /// The part of the decoded frames to output
+    pub conformance_window: FrameCrop,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub vui: Option<VuiParameters>,
+    /// The part of the output frames to display, from the VUI
+    pub default_display_window: Option<FrameCrop>,
+}
+
+impl HevcSps {
+    /// Parses a NAL unit holding an SPS, header included but without a start code
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let data = nal_payload(nal, NAL_SPS, "parse SPS")?;
+        let mut reader = BitReader::new(&data, "parse SPS");
+
+        let vps_id = reader.read_bits(4)? as u8;
+        let max_sub_layers_minus1 = reader.read_bits(3)?;
+        // sps_temporal_id_nesting_flag
+        reader.skip_bits(1)?;
+        let profile_tier_level = HevcProfileTierLevel::parse(&mut reader, max_sub_layers_minus1)?;
+
+        let id = reader.read_ue()?;
+        if id > 15 {
+            return Err(reader.error("invalid SPS id"));
+        }
+        let chroma_format = ChromaFormat::from_idc(reader.read_ue()?)
+            .ok_or_else(|| reader.error("invalid chroma format"))?;
+        let separate_colour_plane = chroma_format == ChromaFormat::Yuv444 && reader.read_bit()?;
+        let coded_width = reader.read_ue()?;
+        let coded_height = reader.read_ue()?;
+        if coded_width == 0 || coded_height == 0 {
+            return Err(reader.error("invalid frame size"));
+        }
+
+        let (unit_x, unit_y) = if separate_colour_plane {
+            (1, 1)
+        } else {
+            chroma_format.subsampling()
+        };
+        let conformance_window = if reader.read_bit()? {
+            read_window(&mut reader, unit_x, unit_y, coded_width, coded_height)?
+        } else {
+            FrameCrop::default()
+        };
+
+        let luma = reader.read_ue()?;
+        let chroma = reader.read_ue()?;
+        if luma > 8 || chroma > 8 {
+            return Err(reader.error("invalid bit depth"));
+        }
+
+        let log2_max_pic_order_cnt_lsb = reader.read_ue()? + 4;
+        if log2_max_pic_order_cnt_lsb > 16 {
+            return Err(reader.error("invalid picture order count size"));
+        }
+        let ordering_start = if reader.read_bit()? {
+            0
+        } else {
+            max_sub_layers_minus1
+        };
+        for _ in ordering_start..=max_sub_layers_minus1 {
+            // sps_max_dec_pic_buffering_minus1, sps_max_num_reorder_pics, sps_max_latency_increase_plus1
+            reader.read_ue()?;
+            reader.read_ue()?;
+            reader.read_ue()?;
+        }
+
+        // Coding and transform block sizes, and transform hierarchy depths
+        for _ in 0..6 {
+            reader.read_ue()?;
+        }
+
+        // scaling_list_enabled_flag, sps_scaling_list_data_present_flag
+        if reader.read_bit()? && reader.read_bit()? {
+            skip_scaling_list_data(&mut reader)?;
+        }
+        // amp_enabled_flag, sample_adaptive_offset_enabled_flag
+        reader.skip_bits(2)?;
+        if reader.read_bit()? {
+            // PCM sample bit depths, sizes and pcm_loop_filter_disabled_flag
+            reader.skip_bits(8)?;
+            reader.read_ue()?;
+            reader.read_ue()?;
+            reader.skip_bits(1)?;
+        }
+
+        skip_short_term_ref_pic_sets(&mut reader)?;
+        if reader.read_bit()? {
+            let count = reader.read_ue()?;
+            if count > 32 {
+                return Err(reader.error("too many long term reference pictures"));
+            }
+            for _ in 0..count {
+                // lt_ref_pic_poc_lsb_sps, used_by_curr_pic_lt_sps_flag
+                reader.skip_bits(log2_max_pic_order_cnt_lsb as usize + 1)?;
+            }
+        }
+        // sps_temporal_mvp_enabled_flag, strong_intra_smoothing_enabled_flag
+        reader.skip_bits(2)?;
+
+        let mut vui = None;
+        let mut default_display_window = None;
+        if reader.read_bit()? {
+            let mut parameters = VuiParameters::parse_common(&mut reader)?;
+            // neutral_chroma_indication_flag, field_seq_flag, frame_field_info_present_flag
+            reader.skip_bits(3)?;
+            if reader.read_bit()? {
+                default_display_window = Some(read_window(
+                    &mut reader,
+                    unit_x,
+                    unit_y,
+                    coded_width,
+                    coded_height,
+                )?);
+            }
+            if reader.read_bit()? {
+                parameters.timing = Some(Timing {
+                    num_units_in_tick: reader.read_bits(32)?,
+                    time_scale: reader.read_bits(32)?,
+                });
+            }
+            vui = Some(parameters);
+        }
+
+        Ok(Self {
+            vps_id,
+            max_sub_layers: max_sub_layers_minus1 as u8 + 1,
+            profile_tier_level,
+            id,
+            chroma_format,
+            separate_colour_plane,
+            coded_width,
+            coded_height,
+            conformance_window,
+            bit_depth_luma: luma as u8 + 8,
+            bit_depth_chroma: chroma as u8 + 8,
+            vui,
+            default_display_window,
+        })
+    }
+
+    /// The width of the output frames, once the conformance window is applied
+    pub fn width(&self) -> u32 {
+        self.coded_width - self.conformance_window.left - self.conformance_window.right
+    }
+
+    /// The height of the output frames, once the conformance window is applied
+    pub fn height(&self) -> u32 {
+        self.coded_height - self.conformance_window.top - self.conformance_window.bottom
+    }
+
+    /// The output part of the frames, like the `crop` format key
+    pub fn display_rect(&self) -> Rect {
+        self.conformance_window
+            .display_rect(self.coded_width, self.coded_height)
+    }
+
+    /// The frame rate, from the VUI timing
+    pub fn frame_rate(&self) -> Option<f64> {
+        let timing = self.vui?.timing?;
+        (timing.num_units_in_tick != 0)
+            .then(|| timing.time_scale as f64 / timing.num_units_in_tick as f64)
+    }
+
+    /// Whether the stream needs a Main 10 decoder
+    pub fn is_main10(&self) -> bool {
+        self.profile_tier_level.profile() == HEVC_PROFILE_MAIN_10
+    }
+
+    /// Whether the chroma planes are only subsampled horizontally. Few hardware decoders support it
+    pub fn is_422(&self) -> bool {
+        self.chroma_format == ChromaFormat::Yuv422
+    }
+
+    /// Whether the VUI signals a PQ or HLG transfer
+    pub fn is_hdr(&self) -> bool {
+        self.vui
+            .and_then(|vui| vui.color)
+            .is_some_and(|color| color.is_hdr())
+    }
+
+    /// The profile as a `MediaCodecInfo.CodecProfileLevel` value (like `HEVCProfileMain10`), for the `profile`
+    /// format key. Android has no value for the range extensions profiles
+    pub fn codec_profile(&self) -> Option<i32> {
+        match self.profile_tier_level.profile() {
+            HEVC_PROFILE_MAIN => Some(0x01),
+            HEVC_PROFILE_MAIN_10 => {
+                let pq = self
+                    .vui
+                    .and_then(|vui| vui.color)
+                    .is_some_and(|color| color.transfer == 16);
+                Some(if pq { 0x1000 } else { 0x02 })
+            }
+            HEVC_PROFILE_MAIN_STILL_PICTURE => Some(0x04),
+            _ => None,
+        }
+    }
+
+    /// The level as a `MediaCodecInfo.CodecProfileLevel` value, for the `level` format key
+    pub fn codec_level(&self) -> Option<i32> {
+        self.profile_tier_level.codec_level()
+    }
+}
+
+/// Reads a conformance or display window, with offsets in chroma samples
+fn read_window(
+    reader: &mut BitReader,
+    unit_x: u32,
+    unit_y: u32,
+    width: u32,
+    height: u32,
+) -> Result<FrameCrop, Error> {
+    let window = FrameCrop {
+        left: reader.read_ue()?.saturating_mul(unit_x),
+        right: reader.read_ue()?.saturating_mul(unit_x),
+        top: reader.read_ue()?.saturating_mul(unit_y),
+        bottom: reader.read_ue()?.saturating_mul(unit_y),
+    };
+    if window.left as u64 + window.right as u64 >= width as u64
+        || window.top as u64 + window.bottom as u64 >= height as u64
+    {
+        return Err(reader.error("window is larger than the frame"));
+    }
+
+    Ok(window)
+}
+
+fn skip_scaling_list_data(reader: &mut BitReader) -> Result<(), Error> {
+    for size in 0..4 {
+        for _ in (0..6).step_by(if size == 3 { 3 } else { 1 }) {
+            // scaling_list_pred_mode_flag
+            if !reader.read_bit()? {
+                // scaling_list_pred_matrix_id_delta
+                reader.read_ue()?;
+                continue;
+            }
+
+            if size > 1 {
+                // scaling_list_dc_coef_minus8
+                reader.read_se()?;
+            }
+            for _ in 0..(16 << (2 * size)).min(64) {
+                reader.read_se()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn skip_short_term_ref_pic_sets(reader: &mut BitReader) -> Result<(), Error> {
+    let count = reader.read_ue()?;
+    if count > 64 {
+        return Err(reader.error("too many short term reference picture sets"));
+    }
+
+    // NumDeltaPocs of each set, which predicted sets are built from
+    let mut delta_pocs: Vec<u32> = Vec::with_capacity(count as usize);
+    for index in 0..count as usize {
+        if index != 0 && reader.read_bit()? {
+            // delta_rps_sign, abs_delta_rps_minus1
+            reader.skip_bits(1)?;
+            reader.read_ue()?;
+
+            let mut used = 0;
+            for _ in 0..=delta_pocs[index - 1] {
+                // used_by_curr_pic_flag, or else use_delta_flag
+                if reader.read_bit()? || reader.read_bit()? {
+                    used += 1;
+                }
+            }
+            delta_pocs.push(used);
+        } else {
+            let negative = reader.read_ue()?;
+            let positive = reader.read_ue()?;
+            if negative > 16 || positive > 16 {
+                return Err(reader.error("too many reference pictures"));
+            }
+            for _ in 0..negative + positive {
+                // delta_poc_minus1, used_by_curr_pic_flag
+                reader.read_ue()?;
+                reader.skip_bits(1)?;
+            }
+            delta_pocs.push(negative + positive);
+        }
+    }
+
+    Ok(())
+}
+
+/// An H.265 picture parameter set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcPps {
+    pub id: u32,
+    pub sps_id: u32,
+    pub dependent_slice_segments_enabled: bool,
+    pub output_flag_present: bool,
+    pub num_extra_slice_header_bits: u8,
+    pub sign_data_hiding_enabled: bool,
+    pub cabac_init_present: bool,
+    pub num_ref_idx_l0_default_active: u32,
+    pub num_ref_idx_l1_default_active: u32,
+    pub init_qp: i32,
+    pub constrained_intra_pred: bool,
+    pub transform_skip_enabled: bool,
+    pub cu_qp_delta_enabled: bool,
+    pub cb_qp_offset: i32,
+    pub cr_qp_offset: i32,
+    pub weighted_pred: bool,
+    pub weighted_bipred: bool,
+    pub transquant_bypass_enabled: bool,
+    /// The number of tile columns and rows, when the pictures are split in tiles
+    pub tiles: Option<(u32, u32)>,
+    /// Wavefront parallel processing
+    pub entropy_coding_sync_enabled: bool,
+}
+
+impl HevcPps {
+    /// Parses a NAL unit holding a PPS, header included but without a start code
+    pub fn parse(nal: &[u8]) -> Result<Self, Error> {
+        let data = nal_payload(nal, NAL_PPS, "parse PPS")?;
+        let mut reader = BitReader::new(&data, "parse PPS");
+
+        let id = reader.read_ue()?;
+        let sps_id = reader.read_ue()?;
+        if id > 63 || sps_id > 15 {
+            return Err(reader.error("invalid parameter set id"));
+        }
+        let dependent_slice_segments_enabled = reader.read_bit()?;
+        let output_flag_present = reader.read_bit()?;
+        let num_extra_slice_header_bits = reader.read_bits(3)? as u8;
+        let sign_data_hiding_enabled = reader.read_bit()?;
+        let cabac_init_present = reader.read_bit()?;
+        let num_ref_idx_l0_default_active = reader.read_ue()?.saturating_add(1);
+        let num_ref_idx_l1_default_active = reader.read_ue()?.saturating_add(1);
+        let init_qp = 26 + reader.read_se()?;
+        let constrained_intra_pred = reader.read_bit()?;
+        let transform_skip_enabled = reader.read_bit()?;
+        let cu_qp_delta_enabled = reader.read_bit()?;
+        if cu_qp_delta_enabled {
+            // diff_cu_qp_delta_depth
+            reader.read_ue()?;
+        }
+        let cb_qp_offset = reader.read_se()?;
+        let cr_qp_offset = reader.read_se()?;
+        // pps_slice_chroma_qp_offsets_present_flag
+        reader.skip_bits(1)?;
+        let weighted_pred = reader.read_bit()?;
+        let weighted_bipred = reader.read_bit()?;
+        let transquant_bypass_enabled = reader.read_bit()?;
+        let tiles_enabled = reader.read_bit()?;
+        let entropy_coding_sync_enabled = reader.read_bit()?;
+
+        let tiles = if tiles_enabled {
+            let columns = reader.read_ue()?.saturating_add(1);
+            let rows = reader.read_ue()?.saturating_add(1);
+            if columns > 20 || rows > 22 {
+                return Err(reader.error("too many tiles"));
+            }
+            // uniform_spacing_flag
+            if !reader.read_bit()? {
+                for _ in 0..columns - 1 + rows - 1 {
+                    reader.read_ue()?;
+                }
+            }
+            Some((columns, rows))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            id,
+            sps_id,
+            dependent_slice_segments_enabled,
+            output_flag_present,
+            num_extra_slice_header_bits,
+            sign_data_hiding_enabled,
+            cabac_init_present,
+            num_ref_idx_l0_default_active,
+            num_ref_idx_l1_default_active,
+            init_qp,
+            constrained_intra_pred,
+            transform_skip_enabled,
+            cu_qp_delta_enabled,
+            cb_qp_offset,
+            cr_qp_offset,
+            weighted_pred,
+            weighted_bipred,
+            transquant_bypass_enabled,
+            tiles,
+            entropy_coding_sync_enabled,
+        })
+    }
+}
+
+/// An `HEVCDecoderConfigurationRecord`, the content of an `hvcC` box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcConfigurationRecord<'a> {
+    pub profile_tier_level: HevcProfileTierLevel,
+    pub min_spatial_segmentation: u16,
+    pub parallelism_type: u8,
+    pub chroma_format: ChromaFormat,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    /// In frames per 256 seconds, 0 if unknown
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    /// The size of the length prefixing each NAL unit in the samples
+    pub nal_length_size: u8,
+    /// The parameter sets (and SEI messages) of the stream
+    pub nal_units: Vec<&'a [u8]>,
+}
+
+impl<'a> HevcConfigurationRecord<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = BitReader::new(data, "parse hvcC");
+
+        if reader.read_bits(8)? != 1 {
+            return Err(reader.error("unknown configuration version"));
+        }
+        let profile_tier_level = HevcProfileTierLevel::parse_general(&mut reader)?;
+        reader.skip_bits(4)?;
+        let min_spatial_segmentation = reader.read_bits(12)? as u16;
+        reader.skip_bits(6)?;
+        let parallelism_type = reader.read_bits(2)? as u8;
+        reader.skip_bits(6)?;
+        let chroma_format = ChromaFormat::from_idc(reader.read_bits(2)?)
+            .ok_or_else(|| reader.error("invalid chroma format"))?;
+        reader.skip_bits(5)?;
+        let bit_depth_luma = reader.read_bits(3)? as u8 + 8;
+        reader.skip_bits(5)?;
+        let bit_depth_chroma = reader.read_bits(3)? as u8 + 8;
+        let avg_frame_rate = reader.read_bits(16)? as u16;
+        let constant_frame_rate = reader.read_bits(2)? as u8;
+        let num_temporal_layers = reader.read_bits(3)? as u8;
+        let temporal_id_nested = reader.read_bit()?;
+        let nal_length_size = reader.read_bits(2)? as u8 + 1;
+
+        let mut nal_units = vec![];
+        for _ in 0..reader.read_bits(8)? {
+            // array_completeness, reserved and NAL_unit_type
+            reader.skip_bits(8)?;
+            for _ in 0..reader.read_bits(16)? {
+                let len = reader.read_bits(16)? as usize;
+                let start = reader.position() / 8;
+                if len == 0 {
+                    return Err(reader.error("empty NAL unit"));
+                }
+                reader.skip_bits(len * 8)?;
+                nal_units.push(&data[start..start + len]);
+            }
+        }
+
+        Ok(Self {
+            profile_tier_level,
+            min_spatial_segmentation,
+            parallelism_type,
+            chroma_format,
+            bit_depth_luma,
+            bit_depth_chroma,
+            avg_frame_rate,
+            constant_frame_rate,
+            num_temporal_layers,
+            temporal_id_nested,
+            nal_length_size,
+            nal_units,
+        })
+    }
+}
+
+/// The parameter sets of an H.265 stream, as found in the codec specific data of its track
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HevcParameterSets {
+    pub vps: Vec<HevcVps>,
+    pub sps: Vec<HevcSps>,
+    pub pps: Vec<HevcPps>,
+}
+
+impl HevcParameterSets {
+    /// Parses a buffer of parameter sets, either in Annex B format (like `csd-0`) or as an `hvcC` box.
+    ///
+    /// NAL units other than VPS, SPS and PPS are skipped
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut sets = Self::default();
+        sets.add(data)?;
+        Ok(sets)
+    }
+
+    /// Parses the `csd-0` buffer of an H.265 track format
+    pub fn from_format(format: &MediaFormat) -> Result<Self, Error> {
+        let mut sets = Self::default();
+        for buffer in format_csd(format, "parse hevc csd")? {
+            sets.add(buffer)?;
+        }
+
+        if sets.sps.is_empty() {
+            return Err(Error::new(MediaStatus::ErrorMalformed, "parse hevc csd")
+                .with_detail("missing SPS"));
+        }
+        Ok(sets)
+    }
+
+    /// The first SPS, which describes the stream unless it changes resolution
+    pub fn first_sps(&self) -> Option<&HevcSps> {
+        self.sps.first()
+    }
+
+    fn add(&mut self, data: &[u8]) -> Result<(), Error> {
+        let nals = if data.first() == Some(&1) {
+            HevcConfigurationRecord::parse(data)?.nal_units
+        } else {
+            split_nal_units(data)
+        };
+
+        for nal in nals {
+            match nal_type(nal) {
+                Some(NAL_VPS) => self.vps.push(HevcVps::parse(nal)?),
+                Some(NAL_SPS) => self.sps.push(HevcSps::parse(nal)?),
+                Some(NAL_PPS) => self.pps.push(HevcPps::parse(nal)?),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,811 @@
+//! Typed keys for [MediaFormat](crate::MediaFormat) entries, matching the `AMEDIAFORMAT_KEY_*` constants of the NDK,
+//! along with the `AMEDIACODEC_KEY_*` parameters of a running codec.
+//!
+//! Each key carries the type of its value, so `format.get(keys::CHANNEL_COUNT)` returns an `Option<i32>`. Keys are
+//! gated behind the API level their constant was introduced in, even though older devices may understand them
+//! through the string getters. Those the crate reads itself are always available
+
+use crate::Key;
+#[cfg(feature = "api28")]
+use crate::Rect;
+
+/// `AMEDIAFORMAT_KEY_AAC_PROFILE`
+pub const AAC_PROFILE: Key<i32> = Key::new("aac-profile");
+
+/// `AMEDIAFORMAT_KEY_ALBUM`
+///
+/// Since: API 29, though older versions understand the key too
+pub const ALBUM: Key<String> = Key::new("album");
+
+/// `AMEDIAFORMAT_KEY_ARTIST`
+///
+/// Since: API 29, though older versions understand the key too
+pub const ARTIST: Key<String> = Key::new("artist");
+
+/// `AMEDIAFORMAT_KEY_BITRATE_MODE`
+///
+/// Since: API 28, though older versions understand the key too
+pub const BITRATE_MODE: Key<i32> = Key::new("bitrate-mode");
+
+/// `AMEDIAFORMAT_KEY_BIT_RATE`
+pub const BIT_RATE: Key<i32> = Key::new("bitrate");
+
+/// `AMEDIAFORMAT_KEY_CHANNEL_COUNT`
+pub const CHANNEL_COUNT: Key<i32> = Key::new("channel-count");
+
+/// `AMEDIAFORMAT_KEY_CHANNEL_MASK`
+pub const CHANNEL_MASK: Key<i32> = Key::new("channel-mask");
+
+/// `AMEDIAFORMAT_KEY_COLOR_FORMAT`
+pub const COLOR_FORMAT: Key<i32> = Key::new("color-format");
+
+/// `AMEDIAFORMAT_KEY_COLOR_RANGE`
+///
+/// Since: API 28, though older versions understand the key too
+pub const COLOR_RANGE: Key<i32> = Key::new("color-range");
+
+/// `AMEDIAFORMAT_KEY_COLOR_STANDARD`
+///
+/// Since: API 28, though older versions understand the key too
+pub const COLOR_STANDARD: Key<i32> = Key::new("color-standard");
+
+/// `AMEDIAFORMAT_KEY_COLOR_TRANSFER`
+///
+/// Since: API 28, though older versions understand the key too
+pub const COLOR_TRANSFER: Key<i32> = Key::new("color-transfer");
+
+/// `crop-bottom`, the bottom edge of the crop rectangle before `DISPLAY_CROP`
+pub const CROP_BOTTOM: Key<i32> = Key::new("crop-bottom");
+
+/// `crop-left`, the left edge of the crop rectangle before `DISPLAY_CROP`
+pub const CROP_LEFT: Key<i32> = Key::new("crop-left");
+
+/// `crop-right`, the right edge of the crop rectangle before `DISPLAY_CROP`
+pub const CROP_RIGHT: Key<i32> = Key::new("crop-right");
+
+/// `crop-top`, the top edge of the crop rectangle before `DISPLAY_CROP`
+pub const CROP_TOP: Key<i32> = Key::new("crop-top");
+
+/// `AMEDIAFORMAT_KEY_CSD_0`
+///
+/// Codec specific data, like the SPS of H.264
+///
+/// Since: API 28, though older versions understand the key too
+pub const CSD_0: Key<Vec<u8>> = Key::new("csd-0");
+
+/// `AMEDIAFORMAT_KEY_CSD_1`
+///
+/// Codec specific data, like the PPS of H.264
+///
+/// Since: API 28, though older versions understand the key too
+pub const CSD_1: Key<Vec<u8>> = Key::new("csd-1");
+
+/// `AMEDIAFORMAT_KEY_CSD_2`
+///
+/// Since: API 28, though older versions understand the key too
+pub const CSD_2: Key<Vec<u8>> = Key::new("csd-2");
+
+/// `AMEDIAFORMAT_KEY_DATE`
+///
+/// Since: API 29, though older versions understand the key too
+pub const DATE: Key<String> = Key::new("date");
+
+/// `AMEDIAFORMAT_KEY_DURATION`
+///
+/// In microseconds
+pub const DURATION: Key<i64> = Key::new("durationUs");
+
+/// `AMEDIAFORMAT_KEY_FLAC_COMPRESSION_LEVEL`
+pub const FLAC_COMPRESSION_LEVEL: Key<i32> = Key::new("flac-compression-level");
+
+/// `AMEDIAFORMAT_KEY_FRAME_RATE`
+pub const FRAME_RATE: Key<i32> = Key::new("frame-rate");
+
+/// `AMEDIAFORMAT_KEY_HEIGHT`
+pub const HEIGHT: Key<i32> = Key::new("height");
+
+/// `AMEDIAFORMAT_KEY_IS_ADTS`
+pub const IS_ADTS: Key<i32> = Key::new("is-adts");
+
+/// `AMEDIAFORMAT_KEY_IS_AUTOSELECT`
+pub const IS_AUTOSELECT: Key<i32> = Key::new("is-autoselect");
+
+/// `AMEDIAFORMAT_KEY_IS_DEFAULT`
+pub const IS_DEFAULT: Key<i32> = Key::new("is-default");
+
+/// `AMEDIAFORMAT_KEY_IS_FORCED_SUBTITLE`
+pub const IS_FORCED_SUBTITLE: Key<i32> = Key::new("is-forced-subtitle");
+
+/// `AMEDIAFORMAT_KEY_I_FRAME_INTERVAL`
+///
+/// In seconds
+pub const I_FRAME_INTERVAL: Key<i32> = Key::new("i-frame-interval");
+
+/// `AMEDIAFORMAT_KEY_LANGUAGE`
+pub const LANGUAGE: Key<String> = Key::new("language");
+
+/// `AMEDIAFORMAT_KEY_LEVEL`
+///
+/// Since: API 28, though older versions understand the key too
+pub const LEVEL: Key<i32> = Key::new("level");
+
+/// `AMEDIAFORMAT_KEY_LOCATION`
+///
+/// An ISO 6709 location, see [Location](crate::Location)
+///
+/// Since: API 29, though older versions understand the key too
+pub const LOCATION: Key<String> = Key::new("location");
+
+/// `AMEDIAFORMAT_KEY_MAX_HEIGHT`
+pub const MAX_HEIGHT: Key<i32> = Key::new("max-height");
+
+/// `AMEDIAFORMAT_KEY_MAX_INPUT_SIZE`
+pub const MAX_INPUT_SIZE: Key<i32> = Key::new("max-input-size");
+
+/// `AMEDIAFORMAT_KEY_MAX_WIDTH`
+pub const MAX_WIDTH: Key<i32> = Key::new("max-width");
+
+/// `AMEDIAFORMAT_KEY_MIME`
+pub const MIME: Key<String> = Key::new("mime");
+
+/// `AMEDIAFORMAT_KEY_PCM_ENCODING`
+///
+/// Since: API 28, though older versions understand the key too
+pub const PCM_ENCODING: Key<i32> = Key::new("pcm-encoding");
+
+/// `AMEDIAFORMAT_KEY_PROFILE`
+///
+/// Since: API 28, though older versions understand the key too
+pub const PROFILE: Key<i32> = Key::new("profile");
+
+/// `AMEDIAFORMAT_KEY_PUSH_BLANK_BUFFERS_ON_STOP`
+pub const PUSH_BLANK_BUFFERS_ON_STOP: Key<i32> = Key::new("push-blank-buffers-on-shutdown");
+
+/// `AMEDIAFORMAT_KEY_REPEAT_PREVIOUS_FRAME_AFTER`
+///
+/// In microseconds
+pub const REPEAT_PREVIOUS_FRAME_AFTER: Key<i64> = Key::new("repeat-previous-frame-after");
+
+/// `AMEDIAFORMAT_KEY_ROTATION`
+///
+/// Since: API 28, though older versions understand the key too
+pub const ROTATION: Key<i32> = Key::new("rotation-degrees");
+
+/// `AMEDIAFORMAT_KEY_SAMPLE_RATE`
+pub const SAMPLE_RATE: Key<i32> = Key::new("sample-rate");
+
+/// `AMEDIAFORMAT_KEY_SLICE_HEIGHT`
+///
+/// Since: API 28, though older versions understand the key too
+pub const SLICE_HEIGHT: Key<i32> = Key::new("slice-height");
+
+/// `AMEDIAFORMAT_KEY_STRIDE`
+pub const STRIDE: Key<i32> = Key::new("stride");
+
+/// `AMEDIAFORMAT_KEY_TITLE`
+///
+/// Since: API 29, though older versions understand the key too
+pub const TITLE: Key<String> = Key::new("title");
+
+/// `AMEDIAFORMAT_KEY_WIDTH`
+pub const WIDTH: Key<i32> = Key::new("width");
+
+/// `AMEDIACODEC_KEY_REQUEST_SYNC_FRAME`
+///
+/// Since: API 26
+#[cfg(feature = "api26")]
+pub const REQUEST_SYNC_FRAME: Key<i32> = Key::new("request-sync");
+
+/// `AMEDIACODEC_KEY_SUSPEND`
+///
+/// Since: API 26
+#[cfg(feature = "api26")]
+pub const SUSPEND: Key<i32> = Key::new("drop-input-frames");
+
+/// `AMEDIACODEC_KEY_VIDEO_BITRATE`
+///
+/// Since: API 26
+#[cfg(feature = "api26")]
+pub const VIDEO_BITRATE: Key<i32> = Key::new("video-bitrate");
+
+/// `AMEDIAFORMAT_KEY_AAC_DRC_ATTENUATION_FACTOR`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_DRC_ATTENUATION_FACTOR: Key<i32> = Key::new("aac-drc-cut-level");
+
+/// `AMEDIAFORMAT_KEY_AAC_DRC_BOOST_FACTOR`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_DRC_BOOST_FACTOR: Key<i32> = Key::new("aac-drc-boost-level");
+
+/// `AMEDIAFORMAT_KEY_AAC_DRC_HEAVY_COMPRESSION`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_DRC_HEAVY_COMPRESSION: Key<i32> = Key::new("aac-drc-heavy-compression");
+
+/// `AMEDIAFORMAT_KEY_AAC_DRC_TARGET_REFERENCE_LEVEL`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_DRC_TARGET_REFERENCE_LEVEL: Key<i32> = Key::new("aac-target-ref-level");
+
+/// `AMEDIAFORMAT_KEY_AAC_ENCODED_TARGET_LEVEL`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_ENCODED_TARGET_LEVEL: Key<i32> = Key::new("aac-encoded-target-level");
+
+/// `AMEDIAFORMAT_KEY_AAC_MAX_OUTPUT_CHANNEL_COUNT`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_MAX_OUTPUT_CHANNEL_COUNT: Key<i32> = Key::new("aac-max-output-channel_count");
+
+/// `AMEDIAFORMAT_KEY_AAC_SBR_MODE`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AAC_SBR_MODE: Key<i32> = Key::new("aac-sbr-mode");
+
+/// `AMEDIAFORMAT_KEY_AUDIO_SESSION_ID`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const AUDIO_SESSION_ID: Key<i32> = Key::new("audio-session-id");
+
+/// `AMEDIAFORMAT_KEY_CAPTURE_RATE`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const CAPTURE_RATE: Key<f32> = Key::new("capture-rate");
+
+/// `AMEDIAFORMAT_KEY_COMPLEXITY`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const COMPLEXITY: Key<i32> = Key::new("complexity");
+
+/// `AMEDIAFORMAT_KEY_CSD`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const CSD: Key<Vec<u8>> = Key::new("csd");
+
+/// `AMEDIAFORMAT_KEY_DISPLAY_CROP`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const DISPLAY_CROP: Key<Rect> = Key::new("crop");
+
+/// `AMEDIAFORMAT_KEY_GRID_COLUMNS`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const GRID_COLUMNS: Key<i32> = Key::new("grid-cols");
+
+/// `AMEDIAFORMAT_KEY_GRID_ROWS`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const GRID_ROWS: Key<i32> = Key::new("grid-rows");
+
+/// `AMEDIAFORMAT_KEY_HDR_STATIC_INFO`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const HDR_STATIC_INFO: Key<Vec<u8>> = Key::new("hdr-static-info");
+
+/// `AMEDIAFORMAT_KEY_INTRA_REFRESH_PERIOD`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const INTRA_REFRESH_PERIOD: Key<i32> = Key::new("intra-refresh-period");
+
+/// `AMEDIAFORMAT_KEY_LATENCY`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const LATENCY: Key<i32> = Key::new("latency");
+
+/// `AMEDIAFORMAT_KEY_MAX_FPS_TO_ENCODER`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const MAX_FPS_TO_ENCODER: Key<f32> = Key::new("max-fps-to-encoder");
+
+/// `AMEDIAFORMAT_KEY_MAX_PTS_GAP_TO_ENCODER`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const MAX_PTS_GAP_TO_ENCODER: Key<i64> = Key::new("max-pts-gap-to-encoder");
+
+/// `AMEDIAFORMAT_KEY_OPERATING_RATE`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const OPERATING_RATE: Key<f32> = Key::new("operating-rate");
+
+/// `AMEDIAFORMAT_KEY_PRIORITY`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const PRIORITY: Key<i32> = Key::new("priority");
+
+/// `AMEDIAFORMAT_KEY_TEMPORAL_LAYERING`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const TEMPORAL_LAYERING: Key<String> = Key::new("ts-schema");
+
+/// `AMEDIAFORMAT_KEY_TILE_HEIGHT`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const TILE_HEIGHT: Key<i32> = Key::new("tile-height");
+
+/// `AMEDIAFORMAT_KEY_TILE_WIDTH`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const TILE_WIDTH: Key<i32> = Key::new("tile-width");
+
+/// `AMEDIAFORMAT_KEY_TRACK_ID`
+///
+/// Since: API 28
+#[cfg(feature = "api28")]
+pub const TRACK_ID: Key<i32> = Key::new("track-id");
+
+/// `AMEDIAFORMAT_KEY_ALBUMART`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const ALBUMART: Key<Vec<u8>> = Key::new("albumart");
+
+/// `AMEDIAFORMAT_KEY_ALBUMARTIST`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const ALBUMARTIST: Key<String> = Key::new("albumartist");
+
+/// `AMEDIAFORMAT_KEY_AUDIO_PRESENTATION_INFO`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const AUDIO_PRESENTATION_INFO: Key<String> = Key::new("audio-presentation-info");
+
+/// `AMEDIAFORMAT_KEY_AUTHOR`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const AUTHOR: Key<String> = Key::new("author");
+
+/// `AMEDIAFORMAT_KEY_BITS_PER_SAMPLE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const BITS_PER_SAMPLE: Key<i32> = Key::new("bits-per-sample");
+
+/// `AMEDIAFORMAT_KEY_CDTRACKNUMBER`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CDTRACKNUMBER: Key<String> = Key::new("cdtracknum");
+
+/// `AMEDIAFORMAT_KEY_COMPILATION`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const COMPILATION: Key<String> = Key::new("compilation");
+
+/// `AMEDIAFORMAT_KEY_COMPOSER`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const COMPOSER: Key<String> = Key::new("composer");
+
+/// `AMEDIAFORMAT_KEY_CREATE_INPUT_SURFACE_SUSPENDED`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CREATE_INPUT_SURFACE_SUSPENDED: Key<i32> = Key::new("create-input-buffers-suspended");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_DEFAULT_IV_SIZE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_DEFAULT_IV_SIZE: Key<i32> = Key::new("crypto-default-iv-size");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_ENCRYPTED_BYTE_BLOCK`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_ENCRYPTED_BYTE_BLOCK: Key<i32> = Key::new("crypto-encrypted-byte-block");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_ENCRYPTED_SIZES`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_ENCRYPTED_SIZES: Key<Vec<u8>> = Key::new("crypto-encrypted-sizes");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_IV`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_IV: Key<Vec<u8>> = Key::new("crypto-iv");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_KEY`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_KEY: Key<Vec<u8>> = Key::new("crypto-key");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_MODE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_MODE: Key<i32> = Key::new("crypto-mode");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_PLAIN_SIZES`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_PLAIN_SIZES: Key<Vec<u8>> = Key::new("crypto-plain-sizes");
+
+/// `AMEDIAFORMAT_KEY_CRYPTO_SKIP_BYTE_BLOCK`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CRYPTO_SKIP_BYTE_BLOCK: Key<i32> = Key::new("crypto-skip-byte-block");
+
+/// `AMEDIAFORMAT_KEY_CSD_AVC`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CSD_AVC: Key<Vec<u8>> = Key::new("csd-avc");
+
+/// `AMEDIAFORMAT_KEY_CSD_HEVC`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const CSD_HEVC: Key<Vec<u8>> = Key::new("csd-hevc");
+
+/// `AMEDIAFORMAT_KEY_D263`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const D263: Key<Vec<u8>> = Key::new("d263");
+
+/// `AMEDIAFORMAT_KEY_DISCNUMBER`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const DISCNUMBER: Key<String> = Key::new("discnum");
+
+/// `AMEDIAFORMAT_KEY_DISPLAY_HEIGHT`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const DISPLAY_HEIGHT: Key<i32> = Key::new("display-height");
+
+/// `AMEDIAFORMAT_KEY_DISPLAY_WIDTH`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const DISPLAY_WIDTH: Key<i32> = Key::new("display-width");
+
+/// `AMEDIAFORMAT_KEY_ENCODER_DELAY`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const ENCODER_DELAY: Key<i32> = Key::new("encoder-delay");
+
+/// `AMEDIAFORMAT_KEY_ENCODER_PADDING`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const ENCODER_PADDING: Key<i32> = Key::new("encoder-padding");
+
+/// `AMEDIAFORMAT_KEY_ESDS`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const ESDS: Key<Vec<u8>> = Key::new("esds");
+
+/// `AMEDIAFORMAT_KEY_EXIF_OFFSET`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const EXIF_OFFSET: Key<i64> = Key::new("exif-offset");
+
+/// `AMEDIAFORMAT_KEY_EXIF_SIZE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const EXIF_SIZE: Key<i64> = Key::new("exif-size");
+
+/// `AMEDIAFORMAT_KEY_FRAME_COUNT`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const FRAME_COUNT: Key<i32> = Key::new("frame-count");
+
+/// `AMEDIAFORMAT_KEY_GENRE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const GENRE: Key<String> = Key::new("genre");
+
+/// `AMEDIAFORMAT_KEY_HAPTIC_CHANNEL_COUNT`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const HAPTIC_CHANNEL_COUNT: Key<i32> = Key::new("haptic-channel-count");
+
+/// `AMEDIAFORMAT_KEY_ICC_PROFILE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const ICC_PROFILE: Key<Vec<u8>> = Key::new("icc-profile");
+
+/// `AMEDIAFORMAT_KEY_IS_SYNC_FRAME`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const IS_SYNC_FRAME: Key<i32> = Key::new("is-sync-frame");
+
+/// `AMEDIAFORMAT_KEY_LOOP`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const LOOP: Key<i32> = Key::new("loop");
+
+/// `AMEDIAFORMAT_KEY_LYRICIST`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const LYRICIST: Key<String> = Key::new("lyricist");
+
+/// `AMEDIAFORMAT_KEY_MANUFACTURER`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const MANUFACTURER: Key<String> = Key::new("manufacturer");
+
+/// `AMEDIAFORMAT_KEY_MAX_BIT_RATE`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const MAX_BIT_RATE: Key<i32> = Key::new("max-bitrate");
+
+/// `AMEDIAFORMAT_KEY_MPEG2_STREAM_HEADER`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const MPEG2_STREAM_HEADER: Key<Vec<u8>> = Key::new("mpeg2-stream-header");
+
+/// `AMEDIAFORMAT_KEY_MPEG_USER_DATA`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const MPEG_USER_DATA: Key<Vec<u8>> = Key::new("mpeg-user-data");
+
+/// `AMEDIAFORMAT_KEY_PCM_BIG_ENDIAN`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const PCM_BIG_ENDIAN: Key<i32> = Key::new("pcm-big-endian");
+
+/// `AMEDIAFORMAT_KEY_PSSH`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const PSSH: Key<Vec<u8>> = Key::new("pssh");
+
+/// `AMEDIAFORMAT_KEY_SAR_HEIGHT`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const SAR_HEIGHT: Key<i32> = Key::new("sar-height");
+
+/// `AMEDIAFORMAT_KEY_SAR_WIDTH`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const SAR_WIDTH: Key<i32> = Key::new("sar-width");
+
+/// `AMEDIAFORMAT_KEY_SEI`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const SEI: Key<Vec<u8>> = Key::new("sei");
+
+/// `AMEDIACODEC_KEY_SUSPEND_TIME`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const SUSPEND_TIME: Key<i64> = Key::new("drop-start-time-us");
+
+/// `AMEDIAFORMAT_KEY_TARGET_TIME`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const TARGET_TIME: Key<i64> = Key::new("target-time");
+
+/// `AMEDIAFORMAT_KEY_TEMPORAL_LAYER_COUNT`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const TEMPORAL_LAYER_COUNT: Key<i32> = Key::new("temporal-layer-count");
+
+/// `AMEDIAFORMAT_KEY_TEMPORAL_LAYER_ID`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const TEMPORAL_LAYER_ID: Key<i32> = Key::new("temporal-layer-id");
+
+/// `AMEDIAFORMAT_KEY_TEXT_FORMAT_DATA`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const TEXT_FORMAT_DATA: Key<Vec<u8>> = Key::new("text-format-data");
+
+/// `AMEDIAFORMAT_KEY_THUMBNAIL_CSD_HEVC`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const THUMBNAIL_CSD_HEVC: Key<Vec<u8>> = Key::new("thumbnail-csd-hevc");
+
+/// `AMEDIAFORMAT_KEY_THUMBNAIL_HEIGHT`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const THUMBNAIL_HEIGHT: Key<i32> = Key::new("thumbnail-height");
+
+/// `AMEDIAFORMAT_KEY_THUMBNAIL_TIME`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const THUMBNAIL_TIME: Key<i64> = Key::new("thumbnail-time");
+
+/// `AMEDIAFORMAT_KEY_THUMBNAIL_WIDTH`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const THUMBNAIL_WIDTH: Key<i32> = Key::new("thumbnail-width");
+
+/// `AMEDIAFORMAT_KEY_TIME_US`
+///
+/// In microseconds
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const TIME_US: Key<i64> = Key::new("timeUs");
+
+/// `AMEDIAFORMAT_KEY_VALID_SAMPLES`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const VALID_SAMPLES: Key<i32> = Key::new("valid-samples");
+
+/// `AMEDIAFORMAT_KEY_YEAR`
+///
+/// Since: API 29
+#[cfg(feature = "api29")]
+pub const YEAR: Key<String> = Key::new("year");
+
+/// `AMEDIAFORMAT_KEY_LOW_LATENCY`
+///
+/// Since: API 30
+#[cfg(feature = "api30")]
+pub const LOW_LATENCY: Key<i32> = Key::new("low-latency");
+
+/// `AMEDIAFORMAT_KEY_HDR10_PLUS_INFO`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const HDR10_PLUS_INFO: Key<Vec<u8>> = Key::new("hdr10-plus-info");
+
+/// `AMEDIAFORMAT_KEY_LAST_SAMPLE_INDEX_IN_CHUNK`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const LAST_SAMPLE_INDEX_IN_CHUNK: Key<i32> = Key::new("last-sample-index-in-chunk");
+
+/// `AMEDIAFORMAT_KEY_MPEGH_COMPATIBLE_SETS`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const MPEGH_COMPATIBLE_SETS: Key<Vec<u8>> = Key::new("mpegh-compatible-sets");
+
+/// `AMEDIAFORMAT_KEY_MPEGH_PROFILE_LEVEL_INDICATION`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const MPEGH_PROFILE_LEVEL_INDICATION: Key<i32> = Key::new("mpegh-profile-level-indication");
+
+/// `AMEDIAFORMAT_KEY_MPEGH_REFERENCE_CHANNEL_LAYOUT`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const MPEGH_REFERENCE_CHANNEL_LAYOUT: Key<i32> = Key::new("mpegh-reference-channel-layout");
+
+/// `AMEDIAFORMAT_KEY_SAMPLE_FILE_OFFSET`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const SAMPLE_FILE_OFFSET: Key<i64> = Key::new("sample-file-offset");
+
+/// `AMEDIAFORMAT_KEY_SAMPLE_TIME_BEFORE_APPEND`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const SAMPLE_TIME_BEFORE_APPEND: Key<i64> = Key::new("sample-time-before-append");
+
+/// `AMEDIAFORMAT_KEY_SLOW_MOTION_MARKERS`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const SLOW_MOTION_MARKERS: Key<Vec<u8>> = Key::new("slow-motion-markers");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_B_MAX`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_B_MAX: Key<i32> = Key::new("video-qp-b-max");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_B_MIN`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_B_MIN: Key<i32> = Key::new("video-qp-b-min");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_I_MAX`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_I_MAX: Key<i32> = Key::new("video-qp-i-max");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_I_MIN`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_I_MIN: Key<i32> = Key::new("video-qp-i-min");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_MAX`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_MAX: Key<i32> = Key::new("video-qp-max");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_MIN`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_MIN: Key<i32> = Key::new("video-qp-min");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_P_MAX`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_P_MAX: Key<i32> = Key::new("video-qp-p-max");
+
+/// `AMEDIAFORMAT_KEY_VIDEO_QP_P_MIN`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const VIDEO_QP_P_MIN: Key<i32> = Key::new("video-qp-p-min");
+
+/// `AMEDIAFORMAT_KEY_XMP_OFFSET`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const XMP_OFFSET: Key<i64> = Key::new("xmp-offset");
+
+/// `AMEDIAFORMAT_KEY_XMP_SIZE`
+///
+/// Since: API 31
+#[cfg(feature = "api31")]
+pub const XMP_SIZE: Key<i64> = Key::new("xmp-size");
@@ -4,30 +4,60 @@
 //! ### Decoding
 //! ```edition2021
 //!
+//! # use log::debug;
 //! # #[no_mangle]
 //! # extern "C" fn process() {
-//!     use mediacodec::{Frame, MediaCodec, MediaExtractor, SampleFormat, VideoFrame};
+//!     use std::time::Duration;
+//!
+//!     use mediacodec::{CodecOutputBuffer, Frame, MediaCodec, MediaExtractor, SampleFormat, VideoFrame};
+//!
+//!     fn handle_output(mut buffer: CodecOutputBuffer) {
+//!         if let Some(ref frame) = buffer.frame() {
+//!             match frame {
+//!                 Frame::Audio(value) => match value.format() {
+//!                     SampleFormat::S16(_) => {
+//!                         // Do something with the audio frame
+//!                     }
+//!                     SampleFormat::F32(_) => {
+//!                         // Do something with the audio frame
+//!                     }
+//!                 },
+//!                 Frame::Video(value) => match value {
+//!                     VideoFrame::Hardware => {
+//!                         // Nothing TODO. The frame will be rendered
+//!                     }
+//!                     VideoFrame::RawFrame(_) => {
+//!                         // Read out the raw buffers or something
+//!                     }
+//!                 },
+//!             }
+//!         }
+//!
+//!         // Set the buffer to render when dropped. Only applicable to video codecs that have a hardware buffer (i.e, attached to a native window)
+//!         buffer.set_render(true);
+//!     }
+//!
 //!     let mut extractor = MediaExtractor::from_url("/path/to/a/resource").unwrap();
-
+//!
 //!     debug!("Track count: {}", extractor.track_count());
-
+//!
 //!     let mut decoders = vec![];
-
+//!
 //!     for i in 0..extractor.track_count() {
 //!         let format = extractor.track_format(i).unwrap();
-//!         debug!("{}", format.to_string());
+//!         debug!("{format}");
 //!         let mime_type = format.get_string("mime").unwrap();
 //!         let mut codec = MediaCodec::create_decoder(&mime_type).unwrap();
-
+//!
 //!         codec.init(&format, None, 0).unwrap();
-
+//!
 //!         codec.start().unwrap();
 //!         decoders.push(codec);
-//!         extractor.select_track(i);
+//!         extractor.select_track(i).unwrap();
 //!     }
-
-//!     while extractor.has_next() {
-//!         // 1. Get the track index
+//!
+//!     loop {
+//!         // 1. Get the track index. It's negative once every packet has been read
 //!         let index = extractor.track_index();
 //!
 //!         if index < 0 {
@@ -36,48 +66,28 @@
 //!
 //!         let codec = &mut decoders[index as usize];
 //!
-//!         // Fetch the codec's input buffer
-//!         while let Ok(mut buffer) = codec.dequeue_input() {
-//!             if !extractor.read_next(&mut buffer) {
-//!                 debug!(
-//!                     "MediaExtractor.read_next() returned false! has_next(): {}",
-//!                     extractor.has_next()
-//!                 );
-//!                 break; // VERY IMPORTANT, there's nothing else to DO, so break!!!
-//!             }
+//!         // Fetch the codec's input buffers, as long as the packets belong to its track
+//!         while extractor.track_index() == index {
+//!             let Ok(mut buffer) = codec.dequeue_input() else {
+//!                 break;
+//!             };
 //!
 //!             // When the buffer gets dropped (here), the buffer will be queued back to MediaCodec
 //!             // And we don't have to do anything else
+//!             extractor.read_next(&mut buffer).unwrap();
 //!         }
 //!
 //!         // Check for output
-//!         let output_fmt = codec.output_format().unwrap();
-//!         while let Ok(mut buffer) = codec.dequeue_output() {
-//!             if let Some(ref frame) = buffer.frame() {
-//!                 match frame {
-//!                     Frame::Audio(value) => match value.format() {
-//!                         SampleFormat::S16(_) => {
-//!                             // Do something with the audio frame
-//!                         }
-//!                         SampleFormat::F32(_) => {
-//!                             // Do something with the audio frame
-//!                         }
-//!                     },
-//!                     Frame::Video(value) => match value {
-//!                         VideoFrame::Hardware => {
-//!                             // Nothing TODO. The frame will be rendered
-//!                         }
-//!                         VideoFrame::RawFrame(_) => {
-//!                             // Read out the raw buffers or something
-//!                         }
-//!                     },
-//!                 }
-//!             }
-//!
-//!             // Set the buffer to render when dropped. Only applicable to video codecs that have a hardware buffer (i.e, attached to a native window)
-//!             buffer.set_render(true);
+//!         while let Ok(buffer) = codec.dequeue_output() {
+//!             handle_output(buffer);
 //!         }
 //!     }
+//!
+//!     // Tell every decoder the stream ended, and pull out the frames they still hold
+//!     for codec in &mut decoders {
+//!         codec.signal_end_of_stream(Duration::from_secs(1)).unwrap();
+//!         codec.drain(Duration::from_secs(1), handle_output).unwrap();
+//!     }
 //! # }
 //! ```
 //!
@@ -94,7 +104,7 @@
 //!
 //!     for i in 0..extractor.track_count() {
 //!         let format = extractor.track_format(i).unwrap();
-//!         debug!("{}", format.to_string());
+//!         debug!("{format}");
 //!         let mime_type = format.get_string("mime").unwrap();
 //!         extractor.select_track(i);
 //!     }
@@ -114,20 +124,51 @@
 //! ```
 // #![cfg(os = "android")]
 
+#[cfg(feature = "async")]
+mod async_codec;
+mod avc;
+mod backend;
 mod codec;
 mod crypto;
+mod csd;
+mod data_source;
+mod drm;
 mod error;
 mod extractor;
 mod format;
+mod format_builder;
+mod format_description;
+mod format_map;
+mod hevc;
+pub mod keys;
+mod metadata;
+#[cfg(mock_ndk)]
+pub mod mock;
 mod muxer;
 mod native_window;
+mod packet;
+mod passthrough;
 mod samples;
+mod seek;
 
+pub use avc::*;
+pub use backend::*;
 pub use codec::*;
 pub use crypto::*;
+pub use csd::*;
+pub use data_source::*;
+pub use drm::*;
 pub use error::*;
 pub use extractor::*;
 pub use format::*;
+pub use format_builder::*;
+pub use format_description::*;
+pub use format_map::*;
+pub use hevc::*;
+pub use metadata::*;
 pub use muxer::*;
 pub use native_window::*;
+pub use packet::*;
+pub use passthrough::*;
 pub use samples::*;
+pub use seek::*;
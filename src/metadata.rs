@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use crate::{keys, MediaFormat};
+
+/// A geographic location, as stored in the container
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    /// In degrees
+    pub latitude: f64,
+    /// In degrees
+    pub longitude: f64,
+    /// In meters, if the container has it
+    pub altitude: Option<f64>,
+}
+
+impl Location {
+    /// Parses an ISO 6709 location, like `+52.5200+013.4050/` or `+52.5200+013.4050+034.000/`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().trim_end_matches('/');
+
+        // Every component starts with its sign
+        let mut components = vec![];
+        let mut start = 0;
+        for (index, char) in value.char_indices().skip(1) {
+            if char == '+' || char == '-' {
+                components.push(&value[start..index]);
+                start = index;
+            }
+        }
+        components.push(&value[start..]);
+
+        if !components.iter().all(|c| c.starts_with(['+', '-'])) {
+            return None;
+        }
+
+        let numbers = components
+            .iter()
+            .map(|component| component.parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        match numbers[..] {
+            [latitude, longitude] => Some(Self {
+                latitude,
+                longitude,
+                altitude: None,
+            }),
+            [latitude, longitude, altitude] => Some(Self {
+                latitude,
+                longitude,
+                altitude: Some(altitude),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Container level metadata, read by `MediaExtractor::metadata` (API 28+)
+#[derive(Debug)]
+pub struct FileMetadata {
+    format: MediaFormat,
+    rotation: Option<i32>,
+}
+
+impl FileMetadata {
+    /// Wraps a container format, like one returned by `MediaExtractor::file_format` (API 28+)
+    pub fn new(format: MediaFormat) -> Self {
+        Self {
+            format,
+            rotation: None,
+        }
+    }
+
+    /// Sets the rotation to fall back to, when the container doesn't have one
+    #[cfg(feature = "api28")]
+    pub(crate) fn with_track_rotation(mut self, rotation: Option<i32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// The raw container format, holding every key the extractor found
+    pub fn format(&self) -> &MediaFormat {
+        &self.format
+    }
+
+    /// Unwraps the raw container format
+    pub fn into_format(self) -> MediaFormat {
+        self.format
+    }
+
+    /// The duration of the media, as stored in the container
+    pub fn duration(&self) -> Option<Duration> {
+        let duration = self.format.get(keys::DURATION)?;
+        Some(Duration::from_micros(u64::try_from(duration).ok()?))
+    }
+
+    /// The title, as stored in the container
+    pub fn title(&self) -> Option<String> {
+        self.format.get(keys::TITLE)
+    }
+
+    /// The performing artist
+    pub fn artist(&self) -> Option<String> {
+        self.format.get(keys::ARTIST)
+    }
+
+    /// The album the media belongs to
+    pub fn album(&self) -> Option<String> {
+        self.format.get(keys::ALBUM)
+    }
+
+    /// The creation time, as stored in the container (like `20230401T120000.000Z` for MP4)
+    pub fn creation_time(&self) -> Option<String> {
+        self.format.get(keys::DATE)
+    }
+
+    /// Where the media was recorded
+    pub fn location(&self) -> Option<Location> {
+        Location::parse(&self.format.get(keys::LOCATION)?)
+    }
+
+    /// The clockwise rotation to apply to the video when displaying it, in degrees.
+    ///
+    /// Read from the container, or from the first video track that has one
+    pub fn rotation(&self) -> Option<i32> {
+        self.format.get(keys::ROTATION).or(self.rotation)
+    }
+}
@@ -0,0 +1,1196 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    ffi::{c_void, CStr, CString},
+    os::raw::c_char,
+    ptr::null_mut,
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+    thread::JoinHandle,
+};
+
+use super::{format::Format, native_window, Failures};
+use crate::{
+    _AMediaCodecOnAsyncNotifyCallback, AMediaCodec, AMediaCodecCryptoInfo, AMediaCrypto,
+    AMediaFormat, ANativeWindow, BufferFlag, BufferInfo, CryptoInfo, CryptoInfoMode,
+    CryptoInfoPattern, InfoFlag, MediaFormat, MediaStatus, SubSample,
+};
+
+/// The lifecycle state of a mock codec, as seen by the NDK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockCodecState {
+    Uninitialized,
+    Configured,
+    Executing,
+    Released,
+}
+
+/// A buffer that went through a mock codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockBuffer {
+    pub data: Vec<u8>,
+    pub time_us: i64,
+    pub flags: u32,
+}
+
+/// An output buffer handed back to a mock codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReleasedOutput {
+    pub time_us: i64,
+    pub rendered: bool,
+}
+
+#[derive(Debug)]
+enum OutputEvent {
+    Buffer(MockBuffer),
+    FormatChanged(Format),
+    BuffersChanged,
+    TryAgain,
+}
+
+/// An event reported through the asynchronous callbacks
+#[derive(Debug)]
+enum Notification {
+    InputAvailable(usize),
+    OutputAvailable(usize, BufferInfo),
+    FormatChanged(Format),
+    Error(MediaStatus, i32, CString),
+}
+
+/// The thread the asynchronous callbacks are fired on, like the NDK's internal looper
+#[derive(Debug)]
+struct Notifier {
+    sender: mpsc::Sender<(u64, Notification)>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct InputSlot {
+    data: Vec<u8>,
+    dequeued: bool,
+}
+
+#[derive(Debug)]
+struct CodecData {
+    state: MockCodecState,
+    fail_creation: bool,
+    passthrough: bool,
+    input_buffers: usize,
+    buffer_capacity: usize,
+    output_override: Option<Format>,
+
+    encoder: bool,
+    name: String,
+    surface: *mut ANativeWindow,
+    crypto: bool,
+    input_format: Option<Format>,
+    output_format: Option<Format>,
+    inputs: Vec<InputSlot>,
+    outputs: BTreeMap<usize, MockBuffer>,
+    events: VecDeque<OutputEvent>,
+    failures: Failures,
+
+    notifier: Option<Notifier>,
+    // Bumped whenever buffers are reset, so notifications posted before that get dropped
+    generation: u64,
+    // In asynchronous mode, a flushed codec has to be started again to resume
+    flushed: bool,
+    input_eos: bool,
+
+    queued: Vec<MockBuffer>,
+    secure: Vec<CryptoInfo>,
+    released: Vec<ReleasedOutput>,
+    parameters: Vec<Format>,
+}
+
+// The surface pointer is only ever touched while holding the lock
+unsafe impl Send for CodecData {}
+
+/// A scriptable stand-in for an `AMediaCodec` instance.
+///
+/// Call [install](MockCodec::install) before creating a `MediaCodec`, and the next codec created on the same thread will be
+/// backed by this mock. Clones share the same codec, so a test can keep one around to script and inspect it.
+///
+/// Codecs created without an installed mock behave like the default one: a passthrough codec that copies each queued input
+/// buffer to its output.
+#[derive(Debug, Clone)]
+pub struct MockCodec {
+    data: Arc<Mutex<CodecData>>,
+}
+
+thread_local! {
+    static PENDING: RefCell<VecDeque<MockCodec>> = const { RefCell::new(VecDeque::new()) };
+}
+
+impl Default for MockCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockCodec {
+    /// Creates a passthrough codec with 4 input buffers of 64KiB each
+    pub fn new() -> Self {
+        Self {
+            data: Arc::new(Mutex::new(CodecData {
+                state: MockCodecState::Uninitialized,
+                fail_creation: false,
+                passthrough: true,
+                input_buffers: 4,
+                buffer_capacity: 64 * 1024,
+                output_override: None,
+                encoder: false,
+                name: String::new(),
+                surface: null_mut(),
+                crypto: false,
+                input_format: None,
+                output_format: None,
+                inputs: vec![],
+                outputs: BTreeMap::new(),
+                events: VecDeque::new(),
+                failures: Failures::default(),
+                notifier: None,
+                generation: 0,
+                flushed: false,
+                input_eos: false,
+                queued: vec![],
+                secure: vec![],
+                released: vec![],
+                parameters: vec![],
+            })),
+        }
+    }
+
+    /// Makes the next codec created on this thread use this mock
+    pub fn install(&self) {
+        PENDING.with(|pending| pending.borrow_mut().push_back(self.clone()));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CodecData> {
+        self.data.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Makes the codec creation fail, the way it would for an unsupported mime type
+    pub fn fail_creation(self) -> Self {
+        self.lock().fail_creation = true;
+        self
+    }
+
+    /// Whether queued input buffers should be copied to the output. Enabled by default
+    pub fn passthrough(self, passthrough: bool) -> Self {
+        self.lock().passthrough = passthrough;
+        self
+    }
+
+    /// The number of input buffers the codec hands out at once
+    pub fn input_buffers(self, count: usize) -> Self {
+        self.lock().input_buffers = count;
+        self
+    }
+
+    /// The capacity of each input buffer
+    pub fn buffer_capacity(self, capacity: usize) -> Self {
+        self.lock().buffer_capacity = capacity;
+        self
+    }
+
+    /// Entries to add to the configured format when reporting the output format
+    pub fn output_format(self, format: &MediaFormat) -> Self {
+        self.lock().output_override = Some(Format::copy_of(format));
+        self
+    }
+
+    /// Makes the next call to `function` (e.g. `"AMediaCodec_start"`) fail with `status`
+    pub fn fail_next(&self, function: &str, status: MediaStatus) {
+        self.lock().failures.push(function, status);
+    }
+
+    /// Queues an output buffer
+    pub fn push_output(&self, data: &[u8], time_us: i64, flags: u32) {
+        let mut codec = self.lock();
+        codec.events.push_back(OutputEvent::Buffer(MockBuffer {
+            data: data.to_vec(),
+            time_us,
+            flags,
+        }));
+        codec.pump();
+    }
+
+    /// Queues an output format change. The entries of `format` are added to the current output format
+    pub fn push_format_change(&self, format: &MediaFormat) {
+        let mut data = self.lock();
+        data.events
+            .push_back(OutputEvent::FormatChanged(Format::copy_of(format)));
+        data.pump();
+    }
+
+    /// Reports an error through the asynchronous callback. Codecs in synchronous mode ignore this
+    pub fn push_error(&self, error: MediaStatus, action_code: i32, detail: &str) {
+        let detail = CString::new(detail).unwrap_or_default();
+        self.lock()
+            .notify(Notification::Error(error, action_code, detail));
+    }
+
+    /// Queues an `OutputBuffersChanged` notification
+    pub fn push_buffers_changed(&self) {
+        self.lock().events.push_back(OutputEvent::BuffersChanged);
+    }
+
+    /// Queues a `TryAgainLater` result, even if there's output available after it
+    pub fn push_try_again(&self) {
+        self.lock().events.push_back(OutputEvent::TryAgain);
+    }
+
+    /// The current state of the codec
+    pub fn state(&self) -> MockCodecState {
+        self.lock().state
+    }
+
+    /// Whether the codec was created or configured as an encoder
+    pub fn is_encoder(&self) -> bool {
+        self.lock().encoder
+    }
+
+    /// The format the codec was configured with
+    pub fn configured_format(&self) -> Option<MediaFormat> {
+        self.lock()
+            .input_format
+            .as_ref()
+            .map(Format::to_media_format)
+    }
+
+    /// Every input buffer queued to the codec so far
+    pub fn queued_inputs(&self) -> Vec<MockBuffer> {
+        self.lock().queued.clone()
+    }
+
+    /// Whether the codec was configured with an `AMediaCrypto`
+    pub fn has_crypto(&self) -> bool {
+        self.lock().crypto
+    }
+
+    /// The crypto info of every secure input buffer queued so far
+    pub fn secure_inputs(&self) -> Vec<CryptoInfo> {
+        self.lock().secure.clone()
+    }
+
+    /// Every output buffer released back to the codec so far
+    pub fn released_outputs(&self) -> Vec<ReleasedOutput> {
+        self.lock().released.clone()
+    }
+
+    /// Every format passed to `AMediaCodec_setParameters` so far
+    pub fn parameters(&self) -> Vec<MediaFormat> {
+        self.lock()
+            .parameters
+            .iter()
+            .map(Format::to_media_format)
+            .collect()
+    }
+}
+
+/// The mock `AMediaCodec` the NDK functions hand out
+struct Instance {
+    mock: MockCodec,
+    name: CString,
+}
+
+unsafe fn instance<'a>(codec: *mut AMediaCodec) -> &'a Instance {
+    &*(codec as *const Instance)
+}
+
+unsafe fn lock<'a>(codec: *mut AMediaCodec) -> MutexGuard<'a, CodecData> {
+    instance(codec).mock.lock()
+}
+
+fn create(name: String, encoder: bool) -> *mut AMediaCodec {
+    let mock = PENDING
+        .with(|pending| pending.borrow_mut().pop_front())
+        .unwrap_or_default();
+
+    {
+        let mut data = mock.lock();
+        if data.fail_creation {
+            return null_mut();
+        }
+
+        data.encoder = encoder;
+        data.name = name.clone();
+    }
+
+    let name = CString::new(name).unwrap_or_default();
+    Box::into_raw(Box::new(Instance { mock, name })).cast()
+}
+
+unsafe fn string(value: *const c_char) -> String {
+    CStr::from_ptr(value).to_string_lossy().to_string()
+}
+
+fn status(result: Result<(), MediaStatus>) -> MediaStatus {
+    result.err().unwrap_or(MediaStatus::Ok)
+}
+
+impl CodecData {
+    fn fail(&mut self, function: &str) -> Result<(), MediaStatus> {
+        match self.failures.take(function) {
+            Some(status) => Err(status),
+            None => Ok(()),
+        }
+    }
+
+    fn expect(&self, state: MockCodecState) -> Result<(), MediaStatus> {
+        if self.state != state {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        Ok(())
+    }
+
+    /// Drops every buffer and notification the codec holds
+    fn reset_buffers(&mut self) {
+        for slot in &mut self.inputs {
+            slot.dequeued = false;
+        }
+        self.outputs.clear();
+        self.events.clear();
+        self.generation += 1;
+        self.input_eos = false;
+    }
+
+    fn queue(&mut self, buffer: MockBuffer) {
+        self.input_eos |= BufferFlag::EndOfStream.is_contained_in(buffer.flags as i32);
+
+        if self.passthrough {
+            self.events.push_back(OutputEvent::Buffer(buffer.clone()));
+        }
+
+        self.queued.push(buffer);
+        self.pump();
+    }
+
+    fn is_async(&self) -> bool {
+        self.notifier.is_some()
+    }
+
+    fn notify(&self, notification: Notification) {
+        if let Some(ref notifier) = self.notifier {
+            // The thread only goes away along with the codec
+            let _ = notifier.sender.send((self.generation, notification));
+        }
+    }
+
+    /// Hands every free input buffer to the asynchronous callback
+    fn offer_inputs(&mut self) {
+        if !self.is_async() || self.flushed || self.input_eos {
+            return;
+        }
+
+        for index in 0..self.inputs.len() {
+            if !self.inputs[index].dequeued {
+                self.inputs[index].dequeued = true;
+                self.notify(Notification::InputAvailable(index));
+            }
+        }
+    }
+
+    /// Reports pending output events through the asynchronous callback, and hands out the input buffers freed up
+    fn pump(&mut self) {
+        if !self.is_async() || self.state != MockCodecState::Executing || self.flushed {
+            return;
+        }
+
+        while let Some(event) = self.events.pop_front() {
+            match event {
+                OutputEvent::Buffer(buffer) => {
+                    let (index, info) = self.add_output(buffer);
+                    self.notify(Notification::OutputAvailable(index, info));
+                }
+                OutputEvent::FormatChanged(format) => {
+                    let output = self.output_format.get_or_insert_with(Format::default);
+                    output.extend(&format);
+
+                    let output = output.clone();
+                    self.notify(Notification::FormatChanged(output));
+                }
+                // There's nothing to report for these in asynchronous mode
+                OutputEvent::BuffersChanged | OutputEvent::TryAgain => {}
+            }
+        }
+
+        self.offer_inputs();
+    }
+
+    /// Makes `buffer` available as an output buffer, returning its index and information
+    fn add_output(&mut self, buffer: MockBuffer) -> (usize, BufferInfo) {
+        let index = (0..)
+            .find(|index| !self.outputs.contains_key(index))
+            .unwrap_or_default();
+
+        let info = BufferInfo {
+            offset: 0,
+            size: buffer.data.len() as i32,
+            presentation_time_us: buffer.time_us,
+            flags: buffer.flags,
+        };
+        self.outputs.insert(index, buffer);
+
+        (index, info)
+    }
+}
+
+/// Fires the notifications posted by a codec, until the codec goes away
+fn notify_loop(
+    mock: MockCodec,
+    codec: usize,
+    callback: _AMediaCodecOnAsyncNotifyCallback,
+    userdata: usize,
+    receiver: mpsc::Receiver<(u64, Notification)>,
+) {
+    let codec = codec as *const AMediaCodec;
+    let userdata = userdata as *const c_void;
+
+    for (generation, notification) in receiver {
+        // Buffers handed out before a flush or a stop are no longer valid
+        if mock.lock().generation != generation {
+            continue;
+        }
+
+        match notification {
+            Notification::InputAvailable(index) => {
+                (callback.on_async_input_available)(codec, userdata, index as i32)
+            }
+            Notification::OutputAvailable(index, info) => {
+                (callback.on_async_output_available)(codec, userdata, index as i32, &info)
+            }
+            Notification::FormatChanged(format) => {
+                // The callback owns the format
+                (callback.on_async_format_changed)(codec, userdata, format.into_raw())
+            }
+            Notification::Error(error, action_code, detail) => (callback.on_async_error)(
+                codec,
+                userdata,
+                error as i32,
+                action_code,
+                detail.as_ptr(),
+            ),
+        }
+    }
+}
+
+/// Stops the notification thread, waiting for the callback in flight to return
+fn stop_notifier(mock: &MockCodec) {
+    let notifier = mock.lock().notifier.take();
+
+    if let Some(Notifier { sender, thread }) = notifier {
+        drop(sender);
+        let _ = thread.join();
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_createCodecByName(name: *const c_char) -> *mut AMediaCodec {
+    let name = string(name);
+    let encoder = name.contains("encoder");
+    create(name, encoder)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_createDecoderByType(mime_type: *const c_char) -> *mut AMediaCodec {
+    create(format!("mock.{}.decoder", string(mime_type)), false)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_createEncoderByType(mime_type: *const c_char) -> *mut AMediaCodec {
+    create(format!("mock.{}.encoder", string(mime_type)), true)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_delete(codec: *mut AMediaCodec) -> MediaStatus {
+    if codec.is_null() {
+        return MediaStatus::ErrorInvalidObject;
+    }
+
+    let instance = Box::from_raw(codec as *mut Instance);
+    stop_notifier(&instance.mock);
+
+    let mut data = instance.mock.lock();
+    data.state = MockCodecState::Released;
+    data.reset_buffers();
+
+    if !data.surface.is_null() {
+        native_window::release(data.surface);
+        data.surface = null_mut();
+    }
+
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_configure(
+    codec: *mut AMediaCodec,
+    format: *const AMediaFormat,
+    surface: *mut ANativeWindow,
+    crypto: *mut AMediaCrypto,
+    flags: u32,
+) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_configure")?;
+        data.expect(MockCodecState::Uninitialized)?;
+
+        if format.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        let format = Format::from_ptr(format).clone();
+        let mut output = format.clone();
+        if let Some(ref extra) = data.output_override {
+            output.extend(extra);
+        }
+
+        if !surface.is_null() {
+            native_window::acquire(surface);
+        }
+        if !data.surface.is_null() {
+            native_window::release(data.surface);
+        }
+
+        data.surface = surface;
+        data.crypto = !crypto.is_null();
+        data.encoder |= BufferFlag::Encode.is_contained_in(flags as i32);
+        data.input_format = Some(format);
+        data.output_format = Some(output);
+        data.state = MockCodecState::Configured;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_start(codec: *mut AMediaCodec) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_start")?;
+
+        // Resuming after a flush in asynchronous mode
+        if data.state == MockCodecState::Executing && data.flushed {
+            data.flushed = false;
+            data.pump();
+            return Ok(());
+        }
+
+        data.expect(MockCodecState::Configured)?;
+
+        let capacity = data.buffer_capacity;
+        data.inputs = (0..data.input_buffers)
+            .map(|_| InputSlot {
+                data: vec![0; capacity],
+                dequeued: false,
+            })
+            .collect();
+        data.state = MockCodecState::Executing;
+        data.pump();
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_stop(codec: *mut AMediaCodec) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_stop")?;
+
+        data.reset_buffers();
+        data.flushed = false;
+        data.state = MockCodecState::Uninitialized;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_flush(codec: *mut AMediaCodec) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_flush")?;
+        data.expect(MockCodecState::Executing)?;
+
+        data.reset_buffers();
+        data.flushed = data.is_async();
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_getInputBuffer(
+    codec: *mut AMediaCodec,
+    idx: usize,
+    out_size: *mut usize,
+) -> *mut u8 {
+    let mut data = lock(codec);
+
+    match data.inputs.get_mut(idx) {
+        Some(slot) if slot.dequeued => {
+            *out_size = slot.data.len();
+            slot.data.as_mut_ptr()
+        }
+        _ => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_getOutputBuffer(
+    codec: *mut AMediaCodec,
+    idx: usize,
+    out_size: *mut usize,
+) -> *mut u8 {
+    let mut data = lock(codec);
+
+    if !data.surface.is_null() {
+        return null_mut();
+    }
+
+    match data.outputs.get_mut(&idx) {
+        Some(buffer) => {
+            *out_size = buffer.data.len();
+            buffer.data.as_mut_ptr()
+        }
+        None => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_dequeueInputBuffer(
+    codec: *mut AMediaCodec,
+    _timeout_us: i64,
+) -> isize {
+    let mut data = lock(codec);
+
+    if let Err(status) = data.fail("AMediaCodec_dequeueInputBuffer") {
+        return status as isize;
+    }
+
+    if data.state != MockCodecState::Executing || data.is_async() {
+        return MediaStatus::ErrorInvalidOperation as isize;
+    }
+
+    match data
+        .inputs
+        .iter_mut()
+        .enumerate()
+        .find(|(_, slot)| !slot.dequeued)
+    {
+        Some((index, slot)) => {
+            slot.dequeued = true;
+            index as isize
+        }
+        None => InfoFlag::TryAgainLater as isize,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_queueInputBuffer(
+    codec: *mut AMediaCodec,
+    idx: usize,
+    offset: i32,
+    size: usize,
+    time: u64,
+    flags: u32,
+) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_queueInputBuffer")?;
+        data.expect(MockCodecState::Executing)?;
+
+        let slot = match data.inputs.get_mut(idx) {
+            Some(slot) if slot.dequeued => slot,
+            _ => return Err(MediaStatus::ErrorInvalidParameter),
+        };
+
+        let start = offset.max(0) as usize;
+        let end = start + size;
+        if end > slot.data.len() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        slot.dequeued = false;
+        let buffer = MockBuffer {
+            data: slot.data[start..end].to_vec(),
+            time_us: time as i64,
+            flags,
+        };
+
+        data.queue(buffer);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_queueSecureInputBuffer(
+    codec: *mut AMediaCodec,
+    idx: usize,
+    offset: i32,
+    info: *mut AMediaCodecCryptoInfo,
+    time: u64,
+    flags: u32,
+) -> MediaStatus {
+    if info.is_null() {
+        return MediaStatus::ErrorInvalidParameter;
+    }
+
+    let info = crypto_info(info);
+    let size = info.clear_bytes.iter().sum::<usize>() + info.encrypted_bytes.iter().sum::<usize>();
+
+    let status = AMediaCodec_queueInputBuffer(codec, idx, offset, size, time, flags);
+    if status.is_ok() {
+        lock(codec).secure.push(info.to_crypto_info());
+    }
+
+    status
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_dequeueOutputBuffer(
+    codec: *mut AMediaCodec,
+    info: *mut BufferInfo,
+    _timeout_us: i64,
+) -> isize {
+    let mut data = lock(codec);
+
+    if let Err(status) = data.fail("AMediaCodec_dequeueOutputBuffer") {
+        return status as isize;
+    }
+
+    if data.state != MockCodecState::Executing || data.is_async() {
+        return MediaStatus::ErrorInvalidOperation as isize;
+    }
+
+    match data.events.pop_front() {
+        Some(OutputEvent::Buffer(buffer)) => {
+            let (index, buffer_info) = data.add_output(buffer);
+            *info = buffer_info;
+            index as isize
+        }
+        Some(OutputEvent::FormatChanged(format)) => {
+            data.output_format
+                .get_or_insert_with(Format::default)
+                .extend(&format);
+            InfoFlag::OutputFormatChanged as isize
+        }
+        Some(OutputEvent::BuffersChanged) => InfoFlag::OutputBuffersChanged as isize,
+        Some(OutputEvent::TryAgain) | None => InfoFlag::TryAgainLater as isize,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_getOutputFormat(codec: *mut AMediaCodec) -> *mut AMediaFormat {
+    match lock(codec).output_format {
+        Some(ref format) => format.clone().into_raw(),
+        None => null_mut(),
+    }
+}
+
+unsafe fn release_output(codec: *mut AMediaCodec, index: usize, render: bool) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_releaseOutputBuffer")?;
+
+        let buffer = data
+            .outputs
+            .remove(&index)
+            .ok_or(MediaStatus::ErrorInvalidParameter)?;
+
+        let rendered = render && !data.surface.is_null();
+        data.released.push(ReleasedOutput {
+            time_us: buffer.time_us,
+            rendered,
+        });
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_releaseOutputBuffer(
+    codec: *mut AMediaCodec,
+    index: usize,
+    render: bool,
+) -> MediaStatus {
+    release_output(codec, index, render)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_setOutputSurface(
+    codec: *mut AMediaCodec,
+    surface: *mut ANativeWindow,
+) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_setOutputSurface")?;
+
+        // Only codecs configured with a surface can switch to another one
+        if data.surface.is_null() || surface.is_null() {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        native_window::acquire(surface);
+        native_window::release(data.surface);
+        data.surface = surface;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_releaseOutputBufferAtTime(
+    codec: *mut AMediaCodec,
+    idx: usize,
+    _timestamp_ns: i64,
+) {
+    release_output(codec, idx, true);
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_createInputSurface(
+    codec: *mut AMediaCodec,
+    surface: *mut *mut ANativeWindow,
+) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_createInputSurface")?;
+        data.expect(MockCodecState::Configured)?;
+
+        if !data.encoder {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        *surface = native_window::create(0, 0);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_createPersistentInputSurface(
+    surface: *mut *mut ANativeWindow,
+) -> MediaStatus {
+    *surface = native_window::create(0, 0);
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_setInputSurface(
+    codec: *mut AMediaCodec,
+    surface: *mut ANativeWindow,
+) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_setInputSurface")?;
+        data.expect(MockCodecState::Configured)?;
+
+        if !data.encoder || surface.is_null() {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_setParameters(
+    codec: *mut AMediaCodec,
+    format: *const AMediaFormat,
+) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_setParameters")?;
+        data.expect(MockCodecState::Executing)?;
+
+        if format.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        let format = Format::from_ptr(format).clone();
+        data.parameters.push(format);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_signalEndOfInputStream(codec: *mut AMediaCodec) -> MediaStatus {
+    let mut data = lock(codec);
+
+    status((|| {
+        data.fail("AMediaCodec_signalEndOfInputStream")?;
+        data.expect(MockCodecState::Executing)?;
+
+        if !data.encoder {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        data.queue(MockBuffer {
+            data: vec![],
+            time_us: 0,
+            flags: BufferFlag::EndOfStream as u32,
+        });
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_getBufferFormat(
+    codec: *mut AMediaCodec,
+    index: usize,
+) -> *mut AMediaFormat {
+    let data = lock(codec);
+
+    match data.output_format {
+        Some(ref format) if data.outputs.contains_key(&index) => format.clone().into_raw(),
+        _ => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_getName(
+    codec: *mut AMediaCodec,
+    out_name: *mut *mut c_char,
+) -> MediaStatus {
+    *out_name = CString::into_raw(instance(codec).name.clone());
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_releaseName(_codec: *mut AMediaCodec, name: *mut c_char) {
+    if !name.is_null() {
+        drop(CString::from_raw(name));
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_releaseCrypto(_codec: *mut AMediaCodec) -> MediaStatus {
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_getInputFormat(codec: *mut AMediaCodec) -> *mut AMediaFormat {
+    match lock(codec).input_format {
+        Some(ref format) => format.clone().into_raw(),
+        None => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodec_setAsyncNotifyCallback(
+    codec: *mut AMediaCodec,
+    callback: _AMediaCodecOnAsyncNotifyCallback,
+    userdata: *mut c_void,
+) -> MediaStatus {
+    let mock = &instance(codec).mock;
+
+    {
+        let mut data = mock.lock();
+        if let Err(status) = data.fail("AMediaCodec_setAsyncNotifyCallback") {
+            return status;
+        }
+
+        if data.state == MockCodecState::Executing {
+            return MediaStatus::ErrorInvalidOperation;
+        }
+    }
+
+    // Wait for the previous callback to be done
+    stop_notifier(mock);
+
+    let (sender, receiver) = mpsc::channel();
+    let thread = {
+        let mock = mock.clone();
+        let codec = codec as usize;
+        let userdata = userdata as usize;
+
+        std::thread::spawn(move || notify_loop(mock, codec, callback, userdata, receiver))
+    };
+
+    mock.lock().notifier = Some(Notifier { sender, thread });
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecActionCode_isRecoverable(action_code: i32) -> bool {
+    // ACTION_CODE_RECOVERABLE
+    action_code == 2
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecActionCode_isTransient(action_code: i32) -> bool {
+    // ACTION_CODE_TRANSIENT
+    action_code == 1
+}
+
+/// The mock `AMediaCodecCryptoInfo`
+struct RawCryptoInfo {
+    key: [u8; 16],
+    iv: [u8; 16],
+    mode: CryptoInfoMode,
+    pattern: Option<CryptoInfoPattern>,
+    clear_bytes: Vec<usize>,
+    encrypted_bytes: Vec<usize>,
+}
+
+impl RawCryptoInfo {
+    fn to_crypto_info(&self) -> CryptoInfo {
+        let subsamples = self
+            .clear_bytes
+            .iter()
+            .zip(&self.encrypted_bytes)
+            .map(|(&clear, &encrypted)| SubSample::new(clear, encrypted))
+            .collect();
+
+        CryptoInfo {
+            mode: self.mode,
+            key: self.key,
+            iv: self.iv,
+            subsamples,
+            pattern: self.pattern,
+        }
+    }
+}
+
+/// Creates an `AMediaCodecCryptoInfo` describing `info`, to be freed with `AMediaCodecCryptoInfo_delete`
+pub(super) fn new_crypto_info(info: &CryptoInfo) -> *mut AMediaCodecCryptoInfo {
+    let info = RawCryptoInfo {
+        key: info.key,
+        iv: info.iv,
+        mode: info.mode,
+        pattern: info.pattern,
+        clear_bytes: info.subsamples.iter().map(|s| s.clear_bytes).collect(),
+        encrypted_bytes: info.subsamples.iter().map(|s| s.encrypted_bytes).collect(),
+    };
+
+    Box::into_raw(Box::new(info)).cast()
+}
+
+unsafe fn crypto_info<'a>(info: *mut AMediaCodecCryptoInfo) -> &'a RawCryptoInfo {
+    &*(info as *const RawCryptoInfo)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_new(
+    num_subsamples: i32,
+    key: &[u8; 16],
+    iv: &[u8; 16],
+    mode: CryptoInfoMode,
+    clearbytes: *mut usize,
+    encrypted_bytes: *mut usize,
+) -> *mut AMediaCodecCryptoInfo {
+    let count = num_subsamples.max(0) as usize;
+    let copy = |values: *mut usize| {
+        if values.is_null() {
+            vec![0; count]
+        } else {
+            std::slice::from_raw_parts(values, count).to_vec()
+        }
+    };
+
+    let info = RawCryptoInfo {
+        key: *key,
+        iv: *iv,
+        mode,
+        pattern: None,
+        clear_bytes: copy(clearbytes),
+        encrypted_bytes: copy(encrypted_bytes),
+    };
+
+    Box::into_raw(Box::new(info)).cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_delete(info: *mut AMediaCodecCryptoInfo) -> MediaStatus {
+    if info.is_null() {
+        return MediaStatus::ErrorInvalidParameter;
+    }
+
+    drop(Box::from_raw(info as *mut RawCryptoInfo));
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_setPattern(
+    info: *mut AMediaCodecCryptoInfo,
+    pattern: *mut CryptoInfoPattern,
+) {
+    if !info.is_null() && !pattern.is_null() {
+        (*(info as *mut RawCryptoInfo)).pattern = Some(*pattern);
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_getNumSubSamples(
+    info: *mut AMediaCodecCryptoInfo,
+) -> usize {
+    crypto_info(info).clear_bytes.len()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_getKey(
+    info: *mut AMediaCodecCryptoInfo,
+    dst: *mut u8,
+) -> isize {
+    if dst.is_null() {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    std::ptr::copy_nonoverlapping(crypto_info(info).key.as_ptr(), dst, 16);
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_getIV(
+    info: *mut AMediaCodecCryptoInfo,
+    dst: *mut u8,
+) -> isize {
+    if dst.is_null() {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    std::ptr::copy_nonoverlapping(crypto_info(info).iv.as_ptr(), dst, 16);
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_getMode(
+    info: *mut AMediaCodecCryptoInfo,
+) -> CryptoInfoMode {
+    crypto_info(info).mode
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_getClearBytes(
+    info: *mut AMediaCodecCryptoInfo,
+    dst: *mut usize,
+) -> isize {
+    if dst.is_null() {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    let values = &crypto_info(info).clear_bytes;
+    std::ptr::copy_nonoverlapping(values.as_ptr(), dst, values.len());
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCodecCryptoInfo_getEncryptedBytes(
+    info: *mut AMediaCodecCryptoInfo,
+    dst: *mut usize,
+) -> isize {
+    if dst.is_null() {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    let values = &crypto_info(info).encrypted_bytes;
+    std::ptr::copy_nonoverlapping(values.as_ptr(), dst, values.len());
+    0
+}
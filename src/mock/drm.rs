@@ -0,0 +1,622 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    ptr::null_mut,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use super::Failures;
+use crate::{
+    AMediaCrypto, AMediaDrm, AMediaDrmByteArray, AMediaDrmEventListener, AMediaDrmKeyValue,
+    DrmEventType, DrmSession, KeyType, MediaStatus, CLEARKEY_UUID,
+};
+
+/// Container formats the mock ClearKey scheme can handle
+const CONTAINERS: [&str; 4] = ["video/mp4", "audio/mp4", "video/webm", "audio/webm"];
+
+const PROVISION_URL: &str = "https://provisioning.mock/";
+
+#[derive(Debug)]
+struct DrmData {
+    fail_creation: bool,
+    provisioned: bool,
+    handle: *mut AMediaDrm,
+    listener: Option<AMediaDrmEventListener>,
+    next_id: u32,
+    sessions: Vec<Vec<u8>>,
+    key_type: KeyType,
+    key_request: Vec<u8>,
+    key_responses: Vec<(Vec<u8>, Vec<u8>)>,
+    key_sets: Vec<Vec<u8>>,
+    provision_request: Vec<u8>,
+    provision_url: CString,
+    properties: HashMap<String, CString>,
+    byte_properties: HashMap<String, Vec<u8>>,
+    failures: Failures,
+}
+
+// The handle is only handed back to the listener
+unsafe impl Send for DrmData {}
+
+/// A scriptable stand-in for an `AMediaDrm` instance, implementing the ClearKey scheme.
+///
+/// Call [install](MockDrm::install) before creating a `MediaDrm`, and the next one created on the same thread will use
+/// this mock. Clones share the same DRM engine, so a test can keep one around to inspect the exchanged keys, or to
+/// [send events](MockDrm::send_event).
+#[derive(Debug, Clone)]
+pub struct MockDrm {
+    data: Arc<Mutex<DrmData>>,
+}
+
+thread_local! {
+    static PENDING: RefCell<VecDeque<MockDrm>> = const { RefCell::new(VecDeque::new()) };
+}
+
+impl Default for MockDrm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockDrm {
+    /// Creates a provisioned ClearKey engine
+    pub fn new() -> Self {
+        let properties = [
+            ("vendor", "Mock"),
+            ("version", "1.0"),
+            ("description", "ClearKey CDM"),
+            ("algorithms", ""),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), CString::new(value).unwrap()))
+        .collect();
+
+        Self {
+            data: Arc::new(Mutex::new(DrmData {
+                fail_creation: false,
+                provisioned: true,
+                handle: null_mut(),
+                listener: None,
+                next_id: 1,
+                sessions: vec![],
+                key_type: KeyType::Streaming,
+                key_request: vec![],
+                key_responses: vec![],
+                key_sets: vec![],
+                provision_request: b"clearkey-provision".to_vec(),
+                provision_url: CString::new(PROVISION_URL).unwrap(),
+                properties,
+                byte_properties: HashMap::from([("deviceUniqueId".to_string(), (0..16).collect())]),
+                failures: Failures::default(),
+            })),
+        }
+    }
+
+    /// Makes the next `MediaDrm` created on this thread use this mock
+    pub fn install(&self) {
+        PENDING.with(|pending| pending.borrow_mut().push_back(self.clone()));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DrmData> {
+        self.data.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Makes the creation fail, the way it would when the DRM service is unavailable
+    pub fn fail_creation(self) -> Self {
+        self.lock().fail_creation = true;
+        self
+    }
+
+    /// Makes the engine require provisioning before it opens sessions
+    pub fn unprovisioned(self) -> Self {
+        self.lock().provisioned = false;
+        self
+    }
+
+    /// Makes the next call to `function` (e.g. `"AMediaDrm_openSession"`) fail with `status`
+    pub fn fail_next(&self, function: &str, status: MediaStatus) {
+        self.lock().failures.push(function, status);
+    }
+
+    /// Whether the engine has been provisioned
+    pub fn is_provisioned(&self) -> bool {
+        self.lock().provisioned
+    }
+
+    /// The ids of the sessions currently open
+    pub fn sessions(&self) -> Vec<Vec<u8>> {
+        self.lock().sessions.clone()
+    }
+
+    /// Every key response provided so far, as (session id, response)
+    pub fn key_responses(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.lock().key_responses.clone()
+    }
+
+    /// Reports an event to the listener of the `MediaDrm`, on the calling thread
+    pub fn send_event(
+        &self,
+        event_type: DrmEventType,
+        session: Option<&DrmSession>,
+        extra: i32,
+        data: &[u8],
+    ) {
+        let (handle, listener) = {
+            let data = self.lock();
+            (data.handle, data.listener)
+        };
+        let Some(listener) = listener else {
+            return;
+        };
+
+        let session = session.map(|session| AMediaDrmByteArray {
+            ptr: session.id().as_ptr(),
+            length: session.id().len(),
+        });
+        listener(
+            handle,
+            session.as_ref().map_or(std::ptr::null(), |session| session),
+            event_type.into(),
+            extra,
+            data.as_ptr(),
+            data.len(),
+        );
+    }
+}
+
+impl DrmData {
+    fn fail(&mut self, function: &str) -> Result<(), MediaStatus> {
+        match self.failures.take(function) {
+            Some(status) => Err(status),
+            None => Ok(()),
+        }
+    }
+
+    fn expect_provisioned(&self) -> Result<(), MediaStatus> {
+        if !self.provisioned {
+            return Err(MediaStatus::DRMNotProvisioned);
+        }
+
+        Ok(())
+    }
+
+    fn expect_session(&self, session: &[u8]) -> Result<(), MediaStatus> {
+        if !self.sessions.iter().any(|id| id == session) {
+            return Err(MediaStatus::DRMSessionNotOpened);
+        }
+
+        Ok(())
+    }
+}
+
+unsafe fn lock<'a>(drm: *mut AMediaDrm) -> MutexGuard<'a, DrmData> {
+    (*(drm as *const MockDrm)).lock()
+}
+
+fn status(result: Result<(), MediaStatus>) -> MediaStatus {
+    result.err().unwrap_or(MediaStatus::Ok)
+}
+
+unsafe fn bytes<'a>(ptr: *const u8, size: usize) -> &'a [u8] {
+    if ptr.is_null() {
+        return &[];
+    }
+
+    std::slice::from_raw_parts(ptr, size)
+}
+
+unsafe fn byte_array<'a>(array: *const AMediaDrmByteArray) -> Result<&'a [u8], MediaStatus> {
+    match array.as_ref() {
+        Some(array) => Ok(bytes(array.ptr, array.length)),
+        None => Err(MediaStatus::ErrorInvalidParameter),
+    }
+}
+
+unsafe fn string(ptr: *const c_char) -> Result<String, MediaStatus> {
+    if ptr.is_null() {
+        return Err(MediaStatus::ErrorInvalidParameter);
+    }
+
+    Ok(CStr::from_ptr(ptr).to_string_lossy().to_string())
+}
+
+unsafe fn is_clearkey(uuid: *const u8) -> bool {
+    !uuid.is_null() && bytes(uuid, 16) == CLEARKEY_UUID
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_isCryptoSchemeSupported(
+    uuid: *const u8,
+    mime_type: *const c_char,
+) -> bool {
+    is_clearkey(uuid)
+        && (mime_type.is_null()
+            || string(mime_type).is_ok_and(|mime| CONTAINERS.contains(&mime.as_str())))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_createByUUID(uuid: *const u8) -> *mut AMediaDrm {
+    let mock = PENDING
+        .with(|pending| pending.borrow_mut().pop_front())
+        .unwrap_or_default();
+
+    if mock.lock().fail_creation || !is_clearkey(uuid) {
+        return null_mut();
+    }
+
+    let drm: *mut AMediaDrm = Box::into_raw(Box::new(mock)).cast();
+    lock(drm).handle = drm;
+    drm
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_release(drm: *mut AMediaDrm) {
+    if drm.is_null() {
+        return;
+    }
+
+    let mock = Box::from_raw(drm as *mut MockDrm);
+    let mut data = mock.lock();
+    data.handle = null_mut();
+    data.listener = None;
+    data.sessions.clear();
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_setOnEventListener(
+    drm: *mut AMediaDrm,
+    listener: AMediaDrmEventListener,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_setOnEventListener")?;
+        data.listener = Some(listener);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_openSession(
+    drm: *mut AMediaDrm,
+    session_id: *mut AMediaDrmByteArray,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_openSession")?;
+        data.expect_provisioned()?;
+
+        if session_id.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        let id = format!("session-{}", data.next_id).into_bytes();
+        data.next_id += 1;
+        data.sessions.push(id);
+
+        // The id stays owned by the engine, until the session gets closed
+        let id = data.sessions.last().unwrap();
+        *session_id = AMediaDrmByteArray {
+            ptr: id.as_ptr(),
+            length: id.len(),
+        };
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_closeSession(
+    drm: *mut AMediaDrm,
+    session_id: *const AMediaDrmByteArray,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_closeSession")?;
+
+        let session = byte_array(session_id)?;
+        data.expect_session(session)?;
+        data.sessions.retain(|id| id != session);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_getKeyRequest(
+    drm: *mut AMediaDrm,
+    scope: *const AMediaDrmByteArray,
+    init: *const u8,
+    init_size: usize,
+    mime_type: *const c_char,
+    key_type: KeyType,
+    _optional_parameters: *const AMediaDrmKeyValue,
+    _num_optional_parameters: usize,
+    key_request: *mut *const u8,
+    key_request_size: *mut usize,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_getKeyRequest")?;
+
+        // Releasing keys is scoped to a key set id, instead of a session
+        let scope = byte_array(scope)?;
+        match key_type {
+            KeyType::Release if !data.key_sets.iter().any(|id| id == scope) => {
+                return Err(MediaStatus::ErrorInvalidParameter)
+            }
+            KeyType::Release => {}
+            _ => data.expect_session(scope)?,
+        }
+
+        string(mime_type)?;
+        if key_request.is_null() || key_request_size.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+        if key_type != KeyType::Release && init_size == 0 {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        let mut request = b"clearkey-request:".to_vec();
+        request.extend_from_slice(bytes(init, init_size));
+        data.key_request = request;
+        data.key_type = key_type;
+
+        *key_request = data.key_request.as_ptr();
+        *key_request_size = data.key_request.len();
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_provideKeyResponse(
+    drm: *mut AMediaDrm,
+    scope: *const AMediaDrmByteArray,
+    response: *const u8,
+    response_size: usize,
+    key_set_id: *mut AMediaDrmByteArray,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_provideKeyResponse")?;
+
+        let scope = byte_array(scope)?.to_vec();
+        if data.key_type != KeyType::Release {
+            data.expect_session(&scope)?;
+        }
+        if response_size == 0 || key_set_id.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        data.key_responses
+            .push((scope.clone(), bytes(response, response_size).to_vec()));
+
+        *key_set_id = AMediaDrmByteArray {
+            ptr: std::ptr::null(),
+            length: 0,
+        };
+        match data.key_type {
+            KeyType::Offline => {
+                let id = format!("keyset-{}", data.key_sets.len() + 1).into_bytes();
+                data.key_sets.push(id);
+
+                let id = data.key_sets.last().unwrap();
+                *key_set_id = AMediaDrmByteArray {
+                    ptr: id.as_ptr(),
+                    length: id.len(),
+                };
+            }
+            KeyType::Release => data.key_sets.retain(|id| *id != scope),
+            KeyType::Streaming => {}
+        }
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_restoreKeys(
+    drm: *mut AMediaDrm,
+    session_id: *const AMediaDrmByteArray,
+    key_set_id: *const AMediaDrmByteArray,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_restoreKeys")?;
+        data.expect_session(byte_array(session_id)?)?;
+
+        let key_set_id = byte_array(key_set_id)?;
+        if !data.key_sets.iter().any(|id| id == key_set_id) {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_removeKeys(
+    drm: *mut AMediaDrm,
+    key_set_id: *const AMediaDrmByteArray,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_removeKeys")?;
+
+        let session = byte_array(key_set_id)?.to_vec();
+        data.expect_session(&session)?;
+        data.key_responses.retain(|(id, _)| *id != session);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_getProvisionRequest(
+    drm: *mut AMediaDrm,
+    provision_request: *mut *const u8,
+    provision_request_size: *mut usize,
+    server_url: *mut *const c_char,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_getProvisionRequest")?;
+
+        if provision_request.is_null() || provision_request_size.is_null() || server_url.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        *provision_request = data.provision_request.as_ptr();
+        *provision_request_size = data.provision_request.len();
+        *server_url = data.provision_url.as_ptr();
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_provideProvisionResponse(
+    drm: *mut AMediaDrm,
+    _response: *const u8,
+    response_size: usize,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_provideProvisionResponse")?;
+
+        if response_size == 0 {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        data.provisioned = true;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_getPropertyString(
+    drm: *mut AMediaDrm,
+    property_name: *const c_char,
+    property_value: *mut *const c_char,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_getPropertyString")?;
+
+        let name = string(property_name)?;
+        match (data.properties.get(&name), property_value.is_null()) {
+            (Some(value), false) => {
+                *property_value = value.as_ptr();
+                Ok(())
+            }
+            _ => Err(MediaStatus::ErrorInvalidParameter),
+        }
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_getPropertyByteArray(
+    drm: *mut AMediaDrm,
+    property_name: *const c_char,
+    property_value: *mut AMediaDrmByteArray,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_getPropertyByteArray")?;
+
+        let name = string(property_name)?;
+        match (data.byte_properties.get(&name), property_value.is_null()) {
+            (Some(value), false) => {
+                *property_value = AMediaDrmByteArray {
+                    ptr: value.as_ptr(),
+                    length: value.len(),
+                };
+                Ok(())
+            }
+            _ => Err(MediaStatus::ErrorInvalidParameter),
+        }
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_setPropertyString(
+    drm: *mut AMediaDrm,
+    property_name: *const c_char,
+    value: *const c_char,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_setPropertyString")?;
+
+        let name = string(property_name)?;
+        let value = CStr::from_ptr(value).to_owned();
+        data.properties.insert(name, value);
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDrm_setPropertyByteArray(
+    drm: *mut AMediaDrm,
+    property_name: *const c_char,
+    value: *const u8,
+    value_size: usize,
+) -> MediaStatus {
+    let mut data = lock(drm);
+
+    status((|| {
+        data.fail("AMediaDrm_setPropertyByteArray")?;
+
+        let name = string(property_name)?;
+        data.byte_properties
+            .insert(name, bytes(value, value_size).to_vec());
+        Ok(())
+    })())
+}
+
+/// A mock `AMediaCrypto`, holding the session it decrypts with
+struct MockCrypto {
+    _session: Vec<u8>,
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCrypto_isCryptoSchemeSupported(uuid: *const u8) -> bool {
+    is_clearkey(uuid)
+}
+
+/// ClearKey decrypts in software, so any decoder will do
+#[no_mangle]
+unsafe extern "C" fn AMediaCrypto_requiresSecureDecoderComponent(_mime: *const c_char) -> bool {
+    false
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCrypto_new(
+    uuid: *const u8,
+    init_data: *const u8,
+    init_size: usize,
+) -> *mut AMediaCrypto {
+    if !is_clearkey(uuid) || init_size == 0 {
+        return null_mut();
+    }
+
+    let crypto = MockCrypto {
+        _session: bytes(init_data, init_size).to_vec(),
+    };
+    Box::into_raw(Box::new(crypto)).cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaCrypto_delete(crypto: *mut AMediaCrypto) {
+    if !crypto.is_null() {
+        drop(Box::from_raw(crypto as *mut MockCrypto));
+    }
+}
@@ -0,0 +1,640 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::CStr,
+    os::raw::{c_char, c_void},
+    ptr::null_mut,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use super::{codec::new_crypto_info, format::Format, Failures};
+use crate::{
+    AMediaCodecCryptoInfo, AMediaDataSource, AMediaDataSourceClose, AMediaDataSourceGetSize,
+    AMediaDataSourceReadAt, AMediaExtractor, AMediaFormat, CryptoInfo, MediaFormat, MediaStatus,
+    PsshEntry, PsshInfo, SampleFlags, SeekMode,
+};
+
+/// The most bytes read from a file or custom data source to find the media it points to
+const MAX_LOCATION: usize = 4096;
+
+/// A demuxed packet stored in a mock media source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockSample {
+    pub track: usize,
+    pub time_us: i64,
+    pub flags: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct MediaData {
+    file_format: Format,
+    pssh: Vec<([u8; 16], Vec<u8>)>,
+    tracks: Vec<Format>,
+    samples: Vec<MockSample>,
+    /// How the encrypted samples are encrypted, by sample index
+    crypto: BTreeMap<usize, CryptoInfo>,
+    cached_duration_us: Option<i64>,
+    failures: Failures,
+}
+
+/// A scriptable media file that `MediaExtractor` can open.
+///
+/// Describe the tracks and the interleaved samples, then [register](MockMedia::register) the media under a location to
+/// make `MediaExtractor::from_url` find it. Files and custom data sources hold the location instead of real media: the
+/// extractor reads them, and opens the media registered under their content.
+#[derive(Debug, Clone, Default)]
+pub struct MockMedia {
+    data: Arc<Mutex<MediaData>>,
+}
+
+static SOURCES: Mutex<BTreeMap<String, MockMedia>> = Mutex::new(BTreeMap::new());
+
+fn sources() -> MutexGuard<'static, BTreeMap<String, MockMedia>> {
+    SOURCES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl MockMedia {
+    /// Creates an empty media source
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MediaData> {
+        self.data.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Adds a track with the specified format
+    pub fn track(self, format: &MediaFormat) -> Self {
+        self.lock().tracks.push(Format::copy_of(format));
+        self
+    }
+
+    /// Adds the entries of `format` to the container format
+    pub fn file_format(self, format: &MediaFormat) -> Self {
+        self.lock().file_format.extend(&Format::copy_of(format));
+        self
+    }
+
+    /// Adds a PSSH box holding the init data of the DRM scheme `uuid`
+    pub fn pssh(self, uuid: [u8; 16], data: &[u8]) -> Self {
+        self.lock().pssh.push((uuid, data.to_vec()));
+        self
+    }
+
+    /// Appends a sample to the media. Samples are demuxed in the order they were added
+    pub fn sample(self, track: usize, time_us: i64, flags: u32, data: &[u8]) -> Self {
+        self.lock().samples.push(MockSample {
+            track,
+            time_us,
+            flags,
+            data: data.to_vec(),
+        });
+        self
+    }
+
+    /// Appends an encrypted sample, flagged as such, which `AMediaExtractor_getSampleCryptoInfo` describes with `info`
+    pub fn encrypted_sample(
+        self,
+        track: usize,
+        time_us: i64,
+        flags: u32,
+        data: &[u8],
+        info: &CryptoInfo,
+    ) -> Self {
+        let mut media = self.lock();
+        let index = media.samples.len();
+        media.crypto.insert(index, info.clone());
+        media.samples.push(MockSample {
+            track,
+            time_us,
+            flags: flags | SampleFlags::ENCRYPTED.bits(),
+            data: data.to_vec(),
+        });
+        drop(media);
+
+        self
+    }
+
+    /// Makes the media behave like a network stream with `duration_us` of media buffered ahead of the current
+    /// position
+    pub fn cached_duration(self, duration_us: i64) -> Self {
+        self.lock().cached_duration_us = Some(duration_us);
+        self
+    }
+
+    /// Makes the media available at `location`
+    pub fn register(&self, location: &str) {
+        sources().insert(location.to_string(), self.clone());
+    }
+
+    /// Removes the media registered at `location`
+    pub fn unregister(location: &str) {
+        sources().remove(location);
+    }
+
+    /// Makes the next call to `function` (e.g. `"AMediaExtractor_readSampleData"`) fail with `status`
+    pub fn fail_next(&self, function: &str, status: MediaStatus) {
+        self.lock().failures.push(function, status);
+    }
+}
+
+/// The mock `AMediaExtractor`
+#[derive(Default)]
+struct Extractor {
+    media: Option<MockMedia>,
+    selected: BTreeSet<usize>,
+    cursor: usize,
+    source: Option<*mut AMediaDataSource>,
+    /// Backs the `PsshInfo` handed out, which lives as long as the extractor
+    pssh: Option<(Vec<usize>, Vec<Vec<u8>>)>,
+}
+
+impl Extractor {
+    /// Index of the sample the extractor currently points to
+    fn current(&self, data: &MediaData) -> Option<usize> {
+        (self.cursor..data.samples.len())
+            .find(|&index| self.selected.contains(&data.samples[index].track))
+    }
+
+    /// Runs `f` with the current sample, or returns `default` when there is none
+    fn with_sample<T>(&self, default: T, f: impl FnOnce(&MockSample) -> T) -> T {
+        let Some(ref media) = self.media else {
+            return default;
+        };
+
+        let data = media.lock();
+        match self.current(&data) {
+            Some(index) => f(&data.samples[index]),
+            None => default,
+        }
+    }
+}
+
+unsafe fn get<'a>(extractor: *mut AMediaExtractor) -> &'a mut Extractor {
+    &mut *(extractor as *mut Extractor)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_new() -> *mut AMediaExtractor {
+    Box::into_raw(Box::<Extractor>::default()).cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_delete(extractor: *mut AMediaExtractor) -> isize {
+    if !extractor.is_null() {
+        let extractor = Box::from_raw(extractor as *mut Extractor);
+        if let Some(source) = extractor.source {
+            close_source(source);
+        }
+    }
+
+    0
+}
+
+/// Opens the media registered at `location`
+unsafe fn open(extractor: *mut AMediaExtractor, location: &str) -> MediaStatus {
+    let Some(media) = sources().get(location).cloned() else {
+        return MediaStatus::ErrorIO;
+    };
+
+    if let Some(status) = media.lock().failures.take("AMediaExtractor_setDataSource") {
+        return status;
+    }
+
+    let extractor = get(extractor);
+    extractor.media = Some(media);
+    extractor.selected.clear();
+    extractor.cursor = 0;
+    extractor.pssh = None;
+
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_setDataSource(
+    extractor: *mut AMediaExtractor,
+    location: *const c_char,
+) -> isize {
+    let location = CStr::from_ptr(location).to_string_lossy();
+    open(extractor, &location) as isize
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_setDataSourceFd(
+    extractor: *mut AMediaExtractor,
+    fd: i32,
+    offset: u64,
+    length: u64,
+) -> isize {
+    match read_fd(fd, offset, length) {
+        Ok(location) => open(extractor, &location) as isize,
+        Err(status) => status as isize,
+    }
+}
+
+#[cfg(unix)]
+unsafe fn read_fd(fd: i32, offset: u64, length: u64) -> Result<String, MediaStatus> {
+    use std::{
+        fs::File,
+        mem::ManuallyDrop,
+        os::unix::{fs::FileExt, io::FromRawFd},
+    };
+
+    if fd < 0 {
+        return Err(MediaStatus::ErrorInvalidParameter);
+    }
+
+    // The descriptor stays owned by the caller
+    let file = ManuallyDrop::new(File::from_raw_fd(fd));
+    let size = file.metadata().map_err(|_| MediaStatus::ErrorIO)?.len();
+
+    let length = length
+        .min(size.saturating_sub(offset))
+        .min(MAX_LOCATION as u64);
+    let mut data = vec![0; length as usize];
+    file.read_exact_at(&mut data, offset)
+        .map_err(|_| MediaStatus::ErrorIO)?;
+
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+#[cfg(not(unix))]
+unsafe fn read_fd(_fd: i32, _offset: u64, _length: u64) -> Result<String, MediaStatus> {
+    Err(MediaStatus::ErrorUnsupported)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_setDataSourceCustom(
+    extractor: *mut AMediaExtractor,
+    source: *mut AMediaDataSource,
+) -> MediaStatus {
+    if source.is_null() {
+        return MediaStatus::ErrorInvalidParameter;
+    }
+
+    let location = match read_source(&*(source as *const DataSource)) {
+        Ok(location) => location,
+        Err(status) => return status,
+    };
+
+    let status = open(extractor, &location);
+    if status.is_ok() {
+        let extractor = get(extractor);
+        if let Some(previous) = extractor.source.replace(source) {
+            close_source(previous);
+        }
+    }
+
+    status
+}
+
+/// The mock `AMediaDataSource`
+struct DataSource {
+    userdata: *mut c_void,
+    read_at: Option<AMediaDataSourceReadAt>,
+    get_size: Option<AMediaDataSourceGetSize>,
+    close: Option<AMediaDataSourceClose>,
+}
+
+/// Reads the whole content of a custom data source, the way the NDK sniffs the container
+unsafe fn read_source(source: &DataSource) -> Result<String, MediaStatus> {
+    let Some(read_at) = source.read_at else {
+        return Err(MediaStatus::ErrorInvalidParameter);
+    };
+
+    // An unknown size means reading until the end of the data
+    let size = match source.get_size {
+        Some(get_size) => get_size(source.userdata),
+        None => -1,
+    };
+    let limit = if size < 0 {
+        MAX_LOCATION
+    } else {
+        (size as usize).min(MAX_LOCATION)
+    };
+
+    let mut data = vec![0u8; limit];
+    let mut count = 0;
+    while count < limit {
+        // Small reads, to make sure the offsets get honoured
+        let chunk = (limit - count).min(16);
+        let read = read_at(
+            source.userdata,
+            count as i64,
+            data[count..].as_mut_ptr().cast(),
+            chunk,
+        );
+
+        match read {
+            0 => break,
+            read if read < 0 => return Err(MediaStatus::ErrorIO),
+            read => count += read as usize,
+        }
+    }
+    data.truncate(count);
+
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+unsafe fn close_source(source: *mut AMediaDataSource) {
+    let source = &*(source as *const DataSource);
+    if let Some(close) = source.close {
+        close(source.userdata);
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDataSource_new() -> *mut AMediaDataSource {
+    let source = DataSource {
+        userdata: null_mut(),
+        read_at: None,
+        get_size: None,
+        close: None,
+    };
+    Box::into_raw(Box::new(source)).cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDataSource_delete(source: *mut AMediaDataSource) {
+    if !source.is_null() {
+        drop(Box::from_raw(source as *mut DataSource));
+    }
+}
+
+unsafe fn data_source<'a>(source: *mut AMediaDataSource) -> &'a mut DataSource {
+    &mut *(source as *mut DataSource)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDataSource_setUserdata(
+    source: *mut AMediaDataSource,
+    userdata: *mut c_void,
+) {
+    data_source(source).userdata = userdata;
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDataSource_setReadAt(
+    source: *mut AMediaDataSource,
+    read_at: AMediaDataSourceReadAt,
+) {
+    data_source(source).read_at = Some(read_at);
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDataSource_setGetSize(
+    source: *mut AMediaDataSource,
+    get_size: AMediaDataSourceGetSize,
+) {
+    data_source(source).get_size = Some(get_size);
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaDataSource_setClose(
+    source: *mut AMediaDataSource,
+    close: AMediaDataSourceClose,
+) {
+    data_source(source).close = Some(close);
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getTrackCount(extractor: *mut AMediaExtractor) -> usize {
+    match get(extractor).media {
+        Some(ref media) => media.lock().tracks.len(),
+        None => 0,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getTrackFormat(
+    extractor: *mut AMediaExtractor,
+    index: usize,
+) -> *mut AMediaFormat {
+    let Some(ref media) = get(extractor).media else {
+        return null_mut();
+    };
+
+    match media.lock().tracks.get(index) {
+        Some(format) => format.clone().into_raw(),
+        None => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getFileFormat(
+    extractor: *mut AMediaExtractor,
+) -> *mut AMediaFormat {
+    match get(extractor).media {
+        Some(ref media) => media.lock().file_format.clone().into_raw(),
+        None => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getPsshInfo(extractor: *mut AMediaExtractor) -> *mut PsshInfo {
+    let extractor = get(extractor);
+
+    let Some(ref media) = extractor.media else {
+        return null_mut();
+    };
+    let pssh = media.lock().pssh.clone();
+    if pssh.is_empty() {
+        return null_mut();
+    }
+
+    // The count, followed by the entries, laid out in words to keep the alignment
+    let words = std::mem::size_of::<PsshEntry>() / std::mem::size_of::<usize>();
+    let mut info = vec![0usize; 1 + words * pssh.len()];
+    info[0] = pssh.len();
+
+    let mut data = vec![];
+    for (index, (uuid, mut bytes)) in pssh.into_iter().enumerate() {
+        let entry = PsshEntry {
+            uuid,
+            datalen: bytes.len(),
+            data: bytes.as_mut_ptr().cast(),
+        };
+        info.as_mut_ptr()
+            .add(1 + words * index)
+            .cast::<PsshEntry>()
+            .write(entry);
+        data.push(bytes);
+    }
+
+    let (info, _) = extractor.pssh.insert((info, data));
+    info.as_mut_ptr().cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_selectTrack(
+    extractor: *mut AMediaExtractor,
+    index: usize,
+) -> isize {
+    if index >= AMediaExtractor_getTrackCount(extractor) {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    get(extractor).selected.insert(index);
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_unselectTrack(
+    extractor: *mut AMediaExtractor,
+    index: usize,
+) -> isize {
+    let extractor = get(extractor);
+
+    if !extractor.selected.remove(&index) {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_readSampleData(
+    extractor: *mut AMediaExtractor,
+    buffer: *mut u8,
+    capacity: usize,
+) -> isize {
+    let extractor = get(extractor);
+
+    if let Some(ref media) = extractor.media {
+        if let Some(status) = media.lock().failures.take("AMediaExtractor_readSampleData") {
+            return status as isize;
+        }
+    }
+
+    extractor.with_sample(-1, |sample| {
+        if sample.data.len() > capacity || buffer.is_null() {
+            return -1;
+        }
+
+        std::ptr::copy_nonoverlapping(sample.data.as_ptr(), buffer, sample.data.len());
+        sample.data.len() as isize
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getSampleSize(extractor: *mut AMediaExtractor) -> isize {
+    get(extractor).with_sample(-1, |sample| sample.data.len() as isize)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getSampleFlags(extractor: *mut AMediaExtractor) -> u32 {
+    get(extractor).with_sample(0, |sample| sample.flags)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getSampleCryptoInfo(
+    extractor: *mut AMediaExtractor,
+) -> *mut AMediaCodecCryptoInfo {
+    let extractor = get(extractor);
+
+    let Some(ref media) = extractor.media else {
+        return null_mut();
+    };
+    let data = media.lock();
+
+    match extractor
+        .current(&data)
+        .and_then(|index| data.crypto.get(&index))
+    {
+        Some(info) => new_crypto_info(info),
+        None => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getCachedDuration(extractor: *mut AMediaExtractor) -> i64 {
+    match get(extractor).media {
+        Some(ref media) => media.lock().cached_duration_us.unwrap_or(-1),
+        None => -1,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getSampleTrackIndex(extractor: *mut AMediaExtractor) -> i32 {
+    get(extractor).with_sample(-1, |sample| sample.track as i32)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_getSampleTime(extractor: *mut AMediaExtractor) -> i64 {
+    get(extractor).with_sample(-1, |sample| sample.time_us)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_advance(extractor: *mut AMediaExtractor) -> bool {
+    let extractor = get(extractor);
+
+    let Some(ref media) = extractor.media else {
+        return false;
+    };
+
+    // Like the NDK, this only fails when there was no sample to move past
+    let current = extractor.current(&media.lock());
+    match current {
+        Some(index) => {
+            extractor.cursor = index + 1;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Moves to a sync sample of the selected tracks. Unlike the NDK, which seeks every track on its own, this moves
+/// the whole interleaved stream to the chosen sample
+#[no_mangle]
+unsafe extern "C" fn AMediaExtractor_seekTo(
+    extractor: *mut AMediaExtractor,
+    seek_pos_us: i64,
+    mode: SeekMode,
+) -> MediaStatus {
+    let extractor = get(extractor);
+
+    let Some(ref media) = extractor.media else {
+        return MediaStatus::ErrorInvalidOperation;
+    };
+    let mut data = media.lock();
+
+    if let Some(status) = data.failures.take("AMediaExtractor_seekTo") {
+        return status;
+    }
+    if extractor.selected.is_empty() {
+        return MediaStatus::ErrorInvalidOperation;
+    }
+
+    let syncs: Vec<(usize, i64)> = data
+        .samples
+        .iter()
+        .enumerate()
+        .filter(|(_, sample)| {
+            extractor.selected.contains(&sample.track)
+                && SampleFlags::from_bits(sample.flags).is_sync()
+        })
+        .map(|(index, sample)| (index, sample.time_us))
+        .collect();
+
+    let previous = syncs.iter().rev().find(|(_, time)| *time <= seek_pos_us);
+    let next = syncs.iter().find(|(_, time)| *time >= seek_pos_us);
+    let target = match mode {
+        // Before the first sync sample, the NDK lands on that one
+        SeekMode::PreviousSync => previous.or(syncs.first()),
+        SeekMode::NextSync => next,
+        SeekMode::ClosestSync => match (previous, next) {
+            (Some(previous), Some(next)) => {
+                if seek_pos_us - previous.1 <= next.1 - seek_pos_us {
+                    Some(previous)
+                } else {
+                    Some(next)
+                }
+            }
+            (previous, next) => previous.or(next),
+        },
+    };
+
+    // Past the last sync sample, there is nothing left to read
+    extractor.cursor = target.map_or(data.samples.len(), |(index, _)| *index);
+    MediaStatus::Ok
+}
@@ -0,0 +1,355 @@
+use std::{
+    ffi::{c_void, CStr, CString},
+    fmt::Write,
+    os::raw::c_char,
+};
+
+use crate::{AMediaFormat, MediaFormat, MediaStatus};
+
+/// A single value stored in a mock format
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Int32(i32),
+    Int64(i64),
+    Float(f32),
+    Double(f64),
+    Size(usize),
+    Rect(i32, i32, i32, i32),
+    String(CString),
+    Buffer(Vec<u8>),
+}
+
+/// The mock `AMediaFormat`. Entries keep their insertion order, like the NDK's `AMessage` does.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Format {
+    entries: Vec<(String, Value)>,
+    description: CString,
+}
+
+impl Format {
+    /// Borrows the mock format behind a raw `AMediaFormat` pointer
+    pub(crate) unsafe fn from_ptr<'a>(format: *const AMediaFormat) -> &'a mut Format {
+        &mut *(format as *mut Format)
+    }
+
+    /// Copies the entries of a `MediaFormat` backed by this mock
+    pub(crate) fn copy_of(format: &MediaFormat) -> Format {
+        unsafe { Self::from_ptr(format.inner).clone() }
+    }
+
+    /// Hands the format over to the caller, who is responsible for calling `AMediaFormat_delete`
+    pub(crate) fn into_raw(self) -> *mut AMediaFormat {
+        Box::into_raw(Box::new(self)).cast()
+    }
+
+    /// Wraps a copy of this format in a `MediaFormat`
+    pub(crate) fn to_media_format(&self) -> MediaFormat {
+        MediaFormat::from_raw(self.clone().into_raw())
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: Value) {
+        match self.entries.iter_mut().find(|(key, _)| key == name) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((name.to_string(), value)),
+        }
+    }
+
+    /// Copies every entry of `other` into this format
+    pub(crate) fn extend(&mut self, other: &Format) {
+        for (name, value) in &other.entries {
+            self.set(name, value.clone());
+        }
+    }
+
+    /// Builds the same debug string as `AMediaFormat_toString`
+    fn describe(&self) -> String {
+        let mut out = String::new();
+
+        for (index, (name, value)) in self.entries.iter().enumerate() {
+            if index != 0 {
+                out.push_str(", ");
+            }
+
+            let _ = match value {
+                Value::Int32(value) => write!(out, "{name}: int32({value})"),
+                Value::Int64(value) => write!(out, "{name}: int64({value})"),
+                Value::Float(value) => write!(out, "{name}: float({value:.6})"),
+                Value::Double(value) => write!(out, "{name}: double({value:.6})"),
+                Value::Size(value) => write!(out, "{name}: size_t({value})"),
+                Value::Rect(left, top, right, bottom) => {
+                    write!(out, "{name}: Rect({left}, {top}, {right}, {bottom})")
+                }
+                Value::String(value) => {
+                    write!(out, "{name}: string({})", value.to_string_lossy())
+                }
+                Value::Buffer(_) => write!(out, "{name}: data"),
+            };
+        }
+
+        out
+    }
+}
+
+unsafe fn name<'a>(name: *const c_char) -> &'a str {
+    CStr::from_ptr(name).to_str().unwrap_or_default()
+}
+
+unsafe fn set(format: *mut AMediaFormat, key: *const c_char, value: Value) -> bool {
+    Format::from_ptr(format).set(name(key), value);
+    true
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_new() -> *mut AMediaFormat {
+    Format::default().into_raw()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_delete(format: *mut AMediaFormat) -> isize {
+    if !format.is_null() {
+        drop(Box::from_raw(format as *mut Format));
+    }
+
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_toString(format: *mut AMediaFormat) -> *const c_char {
+    let format = Format::from_ptr(format);
+    format.description = CString::new(format.describe()).unwrap_or_default();
+    format.description.as_ptr()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getInt32(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut i32,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Int32(value)) => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getInt64(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut i64,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Int64(value)) => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getFloat(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut f32,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Float(value)) => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getDouble(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut f64,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Double(value)) => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getRect(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    left: *mut i32,
+    top: *mut i32,
+    right: *mut i32,
+    bottom: *mut i32,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Rect(l, t, r, b)) => {
+            *left = *l;
+            *top = *t;
+            *right = *r;
+            *bottom = *b;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getSize(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut usize,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Size(value)) => {
+            *out = *value;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getBuffer(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut *mut c_void,
+    size: *mut usize,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::Buffer(value)) => {
+            *out = value.as_ptr() as *mut c_void;
+            *size = value.len();
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_getString(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    out: *mut *mut c_char,
+) -> bool {
+    match Format::from_ptr(format).get(name(key)) {
+        Some(Value::String(value)) => {
+            *out = value.as_ptr() as *mut c_char;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setInt32(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: i32,
+) -> bool {
+    set(format, key, Value::Int32(value))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setInt64(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: i64,
+) -> bool {
+    set(format, key, Value::Int64(value))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setFloat(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: f32,
+) -> bool {
+    set(format, key, Value::Float(value))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setDouble(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: f64,
+) -> bool {
+    set(format, key, Value::Double(value))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setSize(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: usize,
+) -> bool {
+    set(format, key, Value::Size(value))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setRect(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+) -> bool {
+    set(format, key, Value::Rect(left, top, right, bottom))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setString(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: *const c_char,
+) -> bool {
+    set(format, key, Value::String(CStr::from_ptr(value).into()))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_setBuffer(
+    format: *mut AMediaFormat,
+    key: *const c_char,
+    value: *const c_void,
+    size: usize,
+) -> bool {
+    let data = if value.is_null() || size == 0 {
+        vec![]
+    } else {
+        std::slice::from_raw_parts(value as *const u8, size).to_vec()
+    };
+
+    set(format, key, Value::Buffer(data))
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_clear(format: *mut AMediaFormat) {
+    Format::from_ptr(format).entries.clear();
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaFormat_copy(to: *mut AMediaFormat, from: *mut AMediaFormat) -> isize {
+    if to.is_null() || from.is_null() {
+        return MediaStatus::ErrorInvalidParameter as isize;
+    }
+
+    let entries = Format::from_ptr(from).entries.clone();
+    Format::from_ptr(to).entries = entries;
+
+    0
+}
@@ -0,0 +1,49 @@
+//! An in-process stand-in for the NDK media libraries.
+//!
+//! Every `AMediaFormat_*`, `AMediaCodec_*`, `AMediaExtractor_*`, `AMediaMuxer_*`, `AMediaDrm_*`,
+//! `AMediaCrypto_*` and `ANativeWindow_*` function used by this crate is implemented here in plain Rust,
+//! so the safe wrappers can be exercised with `cargo test` on any host.
+//!
+//! The mock gets compiled in when the `mock-ndk` feature is enabled, and whenever the crate is built for
+//! something other than Android.
+//!
+//! Codecs, extractors, muxers and DRM engines can be scripted with [MockCodec], [MockMedia], [MockMuxer] and
+//! [MockDrm]. The DRM engine only implements the ClearKey scheme.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::MediaStatus;
+
+mod codec;
+mod drm;
+mod extractor;
+mod format;
+mod muxer;
+mod native_window;
+
+pub use codec::*;
+pub use drm::*;
+pub use extractor::*;
+pub use muxer::*;
+pub use native_window::*;
+
+/// Errors waiting to be returned by the next calls to specific NDK functions
+#[derive(Debug, Default)]
+struct Failures {
+    pending: HashMap<String, VecDeque<MediaStatus>>,
+}
+
+impl Failures {
+    /// Queue `status` to be returned by the next call to `function`
+    fn push(&mut self, function: &str, status: MediaStatus) {
+        self.pending
+            .entry(function.to_string())
+            .or_default()
+            .push_back(status);
+    }
+
+    /// Takes the next error queued for `function`, if any
+    fn take(&mut self, function: &str) -> Option<MediaStatus> {
+        self.pending.get_mut(function)?.pop_front()
+    }
+}
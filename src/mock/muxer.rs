@@ -0,0 +1,310 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ptr::null_mut,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use super::{format::Format, Failures, MockSample};
+use crate::{AMediaFormat, AMediaMuxer, BufferInfo, MediaFormat, MediaStatus, OutputFormat};
+
+/// The lifecycle state of a mock muxer, as seen by the NDK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockMuxerState {
+    Initialized,
+    Started,
+    Stopped,
+    Released,
+}
+
+#[derive(Debug)]
+struct MuxerData {
+    state: MockMuxerState,
+    fail_creation: bool,
+    fd: i32,
+    output_format: Option<OutputFormat>,
+    location: Option<(f32, f32)>,
+    orientation_hint: i32,
+    tracks: Vec<Format>,
+    samples: Vec<MockSample>,
+    failures: Failures,
+}
+
+/// A scriptable stand-in for an `AMediaMuxer` instance.
+///
+/// Call [install](MockMuxer::install) before creating a `MediaMuxer`, and the next muxer created on the same thread will
+/// write into this mock instead of a file. Clones share the same muxer, so a test can keep one around to inspect what
+/// got written.
+#[derive(Debug, Clone)]
+pub struct MockMuxer {
+    data: Arc<Mutex<MuxerData>>,
+}
+
+thread_local! {
+    static PENDING: RefCell<VecDeque<MockMuxer>> = const { RefCell::new(VecDeque::new()) };
+}
+
+impl Default for MockMuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockMuxer {
+    /// Creates a muxer that accepts everything
+    pub fn new() -> Self {
+        Self {
+            data: Arc::new(Mutex::new(MuxerData {
+                state: MockMuxerState::Initialized,
+                fail_creation: false,
+                fd: -1,
+                output_format: None,
+                location: None,
+                orientation_hint: 0,
+                tracks: vec![],
+                samples: vec![],
+                failures: Failures::default(),
+            })),
+        }
+    }
+
+    /// Makes the next muxer created on this thread use this mock
+    pub fn install(&self) {
+        PENDING.with(|pending| pending.borrow_mut().push_back(self.clone()));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MuxerData> {
+        self.data.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Makes the muxer creation fail, the way it would for an invalid file descriptor
+    pub fn fail_creation(self) -> Self {
+        self.lock().fail_creation = true;
+        self
+    }
+
+    /// Makes the next call to `function` (e.g. `"AMediaMuxer_start"`) fail with `status`
+    pub fn fail_next(&self, function: &str, status: MediaStatus) {
+        self.lock().failures.push(function, status);
+    }
+
+    /// The current state of the muxer
+    pub fn state(&self) -> MockMuxerState {
+        self.lock().state
+    }
+
+    /// The file descriptor the muxer was created with
+    pub fn fd(&self) -> i32 {
+        self.lock().fd
+    }
+
+    /// The container format the muxer was created with
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.lock().output_format
+    }
+
+    /// The geodata set on the muxer, as (latitude, longitude)
+    pub fn location(&self) -> Option<(f32, f32)> {
+        self.lock().location
+    }
+
+    /// The orientation hint set on the muxer
+    pub fn orientation_hint(&self) -> i32 {
+        self.lock().orientation_hint
+    }
+
+    /// The formats of the tracks added so far
+    pub fn tracks(&self) -> Vec<MediaFormat> {
+        self.lock()
+            .tracks
+            .iter()
+            .map(Format::to_media_format)
+            .collect()
+    }
+
+    /// Every sample written so far
+    pub fn samples(&self) -> Vec<MockSample> {
+        self.lock().samples.clone()
+    }
+}
+
+impl MuxerData {
+    fn fail(&mut self, function: &str) -> Result<(), MediaStatus> {
+        match self.failures.take(function) {
+            Some(status) => Err(status),
+            None => Ok(()),
+        }
+    }
+
+    fn expect(&self, state: MockMuxerState) -> Result<(), MediaStatus> {
+        if self.state != state {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        Ok(())
+    }
+}
+
+unsafe fn lock<'a>(muxer: *mut AMediaMuxer) -> MutexGuard<'a, MuxerData> {
+    (*(muxer as *const MockMuxer)).lock()
+}
+
+fn status(result: Result<(), MediaStatus>) -> MediaStatus {
+    result.err().unwrap_or(MediaStatus::Ok)
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_new(fd: i32, format: OutputFormat) -> *mut AMediaMuxer {
+    let mock = PENDING
+        .with(|pending| pending.borrow_mut().pop_front())
+        .unwrap_or_default();
+
+    {
+        let mut data = mock.lock();
+        if data.fail_creation || fd < 0 {
+            return null_mut();
+        }
+
+        data.fd = fd;
+        data.output_format = Some(format);
+    }
+
+    Box::into_raw(Box::new(mock)).cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_delete(muxer: *mut AMediaMuxer) -> MediaStatus {
+    if muxer.is_null() {
+        return MediaStatus::ErrorInvalidObject;
+    }
+
+    let mock = Box::from_raw(muxer as *mut MockMuxer);
+    mock.lock().state = MockMuxerState::Released;
+
+    MediaStatus::Ok
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_setLocation(
+    muxer: *mut AMediaMuxer,
+    latitude: f32,
+    longitude: f32,
+) -> MediaStatus {
+    let mut data = lock(muxer);
+
+    status((|| {
+        data.fail("AMediaMuxer_setLocation")?;
+        data.expect(MockMuxerState::Initialized)?;
+
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        data.location = Some((latitude, longitude));
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_setOrientationHint(
+    muxer: *mut AMediaMuxer,
+    degrees: i32,
+) -> MediaStatus {
+    let mut data = lock(muxer);
+
+    status((|| {
+        data.fail("AMediaMuxer_setOrientationHint")?;
+        data.expect(MockMuxerState::Initialized)?;
+
+        if ![0, 90, 180, 270].contains(&degrees) {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        data.orientation_hint = degrees;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_addTrack(
+    muxer: *mut AMediaMuxer,
+    format: *const AMediaFormat,
+) -> isize {
+    let mut data = lock(muxer);
+
+    if let Err(status) = data.fail("AMediaMuxer_addTrack") {
+        return status as isize;
+    }
+
+    if data.state != MockMuxerState::Initialized || format.is_null() {
+        return MediaStatus::ErrorInvalidOperation as isize;
+    }
+
+    data.tracks.push(Format::from_ptr(format).clone());
+    data.tracks.len() as isize - 1
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_start(muxer: *mut AMediaMuxer) -> MediaStatus {
+    let mut data = lock(muxer);
+
+    status((|| {
+        data.fail("AMediaMuxer_start")?;
+        data.expect(MockMuxerState::Initialized)?;
+
+        if data.tracks.is_empty() {
+            return Err(MediaStatus::ErrorInvalidOperation);
+        }
+
+        data.state = MockMuxerState::Started;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_stop(muxer: *mut AMediaMuxer) -> MediaStatus {
+    let mut data = lock(muxer);
+
+    status((|| {
+        data.fail("AMediaMuxer_stop")?;
+        data.expect(MockMuxerState::Started)?;
+
+        data.state = MockMuxerState::Stopped;
+        Ok(())
+    })())
+}
+
+#[no_mangle]
+unsafe extern "C" fn AMediaMuxer_writeSampleData(
+    muxer: *mut AMediaMuxer,
+    track_index: usize,
+    sample: *const u8,
+    info: *const BufferInfo,
+) -> MediaStatus {
+    let mut data = lock(muxer);
+
+    status((|| {
+        data.fail("AMediaMuxer_writeSampleData")?;
+        data.expect(MockMuxerState::Started)?;
+
+        if track_index >= data.tracks.len() || info.is_null() {
+            return Err(MediaStatus::ErrorInvalidParameter);
+        }
+
+        let info = &*info;
+        let bytes = if info.size > 0 {
+            std::slice::from_raw_parts(sample.add(info.offset.max(0) as usize), info.size as usize)
+                .to_vec()
+        } else {
+            vec![]
+        };
+
+        data.samples.push(MockSample {
+            track: track_index,
+            time_us: info.presentation_time_us,
+            flags: info.flags,
+            data: bytes,
+        });
+        Ok(())
+    })())
+}
@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{ANativeWindow, ARect, NativeWindow, NativeWindowBuffer, NativeWindowFormat};
+
+/// The mock `ANativeWindow`. It's reference counted, just like the real one
+struct Window {
+    references: AtomicUsize,
+    width: i32,
+    height: i32,
+    format: i32,
+    pixels: Vec<u8>,
+    locked: bool,
+}
+
+/// Creates a window that can be handed to a codec as its output surface
+pub fn window(width: i32, height: i32) -> NativeWindow {
+    NativeWindow::from_raw(create(width, height))
+}
+
+pub(crate) fn create(width: i32, height: i32) -> *mut ANativeWindow {
+    let window = Window {
+        references: AtomicUsize::new(1),
+        width,
+        height,
+        format: NativeWindowFormat::Rgba8 as i32,
+        pixels: vec![],
+        locked: false,
+    };
+
+    Box::into_raw(Box::new(window)).cast()
+}
+
+unsafe fn get<'a>(window: *mut ANativeWindow) -> &'a mut Window {
+    &mut *(window as *mut Window)
+}
+
+pub(crate) unsafe fn acquire(window: *mut ANativeWindow) {
+    get(window).references.fetch_add(1, Ordering::SeqCst);
+}
+
+pub(crate) unsafe fn release(window: *mut ANativeWindow) {
+    if get(window).references.fetch_sub(1, Ordering::SeqCst) == 1 {
+        drop(Box::from_raw(window as *mut Window));
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_acquire(window: *mut ANativeWindow) {
+    acquire(window);
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_release(window: *mut ANativeWindow) {
+    release(window);
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_getWidth(window: *mut ANativeWindow) -> i32 {
+    get(window).width
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_getHeight(window: *mut ANativeWindow) -> i32 {
+    get(window).height
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_getFormat(window: *mut ANativeWindow) -> i32 {
+    get(window).format
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_setBuffersGeometry(
+    window: *mut ANativeWindow,
+    width: i32,
+    height: i32,
+    format: i32,
+) -> i32 {
+    let window = get(window);
+
+    if width != 0 {
+        window.width = width;
+    }
+    if height != 0 {
+        window.height = height;
+    }
+    if format != 0 {
+        window.format = format;
+    }
+
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_lock(
+    window: *mut ANativeWindow,
+    buffer: *mut NativeWindowBuffer,
+    rect: *mut ARect,
+) -> i32 {
+    let window = get(window);
+
+    // -EINVAL
+    if window.locked || window.width <= 0 || window.height <= 0 {
+        return -22;
+    }
+
+    window.pixels = vec![0; (window.width * window.height * 4) as usize];
+    window.locked = true;
+
+    let buffer = &mut *buffer;
+    buffer.width = window.width;
+    buffer.height = window.height;
+    buffer.stride = window.width;
+    buffer.format = window.format;
+    buffer.bits = window.pixels.as_mut_ptr().cast();
+
+    if !rect.is_null() {
+        *rect = ARect {
+            left: 0,
+            top: 0,
+            right: window.width,
+            bottom: window.height,
+        };
+    }
+
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn ANativeWindow_unlockAndPost(window: *mut ANativeWindow) -> i32 {
+    let window = get(window);
+
+    if !window.locked {
+        return -22;
+    }
+
+    window.locked = false;
+    0
+}
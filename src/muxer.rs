@@ -1,6 +1,6 @@
 use log::warn;
 
-use crate::{AMediaFormat, BufferInfo, MediaFormat, MediaStatus};
+use crate::{AMediaFormat, BufferInfo, Error, MediaFormat, MediaStatus, OutputBuffer, Packet};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
@@ -19,7 +19,7 @@ pub enum OutputFormat {
 
 // FFI FUNCTIONS
 
-#[link(name = "mediandk")]
+#[cfg_attr(not(mock_ndk), link(name = "mediandk"))]
 extern "C" {
     /// Since: API 21
     pub fn AMediaMuxer_new(fd: i32, format: OutputFormat) -> *mut AMediaMuxer;
@@ -84,14 +84,17 @@ impl MediaMuxer {
     /// `fd` is the file descriptor to write data to
     ///
     /// `output_format` is the container format for the output
-    pub fn new(fd: i32, output_format: OutputFormat) -> Option<Self> {
+    pub fn new(fd: i32, output_format: OutputFormat) -> Result<Self, Error> {
         let value = unsafe { AMediaMuxer_new(fd, output_format) };
 
         if value.is_null() {
-            return None;
+            return Err(Error::new(
+                MediaStatus::ErrorInvalidParameter,
+                "create muxer",
+            ));
         }
 
-        Some(Self {
+        Ok(Self {
             inner: value,
             latitude: 0f32,
             longitude: 0f32,
@@ -113,12 +116,12 @@ impl MediaMuxer {
     /// Longitude must be in the range (-180, 180)
     pub fn set_location(&mut self, latitude: f32, longitude: f32) -> &mut Self {
         match latitude {
-            value if value >= -90.0 && value <= 90.0 => self.latitude = latitude,
+            value if (-90.0..=90.0).contains(&value) => self.latitude = latitude,
             _ => {}
         }
 
         match longitude {
-            value if value >= -180.0 && value <= 180.0 => self.longitude = longitude,
+            value if (-180.0..=180.0).contains(&value) => self.longitude = longitude,
             _ => {}
         }
 
@@ -230,6 +233,32 @@ impl MediaMuxer {
 
         Ok(())
     }
+
+    /// Writes a packet read by a [MediaExtractor](crate::MediaExtractor) into the muxer, which remuxes it without decoding
+    pub fn write_packet(&mut self, track_index: usize, packet: &Packet) -> Result<(), MediaStatus> {
+        self.write_sample_data(track_index, packet.data(), &packet.buffer_info())
+    }
+
+    /// Writes an encoder output buffer into the muxer.
+    ///
+    /// Codec config buffers are skipped, since the muxer reads those from the track format.
+    /// So are empty buffers, like the one flagged with the end of stream
+    pub fn write_buffer(
+        &mut self,
+        track_index: usize,
+        buffer: &impl OutputBuffer,
+    ) -> Result<(), MediaStatus> {
+        let info = buffer.info();
+        if info.is_codec_config() || info.size() <= 0 {
+            return Ok(());
+        }
+
+        let data = buffer.data().ok_or(MediaStatus::ErrorInvalidOperation)?;
+
+        // The data already starts at the buffer offset
+        let info = BufferInfo { offset: 0, ..*info };
+        self.write_sample_data(track_index, data, &info)
+    }
 }
 
 unsafe impl Send for MediaMuxer {}
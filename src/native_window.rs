@@ -1,5 +1,6 @@
 use std::{ffi::c_void, ops::BitOr, ptr::null_mut};
 
+#[cfg(target_os = "android")]
 use jni::{objects::JObject, JNIEnv};
 
 /// Represents an image buffer (or a Surface in Java)
@@ -125,11 +126,12 @@ pub struct ARect {
 
 // Functions start
 
-#[link(name = "android")]
+#[cfg_attr(not(mock_ndk), link(name = "android"))]
 extern "C" {
+    #[cfg(target_os = "android")]
     fn ANativeWindow_fromSurface(env: JNIEnv, surface: JObject) -> *mut ANativeWindow;
 
-    #[cfg(feature = "api26")]
+    #[cfg(all(feature = "api26", target_os = "android"))]
     /// Since API 26
     fn ANativeWindow_toSurface(env: JNIEnv, window: *mut ANativeWindow) -> JObject;
 
@@ -171,6 +173,7 @@ impl NativeWindow {
         Self { inner }
     }
 
+    #[cfg(target_os = "android")]
     pub fn from_surface(surface: JObject) -> Self {
         unsafe {
             let env = javavm::get_env();
@@ -178,7 +181,7 @@ impl NativeWindow {
         }
     }
 
-    #[cfg(feature = "api26")]
+    #[cfg(all(feature = "api26", target_os = "android"))]
     pub fn to_surface(&self) -> JObject {
         let env = javavm::get_env();
         unsafe { ANativeWindow_toSurface(env, self.inner) }
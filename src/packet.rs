@@ -0,0 +1,203 @@
+use std::{
+    borrow::Cow,
+    ops::{BitOr, BitOrAssign},
+};
+
+use crate::{BufferFlag, BufferInfo, Error, MediaExtractor};
+
+/// The flags of a demuxed sample, as returned by [MediaExtractor::sample_flags](MediaExtractor::sample_flags)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SampleFlags(u32);
+
+impl SampleFlags {
+    /// `AMEDIAEXTRACTOR_SAMPLE_FLAG_SYNC`
+    pub const SYNC: Self = Self(1);
+    /// `AMEDIAEXTRACTOR_SAMPLE_FLAG_ENCRYPTED`
+    pub const ENCRYPTED: Self = Self(2);
+    /// Matches `MediaExtractor.SAMPLE_FLAG_PARTIAL_FRAME` in the Java API
+    pub const PARTIAL_FRAME: Self = Self(4);
+
+    /// No flag set
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Keeps every bit, including the ones without a constant
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// The raw flags, as the extractor returns them
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether every flag of `other` is set
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether decoding can start at this sample
+    pub const fn is_sync(self) -> bool {
+        self.contains(Self::SYNC)
+    }
+
+    /// Whether the sample is encrypted, and has to be queued with [queue_secure](crate::CodecInputBuffer::queue_secure)
+    pub const fn is_encrypted(self) -> bool {
+        self.contains(Self::ENCRYPTED)
+    }
+
+    /// Whether the sample only holds part of a frame
+    pub const fn is_partial(self) -> bool {
+        self.contains(Self::PARTIAL_FRAME)
+    }
+}
+
+impl From<u32> for SampleFlags {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<SampleFlags> for u32 {
+    fn from(value: SampleFlags) -> Self {
+        value.0
+    }
+}
+
+impl BitOr for SampleFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for SampleFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A demuxed packet, read by [MediaExtractor::read_packet](MediaExtractor::read_packet).
+///
+/// The data is either owned, or borrowed from a buffer reused across packets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet<'a> {
+    data: Cow<'a, [u8]>,
+    track_index: usize,
+    presentation_time_us: i64,
+    flags: SampleFlags,
+}
+
+impl<'a> Packet<'a> {
+    /// Creates a packet of `track_index`, like one read from a custom source
+    pub fn new(
+        track_index: usize,
+        presentation_time_us: i64,
+        flags: impl Into<SampleFlags>,
+        data: impl Into<Cow<'a, [u8]>>,
+    ) -> Self {
+        Self {
+            data: data.into(),
+            track_index,
+            presentation_time_us,
+            flags: flags.into(),
+        }
+    }
+
+    /// The encoded data of the packet
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Copies the data if it was borrowed, so the packet can outlive its buffer
+    pub fn into_owned(self) -> Packet<'static> {
+        Packet {
+            data: Cow::Owned(self.data.into_owned()),
+            track_index: self.track_index,
+            presentation_time_us: self.presentation_time_us,
+            flags: self.flags,
+        }
+    }
+
+    /// The index of the track the packet belongs to
+    pub fn track_index(&self) -> usize {
+        self.track_index
+    }
+
+    /// When the packet should be presented, in microseconds
+    pub fn presentation_time_us(&self) -> i64 {
+        self.presentation_time_us
+    }
+
+    /// The sample flags, as returned by the extractor
+    pub fn flags(&self) -> SampleFlags {
+        self.flags
+    }
+
+    /// Whether decoding can start at this packet
+    pub fn is_sync(&self) -> bool {
+        self.flags.is_sync()
+    }
+
+    /// Whether the data is encrypted, and has to be queued with [queue_secure](crate::CodecInputBuffer::queue_secure)
+    pub fn is_encrypted(&self) -> bool {
+        self.flags.is_encrypted()
+    }
+
+    /// Whether the packet only holds part of a frame
+    pub fn is_partial(&self) -> bool {
+        self.flags.is_partial()
+    }
+
+    /// Describes the packet for [MediaMuxer::write_sample_data](crate::MediaMuxer::write_sample_data), translating
+    /// the sample flags to their codec buffer counterparts
+    pub fn buffer_info(&self) -> BufferInfo {
+        BufferInfo {
+            offset: 0,
+            size: self.data.len() as i32,
+            presentation_time_us: self.presentation_time_us,
+            flags: codec_flags(self.flags),
+        }
+    }
+}
+
+/// Translates extractor sample flags to codec buffer flags.
+///
+/// They can't be passed through as is, since the encrypted sample flag has the value of the codec config buffer flag
+pub(crate) fn codec_flags(sample_flags: SampleFlags) -> u32 {
+    let mut flags = 0;
+    if sample_flags.is_sync() {
+        // The key frame flag shares its value with the encode flag
+        BufferFlag::Encode.add_to_flag(&mut flags);
+    }
+    if sample_flags.is_partial() {
+        BufferFlag::PartialFrame.add_to_flag(&mut flags);
+    }
+
+    flags as u32
+}
+
+/// An iterator over the packets of the selected tracks. See [MediaExtractor::packets](MediaExtractor::packets).
+///
+/// Yields an error when a packet couldn't be read, and stops afterwards
+#[derive(Debug)]
+pub struct Packets<'a> {
+    pub(crate) extractor: &'a mut MediaExtractor,
+    pub(crate) done: bool,
+}
+
+impl Iterator for Packets<'_> {
+    type Item = Result<Packet<'static>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let packet = self.extractor.read_packet().transpose();
+        self.done = !matches!(packet, Some(Ok(_)));
+        packet
+    }
+}
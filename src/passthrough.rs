@@ -0,0 +1,408 @@
+use std::collections::VecDeque;
+
+use crate::{
+    keys, AudioFrame, BufferFlag, BufferInfo, Codec, CodecState, Error, Frame, InputBuffer,
+    MediaFormat, MediaStatus, NativeWindow, OutputBuffer, RawVideoFrame, SampleFormat, VideoFrame,
+    ENCODING_PCM_16BIT,
+};
+
+const MIME_AUDIO_RAW: &str = "audio/raw";
+const MIME_VIDEO_RAW: &str = "video/raw";
+
+/// `COLOR_FormatYUV420Planar`, the layout assumed for raw video when the format doesn't say otherwise
+const COLOR_FORMAT_YUV420_PLANAR: i32 = 19;
+
+/// The largest width or height of raw video, which keeps the buffers at a sane size
+const MAX_VIDEO_DIMENSION: i32 = 16384;
+
+const INPUT_BUFFERS: usize = 4;
+const AUDIO_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The parameters read from the configured format
+#[derive(Debug, Clone, Copy)]
+enum Params {
+    Audio {
+        channels: i32,
+        sample_rate: i32,
+        encoding: i32,
+    },
+    Video {
+        width: i32,
+        height: i32,
+        color_format: i32,
+    },
+}
+
+impl Params {
+    fn from_format(mime: &str, format: &MediaFormat) -> Result<Self, MediaStatus> {
+        let require = |key| format.get(key).ok_or(MediaStatus::ErrorInvalidParameter);
+
+        if mime == MIME_AUDIO_RAW {
+            Ok(Self::Audio {
+                channels: require(keys::CHANNEL_COUNT)?,
+                sample_rate: require(keys::SAMPLE_RATE)?,
+                encoding: format
+                    .get(keys::PCM_ENCODING)
+                    .unwrap_or(ENCODING_PCM_16BIT as i32),
+            })
+        } else {
+            let width = require(keys::WIDTH)?;
+            let height = require(keys::HEIGHT)?;
+
+            let valid = 1..=MAX_VIDEO_DIMENSION;
+            if !valid.contains(&width) || !valid.contains(&height) {
+                return Err(MediaStatus::ErrorInvalidParameter);
+            }
+
+            let params = Self::Video {
+                width,
+                height,
+                color_format: format
+                    .get(keys::COLOR_FORMAT)
+                    .unwrap_or(COLOR_FORMAT_YUV420_PLANAR),
+            };
+            params
+                .buffer_size()
+                .ok_or(MediaStatus::ErrorInvalidParameter)?;
+
+            Ok(params)
+        }
+    }
+
+    /// The size of each buffer the codec hands out, if it fits in memory
+    fn buffer_size(&self) -> Option<usize> {
+        match *self {
+            Self::Audio { .. } => Some(AUDIO_BUFFER_SIZE),
+            // Enough room for a full 4:2:0 frame
+            Self::Video { width, height, .. } => (width as usize)
+                .checked_mul(height as usize)?
+                .checked_mul(3)
+                .map(|size| size / 2),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Packet {
+    data: Vec<u8>,
+    info: BufferInfo,
+}
+
+/// A pure Rust [Codec](Codec) that hands every input buffer back as output, untouched.
+///
+/// It handles raw PCM audio (`audio/raw`) and raw YUV video (`video/raw`), so it can stand in for a real codec in code
+/// that's generic over [Codec](Codec), on machines with no Android device around.
+#[derive(Debug)]
+pub struct PassthroughCodec {
+    mime: String,
+    state: CodecState,
+    params: Option<Params>,
+    free: Vec<Vec<u8>>,
+    pending: VecDeque<Packet>,
+}
+
+impl PassthroughCodec {
+    /// Creates a passthrough codec for `audio/raw` or `video/raw`.
+    ///
+    /// Fails with `ErrorUnsupported` for any other mime type
+    pub fn new(mime_type: &str) -> Result<Self, Error> {
+        if mime_type != MIME_AUDIO_RAW && mime_type != MIME_VIDEO_RAW {
+            return Err(
+                Error::new(MediaStatus::ErrorUnsupported, "create codec").with_mime_type(mime_type)
+            );
+        }
+
+        Ok(Self {
+            mime: mime_type.to_string(),
+            state: CodecState::Uninitialized,
+            params: None,
+            free: vec![],
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Returns the mime type this codec was created for
+    pub fn mime_type(&self) -> &str {
+        &self.mime
+    }
+
+    fn error(&self, status: MediaStatus, operation: &'static str) -> Error {
+        Error::new(status, operation).with_mime_type(&self.mime)
+    }
+
+    fn expect(&self, operation: &'static str, states: &[CodecState]) -> Result<(), Error> {
+        if !states.contains(&self.state) {
+            return Err(self
+                .error(MediaStatus::ErrorInvalidOperation, operation)
+                .with_codec_state(self.state));
+        }
+
+        Ok(())
+    }
+
+    /// Hands every pending buffer back to the pool
+    fn recycle_pending(&mut self) {
+        while let Some(packet) = self.pending.pop_front() {
+            self.recycle(packet.data);
+        }
+    }
+
+    fn recycle(&mut self, mut data: Vec<u8>) {
+        let size = self
+            .params
+            .and_then(|params| params.buffer_size())
+            .unwrap_or(0);
+        data.resize(size, 0);
+        self.free.push(data);
+    }
+}
+
+impl Codec for PassthroughCodec {
+    type InputBuffer<'a> = PassthroughInputBuffer<'a>;
+    type OutputBuffer<'a> = PassthroughOutputBuffer<'a>;
+
+    fn configure(
+        &mut self,
+        format: &MediaFormat,
+        surface: Option<NativeWindow>,
+        _flags: u32,
+    ) -> Result<(), Error> {
+        self.expect("configure", &[CodecState::Uninitialized])?;
+
+        // Nothing to render with
+        if surface.is_some() {
+            return Err(self.error(MediaStatus::ErrorUnsupported, "configure"));
+        }
+
+        if format.get(keys::MIME).as_deref() != Some(self.mime.as_str()) {
+            return Err(self.error(MediaStatus::ErrorUnsupported, "configure"));
+        }
+
+        let params = Params::from_format(&self.mime, format)
+            .map_err(|status| self.error(status, "configure"))?;
+        self.params = Some(params);
+        self.state = CodecState::Configured;
+
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        self.expect("start", &[CodecState::Configured])?;
+
+        let size = self
+            .params
+            .and_then(|params| params.buffer_size())
+            .unwrap_or(0);
+        self.free = (0..INPUT_BUFFERS).map(|_| vec![0; size]).collect();
+        self.state = CodecState::Executing;
+
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        self.pending.clear();
+        self.free.clear();
+        self.params = None;
+        self.state = CodecState::Uninitialized;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.expect("flush", &[CodecState::Executing, CodecState::EndOfStream])?;
+        self.recycle_pending();
+        self.state = CodecState::Executing;
+
+        Ok(())
+    }
+
+    fn dequeue_input(&mut self) -> Result<Self::InputBuffer<'_>, Error> {
+        if self.state == CodecState::EndOfStream {
+            return Err(self
+                .error(MediaStatus::ErrorEndOfStream, "dequeue input buffer")
+                .with_codec_state(CodecState::EndOfStream));
+        }
+        self.expect("dequeue input buffer", &[CodecState::Executing])?;
+
+        let Some(mut data) = self.free.pop() else {
+            return Err(self.error(MediaStatus::ErrorWouldBlock, "dequeue input buffer"));
+        };
+
+        Ok(PassthroughInputBuffer {
+            codec: self,
+            ptr: data.as_mut_ptr(),
+            data,
+            write_size: 0,
+            time: 0,
+            flags: 0,
+        })
+    }
+
+    fn dequeue_output(&mut self) -> Result<Self::OutputBuffer<'_>, Error> {
+        self.expect(
+            "dequeue output buffer",
+            &[CodecState::Executing, CodecState::EndOfStream],
+        )?;
+
+        let Some(packet) = self.pending.pop_front() else {
+            return Err(self.error(MediaStatus::ErrorWouldBlock, "dequeue output buffer"));
+        };
+
+        Ok(PassthroughOutputBuffer {
+            codec: self,
+            packet,
+        })
+    }
+
+    fn output_format(&self) -> Option<MediaFormat> {
+        let params = self.params?;
+        let mut format = MediaFormat::new()?;
+        format.set(keys::MIME, self.mime.as_str());
+
+        match params {
+            Params::Audio {
+                channels,
+                sample_rate,
+                encoding,
+            } => {
+                format.set(keys::CHANNEL_COUNT, channels);
+                format.set(keys::SAMPLE_RATE, sample_rate);
+                format.set(keys::PCM_ENCODING, encoding);
+            }
+            Params::Video {
+                width,
+                height,
+                color_format,
+            } => {
+                format.set(keys::WIDTH, width);
+                format.set(keys::HEIGHT, height);
+                format.set(keys::STRIDE, width);
+                format.set(keys::SLICE_HEIGHT, height);
+                format.set(keys::COLOR_FORMAT, color_format);
+            }
+        }
+
+        Some(format)
+    }
+
+    fn state(&self) -> CodecState {
+        self.state
+    }
+}
+
+/// An input buffer of a [PassthroughCodec](PassthroughCodec)
+#[derive(Debug)]
+pub struct PassthroughInputBuffer<'a> {
+    codec: &'a mut PassthroughCodec,
+    ptr: *mut u8,
+    data: Vec<u8>,
+    write_size: usize,
+    time: u64,
+    flags: u32,
+}
+
+impl PassthroughInputBuffer<'_> {
+    /// The buffer as a mutable slice
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl InputBuffer for PassthroughInputBuffer<'_> {
+    fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn write_size(&self) -> usize {
+        self.write_size
+    }
+
+    fn buffer(&self) -> (*mut u8, usize) {
+        (self.ptr, self.data.len())
+    }
+
+    fn set_time(&mut self, time: u64) {
+        self.time = time;
+    }
+
+    fn set_flags(&mut self, flags: u32) {
+        self.flags = flags;
+    }
+
+    fn set_write_size(&mut self, write_size: usize) {
+        self.write_size = write_size.min(self.data.len());
+    }
+}
+
+impl Drop for PassthroughInputBuffer<'_> {
+    fn drop(&mut self) {
+        let mut data = std::mem::take(&mut self.data);
+        data.truncate(self.write_size);
+
+        let info = BufferInfo {
+            offset: 0,
+            size: self.write_size as i32,
+            presentation_time_us: self.time as i64,
+            flags: self.flags,
+        };
+
+        self.codec.pending.push_back(Packet { data, info });
+
+        if BufferFlag::EndOfStream.is_contained_in(self.flags as i32) {
+            self.codec.state = CodecState::EndOfStream;
+        }
+    }
+}
+
+/// An output buffer of a [PassthroughCodec](PassthroughCodec)
+#[derive(Debug)]
+pub struct PassthroughOutputBuffer<'a> {
+    codec: &'a mut PassthroughCodec,
+    packet: Packet,
+}
+
+impl OutputBuffer for PassthroughOutputBuffer<'_> {
+    fn info(&self) -> &BufferInfo {
+        &self.packet.info
+    }
+
+    fn data(&self) -> Option<&[u8]> {
+        Some(&self.packet.data)
+    }
+
+    fn frame(&self) -> Option<Frame<'_>> {
+        let data = self.data()?;
+
+        match self.codec.params? {
+            Params::Audio {
+                channels, encoding, ..
+            } => {
+                if channels <= 0 {
+                    return None;
+                }
+
+                let format = SampleFormat::from_bytes(encoding as usize, data)?;
+                Some(Frame::Audio(AudioFrame::new(format, channels as u32)))
+            }
+            Params::Video { .. } => {
+                let frame = RawVideoFrame::new(data, &self.codec.output_format()?)?;
+                Some(Frame::Video(VideoFrame::RawFrame(frame)))
+            }
+        }
+    }
+
+    fn set_render(&mut self, _render: bool) {
+        // There's never a surface to render to
+    }
+}
+
+impl Drop for PassthroughOutputBuffer<'_> {
+    fn drop(&mut self) {
+        let data = std::mem::take(&mut self.packet.data);
+        self.codec.recycle(data);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::{keys, MediaFormat, Rect};
+
 /// Represents a codec frame (either audio or video)
 #[derive(Debug)]
 pub enum Frame<'a> {
@@ -15,6 +17,25 @@ pub enum SampleFormat<'a> {
     F32(&'a [f32]),
 }
 
+impl<'a> SampleFormat<'a> {
+    /// Interprets `bytes` as samples with the specified PCM encoding (`ENCODING_PCM_16BIT` or `ENCODING_PCM_FLOAT`).
+    ///
+    /// Returns None for any other encoding, or if the bytes are not aligned for the sample type
+    pub fn from_bytes(encoding: usize, bytes: &'a [u8]) -> Option<Self> {
+        match encoding {
+            ENCODING_PCM_16BIT => {
+                let (prefix, samples, _) = unsafe { bytes.align_to::<i16>() };
+                prefix.is_empty().then_some(Self::S16(samples))
+            }
+            ENCODING_PCM_FLOAT => {
+                let (prefix, samples, _) = unsafe { bytes.align_to::<f32>() };
+                prefix.is_empty().then_some(Self::F32(samples))
+            }
+            _ => None,
+        }
+    }
+}
+
 impl SampleFormat<'_> {
     /// Returns the number of samples contained by this format
     fn samples(&self, channels: u32) -> usize {
@@ -25,7 +46,7 @@ impl SampleFormat<'_> {
     }
 
     /// Returns the size of one sample represented by this format
-    fn sample_size(&self) -> usize {
+    pub fn sample_size(&self) -> usize {
         match self {
             SampleFormat::S16(_) => std::mem::size_of::<i16>(),
             SampleFormat::F32(_) => std::mem::size_of::<f32>(),
@@ -33,7 +54,7 @@ impl SampleFormat<'_> {
     }
 
     /// Returns the size of one frame represented by this format. It needs the number of channels stored in this buffer to determine the value
-    fn frame_size(&self, channels: u32) -> usize {
+    pub fn frame_size(&self, channels: u32) -> usize {
         self.sample_size() * channels as usize
     }
 }
@@ -57,7 +78,7 @@ impl<'a> AudioFrame<'a> {
     }
 
     /// Returns the sample format for this frame
-    pub fn format(&self) -> &SampleFormat {
+    pub fn format(&self) -> &SampleFormat<'_> {
         &self.format
     }
 
@@ -77,8 +98,265 @@ pub enum VideoFrame<'a> {
     RawFrame(RawVideoFrame<'a>),
 }
 
+/// The color formats a codec can use for raw video buffers, as found under the `color-format` key of its output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    /// `COLOR_FormatYUV420Planar` (I420)
+    YUV420Planar,
+    /// `COLOR_FormatYUV420PackedPlanar`
+    YUV420PackedPlanar,
+    /// `COLOR_FormatYUV420SemiPlanar` (NV12)
+    YUV420SemiPlanar,
+    /// `COLOR_FormatYUV420PackedSemiPlanar`
+    YUV420PackedSemiPlanar,
+    /// `COLOR_FormatYUV420Flexible`. Codecs report the actual layout in the output format instead
+    YUV420Flexible,
+    /// `COLOR_TI_FormatYUV420PackedSemiPlanar`
+    TiYUV420PackedSemiPlanar,
+    /// `COLOR_QCOM_FormatYUV420SemiPlanar`
+    QcomYUV420SemiPlanar,
+    /// Any other vendor-specific format
+    Other(i32),
+}
+
+impl ColorFormat {
+    /// Returns the value used in a `MediaFormat` for this color format
+    pub fn value(&self) -> i32 {
+        match *self {
+            Self::YUV420Planar => 19,
+            Self::YUV420PackedPlanar => 20,
+            Self::YUV420SemiPlanar => 21,
+            Self::YUV420PackedSemiPlanar => 39,
+            Self::YUV420Flexible => 0x7F420888,
+            Self::TiYUV420PackedSemiPlanar => 0x7F000100,
+            Self::QcomYUV420SemiPlanar => 0x7FA30C00,
+            Self::Other(value) => value,
+        }
+    }
+
+    /// Whether the U and V samples are in separate planes
+    pub fn is_planar(&self) -> bool {
+        matches!(self, Self::YUV420Planar | Self::YUV420PackedPlanar)
+    }
+
+    /// Whether the U and V samples are interleaved in a single plane
+    pub fn is_semi_planar(&self) -> bool {
+        matches!(
+            self,
+            Self::YUV420SemiPlanar
+                | Self::YUV420PackedSemiPlanar
+                | Self::TiYUV420PackedSemiPlanar
+                | Self::QcomYUV420SemiPlanar
+        )
+    }
+}
+
+impl From<i32> for ColorFormat {
+    fn from(value: i32) -> Self {
+        [
+            Self::YUV420Planar,
+            Self::YUV420PackedPlanar,
+            Self::YUV420SemiPlanar,
+            Self::YUV420PackedSemiPlanar,
+            Self::YUV420Flexible,
+            Self::TiYUV420PackedSemiPlanar,
+            Self::QcomYUV420SemiPlanar,
+        ]
+        .into_iter()
+        .find(|format| format.value() == value)
+        .unwrap_or(Self::Other(value))
+    }
+}
+
+/// One plane of a raw video frame
+#[derive(Debug, Clone, Copy)]
+pub struct Plane<'a> {
+    /// The plane data, starting with its first row. The last row may be shorter than the stride
+    pub data: &'a [u8],
+    /// The distance (in bytes) between the starts of two consecutive rows
+    pub stride: usize,
+}
+
+impl<'a> Plane<'a> {
+    /// Returns row `index` of the plane, up to the stride
+    pub fn row(&self, index: usize) -> Option<&'a [u8]> {
+        let start = index * self.stride;
+        let end = (start + self.stride).min(self.data.len());
+
+        self.data.get(start..end)
+    }
+}
+
+/// The planes of a 4:2:0 raw video frame
+#[derive(Debug, Clone, Copy)]
+pub enum VideoPlanes<'a> {
+    /// Y, U and V each have their own plane
+    Planar {
+        y: Plane<'a>,
+        u: Plane<'a>,
+        v: Plane<'a>,
+    },
+    /// A Y plane, followed by a plane of interleaved U and V samples
+    SemiPlanar { y: Plane<'a>, uv: Plane<'a> },
+}
+
+/// The `crop` entry of `format`, which can only be read from API 28
+fn display_crop(format: &MediaFormat) -> Option<Rect> {
+    #[cfg(feature = "api28")]
+    return format.get(keys::DISPLAY_CROP);
+    #[cfg(not(feature = "api28"))]
+    {
+        let _ = format;
+        None
+    }
+}
+
 /// A raw video frame with pixel format and a byte buffer to read the data
 #[derive(Debug)]
 pub struct RawVideoFrame<'a> {
     pub buffer: &'a [u8],
+    color_format: ColorFormat,
+    width: usize,
+    height: usize,
+    stride: usize,
+    slice_height: usize,
+    crop: Rect,
+}
+
+impl<'a> RawVideoFrame<'a> {
+    /// Describes `buffer` using the `color-format`, `width`, `height`, `stride`, `slice-height` and `crop` (or
+    /// `crop-*`) entries of `format`. The crop rectangle is clamped to the frame.
+    ///
+    /// Returns None if the format has no dimensions or color format
+    pub fn new(buffer: &'a [u8], format: &MediaFormat) -> Option<Self> {
+        let width = format.get(keys::WIDTH)?;
+        let height = format.get(keys::HEIGHT)?;
+        let color_format = ColorFormat::from(format.get(keys::COLOR_FORMAT)?);
+
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+
+        // Codecs leave out the stride and slice height when they match the frame size
+        let stride = format.get(keys::STRIDE).filter(|&value| value >= width);
+        let slice_height = format
+            .get(keys::SLICE_HEIGHT)
+            .filter(|&value| value >= height);
+
+        let crop = display_crop(format).unwrap_or_else(|| Rect {
+            left: format.get(keys::CROP_LEFT).unwrap_or(0),
+            top: format.get(keys::CROP_TOP).unwrap_or(0),
+            right: format.get(keys::CROP_RIGHT).unwrap_or(width - 1),
+            bottom: format.get(keys::CROP_BOTTOM).unwrap_or(height - 1),
+        });
+
+        // Keeps the rectangle within the frame, and at least one pixel wide
+        let left = crop.left.clamp(0, width - 1);
+        let top = crop.top.clamp(0, height - 1);
+        let crop = Rect {
+            left,
+            top,
+            right: crop.right.clamp(left, width - 1),
+            bottom: crop.bottom.clamp(top, height - 1),
+        };
+
+        Some(Self {
+            buffer,
+            color_format,
+            width: width as usize,
+            height: height as usize,
+            stride: stride.unwrap_or(width) as usize,
+            slice_height: slice_height.unwrap_or(height) as usize,
+            crop,
+        })
+    }
+
+    /// The layout of the frame data
+    pub fn color_format(&self) -> ColorFormat {
+        self.color_format
+    }
+
+    /// The width of the frame, including the area outside the crop rectangle
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the frame, including the area outside the crop rectangle
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The distance (in bytes) between the starts of two consecutive rows of the Y plane
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The number of rows of the Y plane, including padding
+    pub fn slice_height(&self) -> usize {
+        self.slice_height
+    }
+
+    /// The area of the frame that should be displayed
+    pub fn crop(&self) -> Rect {
+        self.crop
+    }
+
+    /// Returns the planes of the frame.
+    ///
+    /// Returns None for color formats with an unknown layout, or if the buffer is too small for the planes. Only the
+    /// padding after the last row may be missing
+    pub fn planes(&self) -> Option<VideoPlanes<'a>> {
+        // The sizes come from the codec, and can overflow on 32-bit targets
+        let luma_size = self.stride.checked_mul(self.slice_height)?;
+        let chroma_rows = self.slice_height.div_ceil(2);
+
+        let plane = |start: usize, size: usize, stride: usize| {
+            self.buffer
+                .get(start..start.checked_add(size)?)
+                .map(|data| Plane { data, stride })
+        };
+        // Codecs may leave out the padding of the last row, as nothing follows it
+        let last_plane = |start: usize, size: usize, stride: usize, row_width: usize| {
+            let padding = stride.saturating_sub(row_width);
+            let end = start.checked_add(size)?;
+            if self.buffer.len() < end - padding.min(size) {
+                return None;
+            }
+
+            let end = end.min(self.buffer.len());
+            Some(Plane {
+                data: &self.buffer[start..end],
+                stride,
+            })
+        };
+
+        let y = plane(0, luma_size, self.stride)?;
+
+        if self.color_format.is_planar() {
+            let chroma_stride = self.stride.div_ceil(2);
+            let chroma_size = chroma_stride.checked_mul(chroma_rows)?;
+            let chroma_width = self.width.div_ceil(2);
+
+            let u = plane(luma_size, chroma_size, chroma_stride)?;
+            let v = last_plane(
+                luma_size.checked_add(chroma_size)?,
+                chroma_size,
+                chroma_stride,
+                chroma_width,
+            )?;
+
+            Some(VideoPlanes::Planar { y, u, v })
+        } else if self.color_format.is_semi_planar() {
+            let uv = last_plane(
+                luma_size,
+                self.stride.checked_mul(chroma_rows)?,
+                self.stride,
+                self.width.div_ceil(2) * 2,
+            )?;
+
+            Some(VideoPlanes::SemiPlanar { y, uv })
+        } else {
+            None
+        }
+    }
 }
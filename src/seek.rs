@@ -0,0 +1,184 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{
+    BufferInfo, CodecOutputBuffer, Dequeued, Error, MediaCodec, MediaExtractor, MediaStatus,
+    SeekMode, Timeout,
+};
+
+/// How long to wait for output when there was no input to feed
+const OUTPUT_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// How many times in a row the decoders may produce nothing, before giving up on a seek
+const MAX_IDLE_POLLS: usize = 500;
+
+/// Seeks a [MediaExtractor](MediaExtractor) along with the decoders fed from it, and lands on an exact frame.
+///
+/// The extractor can only seek to sync samples, so [seek](SeekDecoder::seek) moves to the sync sample before the
+/// requested time and decodes forward, dropping every frame before it
+#[derive(Debug)]
+pub struct SeekDecoder<'a> {
+    extractor: MediaExtractor,
+    decoders: BTreeMap<usize, MediaCodec<'a>>,
+}
+
+impl<'a> SeekDecoder<'a> {
+    /// Seeks within `extractor`, which has no decoders yet
+    pub fn new(extractor: MediaExtractor) -> Self {
+        Self {
+            extractor,
+            decoders: BTreeMap::new(),
+        }
+    }
+
+    /// Decodes the track at `track_index` with `decoder`, which must be started.
+    ///
+    /// This selects the track. Samples of selected tracks without a decoder are skipped
+    pub fn add_decoder(
+        &mut self,
+        track_index: usize,
+        decoder: MediaCodec<'a>,
+    ) -> Result<(), Error> {
+        self.extractor.select_track(track_index)?;
+        self.decoders.insert(track_index, decoder);
+        Ok(())
+    }
+
+    /// The extractor the packets are read from
+    pub fn extractor(&self) -> &MediaExtractor {
+        &self.extractor
+    }
+
+    /// The extractor, to read packets from it directly between seeks
+    pub fn extractor_mut(&mut self) -> &mut MediaExtractor {
+        &mut self.extractor
+    }
+
+    /// The decoder of the track at `track_index`
+    pub fn decoder_mut(&mut self, track_index: usize) -> Option<&mut MediaCodec<'a>> {
+        self.decoders.get_mut(&track_index)
+    }
+
+    /// Returns the extractor, and the decoders by track index
+    pub fn into_parts(self) -> (MediaExtractor, BTreeMap<usize, MediaCodec<'a>>) {
+        (self.extractor, self.decoders)
+    }
+
+    /// Seeks every track to `time_us`, and returns the first frame of the track at `track_index` presented at or after it.
+    ///
+    /// Every decoder gets flushed first. Frames before `time_us` are released without being rendered, as is the
+    /// output of the other decoders while seeking. Fails with `ErrorEndOfStream` when no frame is left after `time_us`
+    pub fn seek(
+        &mut self,
+        track_index: usize,
+        time_us: i64,
+    ) -> Result<CodecOutputBuffer<'_>, Error> {
+        let error = |status| Error::new(status, "seek decoder").with_track_index(track_index);
+
+        if !self.decoders.contains_key(&track_index) {
+            return Err(error(MediaStatus::ErrorInvalidParameter));
+        }
+
+        self.extractor.seek_to(time_us, SeekMode::PreviousSync)?;
+        for decoder in self.decoders.values_mut() {
+            decoder.flush()?;
+        }
+
+        let (index, info) = self.decode_until(track_index, time_us)?;
+
+        let decoder = self.decoders.get_mut(&track_index).unwrap();
+        decoder.output_buffer_at(index, info)
+    }
+
+    /// Feeds the decoders until the one at `track_index` outputs a frame at or after `time_us`.
+    ///
+    /// The frame is handed back by index, as the codec can't be borrowed across loop iterations
+    fn decode_until(
+        &mut self,
+        track_index: usize,
+        time_us: i64,
+    ) -> Result<(usize, BufferInfo), Error> {
+        let error = |status| Error::new(status, "seek decoder").with_track_index(track_index);
+        let mut input_done = false;
+        let mut idle = 0;
+
+        loop {
+            let fed = !input_done && self.feed(track_index, &mut input_done)?;
+
+            // Keep the other decoders going, so they don't stall the extractor
+            for (&index, decoder) in self.decoders.iter_mut() {
+                if index == track_index {
+                    continue;
+                }
+
+                while let Ok(Dequeued::Buffer((buffer, _))) =
+                    decoder.dequeue_output_index(Timeout::NonBlocking)
+                {
+                    decoder.release_output_index(buffer);
+                }
+            }
+
+            let decoder = self.decoders.get_mut(&track_index).unwrap();
+            let timeout = if fed {
+                Timeout::NonBlocking
+            } else {
+                Timeout::Duration(OUTPUT_TIMEOUT)
+            };
+
+            let (index, info) = match decoder.dequeue_output_index(timeout)? {
+                Dequeued::Buffer(buffer) => buffer,
+                _ if fed => continue,
+                _ => {
+                    idle += 1;
+                    if idle >= MAX_IDLE_POLLS {
+                        return Err(error(MediaStatus::ErrorWouldBlock));
+                    }
+
+                    continue;
+                }
+            };
+            idle = 0;
+
+            let is_frame = !info.is_codec_config() && info.size() > 0;
+            if is_frame && info.presentation_time_us() >= time_us {
+                return Ok((index, info));
+            }
+
+            decoder.release_output_index(index);
+            if info.is_end_of_stream() {
+                return Err(error(MediaStatus::ErrorEndOfStream));
+            }
+        }
+    }
+
+    /// Feeds the current packet to its decoder, or signals the end of the stream to the decoder at `track_index` once
+    /// the extractor ran out of packets. Returns whether anything was queued
+    fn feed(&mut self, track_index: usize, input_done: &mut bool) -> Result<bool, Error> {
+        let index = self.extractor.track_index();
+
+        if index < 0 {
+            let decoder = self.decoders.get_mut(&track_index).unwrap();
+            let Ok(mut buffer) = decoder.dequeue_input() else {
+                return Ok(false);
+            };
+
+            // Flags the end of stream
+            self.extractor.read_next(&mut buffer)?;
+            *input_done = true;
+            return Ok(true);
+        }
+
+        let Some(decoder) = self.decoders.get_mut(&(index as usize)) else {
+            self.extractor.advance();
+            return Ok(true);
+        };
+
+        match decoder.dequeue_input() {
+            Ok(mut buffer) => {
+                self.extractor.read_next(&mut buffer)?;
+                Ok(true)
+            }
+            Err(error) if error.status() == MediaStatus::ErrorWouldBlock => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
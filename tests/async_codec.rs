@@ -0,0 +1,161 @@
+#![cfg(all(mock_ndk, feature = "async"))]
+
+mod common;
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+};
+
+use mediacodec::{mock::MockCodec, BufferFlag, MediaCodec, MediaStatus};
+
+use common::pcm_format;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+fn started_codec(mock: &MockCodec) -> MediaCodec<'static> {
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.enable_async().unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+    codec
+}
+
+#[test]
+fn decodes_with_await() {
+    let mut codec = started_codec(&MockCodec::new());
+
+    let packets: [&[u8]; 2] = [&[1, 2], &[3, 4, 5, 6]];
+    let outputs = block_on(async {
+        for (time, packet) in packets.iter().enumerate() {
+            let mut buffer = codec.next_input().await.unwrap();
+            let (ptr, size) = buffer.buffer();
+            assert!(packet.len() <= size);
+
+            unsafe { std::ptr::copy_nonoverlapping(packet.as_ptr(), ptr, packet.len()) };
+            buffer.set_write_size(packet.len());
+            buffer.set_time(time as u64 * 1000);
+        }
+
+        let mut buffer = codec.next_input().await.unwrap();
+        buffer.set_flags(BufferFlag::EndOfStream as u32);
+        drop(buffer);
+
+        let mut outputs = vec![];
+        loop {
+            let buffer = codec.next_output().await.unwrap();
+            let info = *buffer.info();
+            if BufferFlag::EndOfStream.is_contained_in(info.flags() as i32) {
+                break;
+            }
+
+            outputs.push((buffer.data().unwrap().to_vec(), info.presentation_time_us()));
+        }
+        outputs
+    });
+
+    assert_eq!(outputs, [(vec![1, 2], 0), (vec![3, 4, 5, 6], 1000)]);
+}
+
+#[test]
+fn flush_drops_pending_buffers() {
+    let mock = MockCodec::new();
+    let mut codec = started_codec(&mock);
+
+    block_on(async {
+        let mut buffer = codec.next_input().await.unwrap();
+        buffer.set_write_size(1);
+    });
+
+    codec.flush().unwrap();
+    mock.push_output(&[7], 5000, 0);
+
+    let buffer = block_on(codec.next_output()).unwrap();
+    assert_eq!(buffer.data(), Some(&[7][..]));
+}
+
+#[test]
+fn requires_enabling_async() {
+    MockCodec::new().install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    assert_eq!(
+        block_on(codec.next_input()).unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn reports_codec_errors() {
+    let mock = MockCodec::new().input_buffers(0);
+    let mut codec = started_codec(&mock);
+
+    mock.push_error(MediaStatus::ErrorIO, 0, "Lost the hardware");
+    assert_eq!(
+        block_on(codec.next_output()).unwrap_err().status(),
+        MediaStatus::ErrorIO
+    );
+}
+
+#[test]
+fn restarts_after_stop() {
+    let mut codec = started_codec(&MockCodec::new());
+    codec.stop().unwrap();
+    assert_eq!(
+        block_on(codec.next_input()).unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+    let buffer = block_on(codec.next_input()).unwrap();
+    assert!(buffer.buffer().1 > 0);
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn keeps_the_async_callback() {
+    use mediacodec::{CodecCallback, CodecInputBuffer, CodecOutputBuffer, Error};
+
+    struct Ignore;
+
+    impl CodecCallback for Ignore {
+        fn on_input_available(&mut self, _buffer: CodecInputBuffer<'_>) {}
+        fn on_output_available(&mut self, _buffer: CodecOutputBuffer<'_>) {}
+        fn on_error(&mut self, _error: Error) {}
+    }
+
+    MockCodec::new().install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.enable_async().unwrap();
+    assert_eq!(
+        codec.set_callback(Ignore).unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+}
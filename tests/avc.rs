@@ -0,0 +1,192 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    remove_emulation_prevention, split_nal_units, AvcParameterSets, AvcPps, AvcSps, BitReader,
+    ChromaFormat, ColorRange, ColorStandard, ColorTransfer, FrameCrop, MediaFormat, MediaStatus,
+    Rect,
+};
+
+use common::hex;
+
+/// x264, High@4.0, 1920x1080 at 30 fps
+const X264_SPS: &str = "67640028acd940780227e5c044000003000400000300f03c60c658";
+const X264_PPS: &str = "68ebe3cb22c0";
+/// High 4:2:2@4.1, 10 bits, 1280x720 at 29.97 fps, full range BT.2020 PQ, 4:3 pixels
+const HDR_SPS: &str = "677a0029b6cb402802ddff80020001b7091009400000fa40003a9821";
+/// Constrained Baseline@3.0, interlaced 720x576 cropped to 712x572, SPS id 1
+const INTERLACED_SPS: &str = "6742c01e5b2016848e5a40";
+const BASELINE_PPS: &str = "68ce3c80";
+
+#[test]
+fn reads_exp_golomb_values() {
+    // 1, 010, 011, 00100, 00101
+    let data = [0b1010_0110, 0b0100_0010, 0b1000_0000];
+    let mut reader = BitReader::new(&data, "test");
+
+    assert_eq!(reader.read_ue().unwrap(), 0);
+    assert_eq!(reader.read_ue().unwrap(), 1);
+    assert_eq!(reader.read_se().unwrap(), -1);
+    assert_eq!(reader.read_ue().unwrap(), 3);
+    assert_eq!(reader.read_se().unwrap(), -2);
+    assert_eq!(reader.position(), 17);
+    assert!(!reader.more_rbsp_data());
+
+    let error = reader.read_bits(9).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorMalformed);
+    assert_eq!(error.detail(), Some("unexpected end of data"));
+}
+
+#[test]
+fn removes_emulation_prevention_bytes() {
+    assert_eq!(
+        remove_emulation_prevention(&[0x44, 0, 0, 3, 0, 4, 0, 0, 3, 3, 0, 3]),
+        [0x44, 0, 0, 0, 4, 0, 0, 3, 0, 3]
+    );
+}
+
+#[test]
+fn splits_annex_b_streams() {
+    let stream = [
+        0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 0, 1, 0x65,
+    ];
+    assert_eq!(
+        split_nal_units(&stream),
+        [&[0x67, 0x42][..], &[0x68, 0xCE][..], &[0x65][..]]
+    );
+    assert_eq!(split_nal_units(&[0x67, 0x42]), [&[0x67, 0x42][..]]);
+}
+
+#[test]
+fn parses_x264_sps() {
+    let sps = AvcSps::parse(&hex(X264_SPS)).unwrap();
+
+    assert_eq!(sps.profile_idc, 100);
+    assert_eq!(sps.level_idc, 40);
+    assert_eq!(sps.codec_profile(), Some(0x08));
+    assert_eq!(sps.codec_level(), Some(0x800));
+    assert_eq!(sps.chroma_format, ChromaFormat::Yuv420);
+    assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (8, 8));
+    assert_eq!(sps.max_num_ref_frames, 4);
+    assert_eq!((sps.coded_width, sps.coded_height), (1920, 1088));
+    assert_eq!(
+        sps.crop,
+        FrameCrop {
+            bottom: 8,
+            ..Default::default()
+        }
+    );
+    assert_eq!((sps.width(), sps.height()), (1920, 1080));
+    assert_eq!(
+        sps.display_rect(),
+        Rect {
+            left: 0,
+            top: 0,
+            right: 1919,
+            bottom: 1079,
+        }
+    );
+
+    let vui = sps.vui.unwrap();
+    assert_eq!(vui.sample_aspect_ratio, Some((1, 1)));
+    assert_eq!(vui.color, None);
+    assert_eq!(vui.color_range(), ColorRange::Limited);
+    assert_eq!(sps.frame_rate(), Some(30.0));
+}
+
+#[test]
+fn parses_high_bit_depth_sps() {
+    let sps = AvcSps::parse(&hex(HDR_SPS)).unwrap();
+
+    assert_eq!(sps.codec_profile(), Some(0x20));
+    assert_eq!(sps.codec_level(), Some(0x1000));
+    assert_eq!(sps.chroma_format, ChromaFormat::Yuv422);
+    assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (10, 10));
+    assert_eq!((sps.width(), sps.height()), (1280, 720));
+
+    let vui = sps.vui.unwrap();
+    assert_eq!(vui.sample_aspect_ratio, Some((4, 3)));
+    assert_eq!(vui.video_format, Some(5));
+    assert_eq!(vui.color_range(), ColorRange::Full);
+
+    let color = vui.color.unwrap();
+    assert_eq!(color.color_standard(), Some(ColorStandard::Bt2020));
+    assert_eq!(color.color_transfer(), Some(ColorTransfer::St2084));
+    assert!(color.is_hdr());
+    assert!((sps.frame_rate().unwrap() - 29.97).abs() < 0.001);
+}
+
+#[test]
+fn crops_interlaced_sps() {
+    let sps = AvcSps::parse(&hex(INTERLACED_SPS)).unwrap();
+
+    assert_eq!(sps.id, 1);
+    assert_eq!(sps.codec_profile(), Some(0x10000));
+    assert_eq!(sps.codec_level(), Some(0x100));
+    assert!(!sps.frame_mbs_only);
+    assert_eq!((sps.coded_width, sps.coded_height), (720, 576));
+    assert_eq!((sps.width(), sps.height()), (712, 572));
+    assert_eq!(sps.vui, None);
+    assert_eq!(sps.frame_rate(), None);
+}
+
+#[test]
+fn parses_pps() {
+    let pps = AvcPps::parse(&hex(X264_PPS)).unwrap();
+    assert!(pps.entropy_coding_mode);
+    assert_eq!(pps.num_ref_idx_l0_default_active, 3);
+    assert!(pps.weighted_pred);
+    assert_eq!(pps.weighted_bipred_idc, 2);
+    assert_eq!(pps.pic_init_qp, 23);
+    assert_eq!(pps.chroma_qp_index_offset, -2);
+    assert!(pps.deblocking_filter_control_present);
+    assert!(pps.transform_8x8_mode);
+    assert_eq!(pps.second_chroma_qp_index_offset, Some(-2));
+
+    let pps = AvcPps::parse(&hex(BASELINE_PPS)).unwrap();
+    assert!(!pps.entropy_coding_mode);
+    assert!(!pps.transform_8x8_mode);
+    assert_eq!(pps.second_chroma_qp_index_offset, Some(0));
+}
+
+#[test]
+fn parses_track_formats() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/avc");
+    format.set_buffer("csd-0", &[[0, 0, 0, 1].as_slice(), &hex(X264_SPS)].concat());
+    format.set_buffer("csd-1", &[[0, 0, 0, 1].as_slice(), &hex(X264_PPS)].concat());
+
+    let sets = AvcParameterSets::from_format(&format).unwrap();
+    assert_eq!(sets.sps.len(), 1);
+    assert_eq!(sets.pps.len(), 1);
+    assert_eq!(sets.first_sps().unwrap().height(), 1080);
+    assert!(sets.pps[0].entropy_coding_mode);
+}
+
+#[test]
+fn parses_avcc_records() {
+    let record = hex(
+        "01640028ffe1001b67640028acd940780227e5c044000003000400000300f03c60c65801000668ebe3cb22c0",
+    );
+
+    let sets = AvcParameterSets::parse(&record).unwrap();
+    assert_eq!(sets.sps, [AvcSps::parse(&hex(X264_SPS)).unwrap()]);
+    assert_eq!(sets.pps, [AvcPps::parse(&hex(X264_PPS)).unwrap()]);
+
+    let error = AvcParameterSets::parse(&record[..20]).unwrap_err();
+    assert_eq!(error.detail(), Some("truncated NAL unit"));
+}
+
+#[test]
+fn rejects_malformed_parameter_sets() {
+    let error = AvcSps::parse(&hex(&X264_SPS[..16])).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorMalformed);
+    assert_eq!(error.detail(), Some("unexpected end of data"));
+
+    let error = AvcSps::parse(&hex(X264_PPS)).unwrap_err();
+    assert_eq!(error.detail(), Some("expected a NAL unit of type 7"));
+
+    let error = AvcParameterSets::from_format(&MediaFormat::new().unwrap()).unwrap_err();
+    assert_eq!(error.detail(), Some("missing csd-0"));
+}
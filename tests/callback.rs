@@ -0,0 +1,144 @@
+#![cfg(all(mock_ndk, feature = "api28"))]
+
+mod common;
+
+use std::{collections::VecDeque, sync::mpsc, time::Duration};
+
+use mediacodec::{
+    mock::MockCodec, BufferFlag, CodecCallback, CodecInputBuffer, CodecOutputBuffer, Error,
+    MediaCodec, MediaFormat, MediaStatus,
+};
+
+use common::pcm_format;
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Output(Vec<u8>, i64, u32),
+    FormatChanged(Option<i32>),
+    Error(MediaStatus, bool, String),
+}
+
+/// Feeds `packets` to the codec, then signals the end of stream
+struct Feeder {
+    packets: VecDeque<Vec<u8>>,
+    time: u64,
+    events: mpsc::Sender<Event>,
+}
+
+impl Feeder {
+    fn new(packets: &[&[u8]]) -> (Self, mpsc::Receiver<Event>) {
+        let (events, receiver) = mpsc::channel();
+        let feeder = Self {
+            packets: packets.iter().map(|packet| packet.to_vec()).collect(),
+            time: 0,
+            events,
+        };
+
+        (feeder, receiver)
+    }
+}
+
+impl CodecCallback for Feeder {
+    fn on_input_available(&mut self, mut buffer: CodecInputBuffer<'_>) {
+        let Some(packet) = self.packets.pop_front() else {
+            buffer.set_flags(BufferFlag::EndOfStream as u32);
+            return;
+        };
+
+        let (ptr, size) = buffer.buffer();
+        assert!(packet.len() <= size);
+        unsafe { std::ptr::copy_nonoverlapping(packet.as_ptr(), ptr, packet.len()) };
+
+        buffer.set_write_size(packet.len());
+        buffer.set_time(self.time);
+        self.time += 1000;
+    }
+
+    fn on_output_available(&mut self, buffer: CodecOutputBuffer<'_>) {
+        let info = buffer.info();
+        let data = buffer.data().unwrap_or_default().to_vec();
+        let _ = self.events.send(Event::Output(
+            data,
+            info.presentation_time_us(),
+            info.flags(),
+        ));
+    }
+
+    fn on_format_changed(&mut self, format: &MediaFormat) {
+        let _ = self
+            .events
+            .send(Event::FormatChanged(format.get_i32("sample-rate")));
+    }
+
+    fn on_error(&mut self, error: Error) {
+        let _ = self.events.send(Event::Error(
+            error.status(),
+            error.is_recoverable(),
+            error.detail().unwrap_or_default().to_string(),
+        ));
+    }
+}
+
+fn next(events: &mpsc::Receiver<Event>) -> Event {
+    events.recv_timeout(Duration::from_secs(5)).unwrap()
+}
+
+#[test]
+fn decodes_with_callbacks() {
+    MockCodec::new().install();
+
+    let (feeder, events) = Feeder::new(&[&[1, 2], &[3, 4, 5]]);
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.set_callback(feeder).unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    // Input buffers come in order, so the outputs do too
+    assert_eq!(next(&events), Event::Output(vec![1, 2], 0, 0));
+    assert_eq!(next(&events), Event::Output(vec![3, 4, 5], 1000, 0));
+    assert_eq!(
+        next(&events),
+        Event::Output(vec![], 0, BufferFlag::EndOfStream as u32)
+    );
+
+    // Polling is not allowed in asynchronous mode
+    assert!(codec.dequeue_input().is_err());
+}
+
+#[test]
+fn reports_format_changes_and_errors() {
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+
+    let (feeder, events) = Feeder::new(&[]);
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.set_callback(feeder).unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let mut change = MediaFormat::new().unwrap();
+    change.set_i32("sample-rate", 44100);
+    mock.push_format_change(&change);
+    assert_eq!(next(&events), Event::FormatChanged(Some(44100)));
+
+    mock.push_error(MediaStatus::ErrorIO, 2, "Lost the hardware");
+    assert_eq!(
+        next(&events),
+        Event::Error(MediaStatus::ErrorIO, true, "Lost the hardware".to_string())
+    );
+}
+
+#[test]
+fn rejects_callback_while_executing() {
+    MockCodec::new().install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let (feeder, _events) = Feeder::new(&[]);
+    assert_eq!(
+        codec.set_callback(feeder).unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+}
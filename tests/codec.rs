@@ -0,0 +1,544 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use std::time::Duration;
+
+use mediacodec::{
+    keys,
+    mock::{window, MockCodec, MockCodecState},
+    BufferFlag, CodecState, ColorFormat, CryptoInfo, CryptoInfoMode, CryptoInfoPattern, Dequeued,
+    Frame, MediaCodec, MediaFormat, MediaStatus, RawVideoFrame, Rect, SampleFormat, SubSample,
+    Timeout, VideoFrame, VideoPlanes,
+};
+
+use common::{pcm_format, write};
+
+#[test]
+fn decodes_pcm_through_passthrough_codec() {
+    let mock = MockCodec::new();
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+    assert_eq!(mock.state(), MockCodecState::Executing);
+
+    let samples: Vec<u8> = [1i16, -1, 2, -2]
+        .iter()
+        .flat_map(|s| s.to_ne_bytes())
+        .collect();
+    {
+        let mut buffer = codec.dequeue_input().unwrap();
+        write(&mut buffer, &samples, 1000);
+    }
+
+    let queued = mock.queued_inputs();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].data, samples);
+    assert_eq!(queued[0].time_us, 1000);
+
+    let buffer = codec.dequeue_output().unwrap();
+    match buffer.frame() {
+        Some(Frame::Audio(frame)) => {
+            assert_eq!(frame.channels(), 2);
+            assert_eq!(frame.nb_samples(), 2);
+            match frame.format() {
+                SampleFormat::S16(data) => assert_eq!(*data, [1, -1, 2, -2]),
+                format => panic!("Unexpected sample format {format:?}"),
+            }
+        }
+        frame => panic!("Unexpected frame {frame:?}"),
+    }
+    drop(buffer);
+
+    assert_eq!(mock.released_outputs().len(), 1);
+}
+
+#[test]
+fn runs_out_of_input_buffers() {
+    MockCodec::new().input_buffers(1).install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let buffer = codec.dequeue_input().unwrap();
+    std::mem::forget(buffer);
+
+    assert!(codec.dequeue_input().is_err());
+}
+
+#[test]
+fn reports_injected_errors() {
+    let mock = MockCodec::new();
+    mock.install();
+    mock.fail_next("AMediaCodec_start", MediaStatus::ErrorInsufficientResource);
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+
+    let error = codec.start().unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorInsufficientResource);
+    assert_eq!(error.operation(), "start");
+    assert_eq!(mock.state(), MockCodecState::Configured);
+    assert_eq!(codec.start(), Ok(()));
+}
+
+#[test]
+fn fails_to_create_unsupported_codecs() {
+    MockCodec::new().fail_creation().install();
+
+    let error = MediaCodec::create_decoder("video/unknown").unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorUnsupported);
+    assert_eq!(error.mime_type(), Some("video/unknown"));
+    assert_eq!(
+        error.to_string(),
+        "create decoder failed: unsupported (mime type video/unknown)"
+    );
+    // Error chains would print the status twice otherwise
+    assert!(std::error::Error::source(&error).is_none());
+    assert!(!error.is_recoverable());
+}
+
+#[test]
+fn applies_output_format_changes() {
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let mut change = MediaFormat::new().unwrap();
+    change.set_i32("sample-rate", 44100);
+    mock.push_format_change(&change);
+
+    assert!(codec.dequeue_output().is_err());
+    let format = codec.output_format().unwrap();
+    assert_eq!(format.get_i32("sample-rate"), Some(44100));
+    assert_eq!(format.get_i32("channel-count"), Some(2));
+}
+
+#[test]
+fn renders_to_surface() {
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/avc");
+
+    let mut codec = MediaCodec::create_decoder("video/avc").unwrap();
+    codec.init(&format, Some(window(320, 240)), 0).unwrap();
+    codec.start().unwrap();
+
+    mock.push_output(&[], 40_000, BufferFlag::EndOfStream as u32);
+
+    let mut buffer = codec.dequeue_output().unwrap();
+    assert!(matches!(
+        buffer.frame(),
+        Some(Frame::Video(VideoFrame::Hardware))
+    ));
+    buffer.set_render(true);
+    drop(buffer);
+
+    let released = mock.released_outputs();
+    assert_eq!(released.len(), 1);
+    assert!(released[0].rendered);
+    assert_eq!(codec.set_output_surface(window(640, 480)), Ok(()));
+}
+
+#[test]
+fn describes_raw_video_planes() {
+    // A 6x4 NV12 frame, padded to a stride of 8 and a slice height of 4, with the last row cropped out
+    let mut output = MediaFormat::new().unwrap();
+    output.set_i32("color-format", ColorFormat::YUV420SemiPlanar.value());
+    output.set_i32("stride", 8);
+    output.set_i32("slice-height", 4);
+    output.set_i32("crop-right", 5);
+    output.set_i32("crop-bottom", 2);
+
+    let mock = MockCodec::new().passthrough(false).output_format(&output);
+    mock.install();
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/avc");
+    format.set_i32("width", 6);
+    format.set_i32("height", 4);
+
+    let mut codec = MediaCodec::create_decoder("video/avc").unwrap();
+    codec.init(&format, None, 0).unwrap();
+    codec.start().unwrap();
+
+    let data: Vec<u8> = (0..48).collect();
+    mock.push_output(&data, 0, 0);
+
+    let buffer = codec.dequeue_output().unwrap();
+    let Some(Frame::Video(VideoFrame::RawFrame(frame))) = buffer.frame() else {
+        panic!("Expected a raw video frame");
+    };
+
+    assert_eq!(frame.color_format(), ColorFormat::YUV420SemiPlanar);
+    assert_eq!((frame.width(), frame.height()), (6, 4));
+    assert_eq!((frame.stride(), frame.slice_height()), (8, 4));
+    assert_eq!(
+        frame.crop(),
+        Rect {
+            left: 0,
+            top: 0,
+            right: 5,
+            bottom: 2
+        }
+    );
+
+    match frame.planes() {
+        Some(VideoPlanes::SemiPlanar { y, uv }) => {
+            assert_eq!(y.stride, 8);
+            assert_eq!(y.row(1), Some(&data[8..16]));
+            assert_eq!(uv.data, &data[32..48]);
+            assert_eq!(uv.row(1), Some(&data[40..48]));
+        }
+        planes => panic!("Unexpected planes {planes:?}"),
+    }
+}
+
+#[test]
+fn rejects_truncated_raw_video_frames() {
+    // A 6x4 NV12 frame, padded to a stride of 8
+    let mut format = MediaFormat::new().unwrap();
+    format.set_i32("color-format", ColorFormat::YUV420SemiPlanar.value());
+    format.set_i32("width", 6);
+    format.set_i32("height", 4);
+    format.set_i32("stride", 8);
+
+    let data: Vec<u8> = (0..48).collect();
+    let planes = |len: usize| RawVideoFrame::new(&data[..len], &format).unwrap().planes();
+
+    // Only the padding of the last row may be left out
+    match planes(46) {
+        Some(VideoPlanes::SemiPlanar { uv, .. }) => {
+            assert_eq!(uv.data, &data[32..46]);
+            assert_eq!(uv.row(1), Some(&data[40..46]));
+        }
+        planes => panic!("Unexpected planes {planes:?}"),
+    }
+    assert!(planes(45).is_none());
+    assert!(planes(40).is_none());
+    assert!(planes(20).is_none());
+}
+
+#[test]
+fn clamps_the_crop_rectangle() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set(keys::COLOR_FORMAT, ColorFormat::YUV420SemiPlanar.value());
+    format.set(keys::WIDTH, 6);
+    format.set(keys::HEIGHT, 4);
+    format.set(keys::CROP_LEFT, -2);
+    format.set(keys::CROP_TOP, 1);
+    format.set(keys::CROP_RIGHT, 100);
+    format.set(keys::CROP_BOTTOM, 0);
+
+    let data = [0; 36];
+    let frame = RawVideoFrame::new(&data, &format).unwrap();
+    assert_eq!(
+        frame.crop(),
+        Rect {
+            left: 0,
+            top: 1,
+            right: 5,
+            bottom: 1
+        }
+    );
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn prefers_the_display_crop() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set(keys::COLOR_FORMAT, ColorFormat::YUV420SemiPlanar.value());
+    format.set(keys::WIDTH, 6);
+    format.set(keys::HEIGHT, 4);
+    format.set(keys::CROP_RIGHT, 1);
+
+    let crop = Rect {
+        left: 1,
+        top: 0,
+        right: 4,
+        bottom: 2,
+    };
+    format.set(keys::DISPLAY_CROP, crop);
+
+    let data = [0; 36];
+    let frame = RawVideoFrame::new(&data, &format).unwrap();
+    assert_eq!(frame.crop(), crop);
+}
+
+#[test]
+fn tells_dequeue_results_apart() {
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let mut change = MediaFormat::new().unwrap();
+    change.set_i32("sample-rate", 44100);
+    mock.push_format_change(&change);
+    mock.push_buffers_changed();
+    mock.push_output(&[1, 0], 0, 0);
+
+    assert!(matches!(
+        codec.dequeue_output_timeout(Timeout::NonBlocking),
+        Ok(Dequeued::OutputFormatChanged)
+    ));
+    assert!(matches!(
+        codec.dequeue_output_timeout(Duration::from_millis(10)),
+        Ok(Dequeued::OutputBuffersChanged)
+    ));
+
+    let buffer = codec
+        .dequeue_output_timeout(Timeout::Blocking)
+        .unwrap()
+        .buffer()
+        .unwrap();
+    assert_eq!(buffer.format().get_i32("sample-rate"), Some(44100));
+    drop(buffer);
+
+    assert!(matches!(
+        codec.dequeue_output_timeout(Timeout::NonBlocking),
+        Ok(Dequeued::TryAgainLater)
+    ));
+    assert_eq!(
+        codec.dequeue_output().unwrap_err().status(),
+        MediaStatus::ErrorWouldBlock
+    );
+
+    mock.fail_next("AMediaCodec_dequeueOutputBuffer", MediaStatus::ErrorIO);
+    assert_eq!(
+        codec
+            .dequeue_output_timeout(Timeout::NonBlocking)
+            .unwrap_err()
+            .status(),
+        MediaStatus::ErrorIO
+    );
+}
+
+#[test]
+fn tracks_codec_state() {
+    let mock = MockCodec::new();
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    assert_eq!(codec.state(), CodecState::Uninitialized);
+    let error = codec.start().unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorInvalidOperation);
+    assert_eq!(error.codec_state(), Some(CodecState::Uninitialized));
+    assert_eq!(
+        error.to_string(),
+        "start failed: invalid operation (codec is Uninitialized)"
+    );
+    assert_eq!(
+        codec.dequeue_input().unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.init(&pcm_format(), None, 0).unwrap();
+    assert_eq!(codec.state(), CodecState::Configured);
+    assert_eq!(
+        codec
+            .set_output_surface(window(640, 480))
+            .unwrap_err()
+            .status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.start().unwrap();
+    let mut buffer = codec.dequeue_input().unwrap();
+    buffer.set_flags(BufferFlag::EndOfStream as u32);
+    drop(buffer);
+
+    assert_eq!(codec.state(), CodecState::EndOfStream);
+    assert_eq!(
+        codec.dequeue_input().unwrap_err().status(),
+        MediaStatus::ErrorEndOfStream
+    );
+    assert!(codec.dequeue_output().is_ok());
+
+    codec.flush().unwrap();
+    assert_eq!(codec.state(), CodecState::Executing);
+
+    codec.stop().unwrap();
+    assert_eq!(codec.state(), CodecState::Uninitialized);
+    assert_eq!(
+        codec.dequeue_input().unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.release().unwrap();
+    assert_eq!(codec.state(), CodecState::Released);
+    assert_eq!(mock.state(), MockCodecState::Released);
+    assert!(codec.output_format().is_none());
+    assert_eq!(
+        codec.release().unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+}
+
+#[test]
+fn enters_error_state() {
+    let mock = MockCodec::new();
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    mock.fail_next("AMediaCodec_dequeueInputBuffer", MediaStatus::ErrorIO);
+    assert_eq!(
+        codec.dequeue_input().unwrap_err().status(),
+        MediaStatus::ErrorIO
+    );
+    assert_eq!(codec.state(), CodecState::Error);
+    assert_eq!(
+        codec.flush().unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    // Stopping recovers the codec
+    codec.stop().unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+    assert!(codec.dequeue_input().is_ok());
+}
+
+#[test]
+fn queues_secure_buffers() {
+    let mock = MockCodec::new();
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let info = CryptoInfo::new(
+        CryptoInfoMode::AesCtr,
+        [1; 16],
+        [2; 16],
+        vec![SubSample::new(2, 4), SubSample::new(1, 0)],
+    );
+    let mut buffer = codec.dequeue_input().unwrap();
+    write(&mut buffer, &[0, 1, 2, 3, 4, 5, 6], 1000);
+    buffer.queue_secure(&info).unwrap();
+
+    assert_eq!(mock.secure_inputs(), [info]);
+    assert_eq!(mock.queued_inputs()[0].data, [0, 1, 2, 3, 4, 5, 6]);
+
+    // Subsamples larger than the buffer never reach the codec
+    let buffer = codec.dequeue_input().unwrap();
+    let size = buffer.size();
+    let oversized = CryptoInfo::new(
+        CryptoInfoMode::AesCtr,
+        [1; 16],
+        [2; 16],
+        vec![SubSample::new(0, size + 1)],
+    );
+    assert_eq!(
+        buffer.queue_secure(&oversized).unwrap_err().status(),
+        MediaStatus::ErrorInvalidParameter
+    );
+    assert_eq!(mock.secure_inputs().len(), 1);
+}
+
+#[test]
+fn applies_encryption_patterns() {
+    let mock = MockCodec::new();
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let info = CryptoInfo::new(
+        CryptoInfoMode::AesCbc,
+        [1; 16],
+        [2; 16],
+        vec![SubSample::new(0, 16)],
+    )
+    .with_pattern(CryptoInfoPattern {
+        encrypt_blocks: 1,
+        skip_blocks: 9,
+    });
+
+    let buffer = codec.dequeue_input().unwrap();
+    if cfg!(feature = "api24") {
+        buffer.queue_secure(&info).unwrap();
+        assert_eq!(mock.secure_inputs(), [info]);
+    } else {
+        assert_eq!(
+            buffer.queue_secure(&info).unwrap_err().status(),
+            MediaStatus::ErrorUnsupported
+        );
+    }
+}
+
+#[test]
+fn drains_the_last_frames() {
+    MockCodec::new().install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    for time in [0, 10] {
+        let mut buffer = codec.dequeue_input().unwrap();
+        write(&mut buffer, &[1, 2], time);
+    }
+
+    // Nothing to drain until the end of stream is queued
+    assert_eq!(
+        codec
+            .drain(Timeout::NonBlocking, |_| {})
+            .unwrap_err()
+            .status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.signal_end_of_stream(Timeout::NonBlocking).unwrap();
+
+    let mut drained = vec![];
+    codec
+        .drain(Timeout::NonBlocking, |buffer| {
+            let info = buffer.info();
+            drained.push((info.presentation_time_us(), info.is_end_of_stream()));
+        })
+        .unwrap();
+    assert_eq!(drained, [(0, false), (10, false), (0, true)]);
+
+    // Nothing is left, which times out instead of waiting forever
+    assert_eq!(
+        codec
+            .drain(Duration::from_millis(1), |_| {})
+            .unwrap_err()
+            .status(),
+        MediaStatus::ErrorWouldBlock
+    );
+}
+
+#[test]
+fn reports_failed_end_of_stream() {
+    let mock = MockCodec::new();
+    mock.install();
+
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    mock.fail_next("AMediaCodec_queueInputBuffer", MediaStatus::ErrorIO);
+    let error = codec
+        .signal_end_of_stream(Timeout::NonBlocking)
+        .unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorIO);
+    assert_eq!(error.operation(), "queue input buffer");
+    assert_eq!(codec.state(), CodecState::Error);
+}
@@ -0,0 +1,61 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use mediacodec::{keys, mock::MockCodec, BufferFlag, InputBuffer, MediaCodec, MediaFormat};
+
+/// A track with nothing but its mime type
+pub fn track(mime: &str) -> MediaFormat {
+    let mut format = MediaFormat::new().unwrap();
+    format.set(keys::MIME, mime);
+    format
+}
+
+/// Stereo 16 bit PCM at 48 kHz
+pub fn pcm_format() -> MediaFormat {
+    let mut format = track("audio/raw");
+    format.set(keys::CHANNEL_COUNT, 2);
+    format.set(keys::SAMPLE_RATE, 48000);
+    format.set(keys::PCM_ENCODING, 2);
+    format
+}
+
+/// A tiny 4x2 H.264 stream from YUV420 planar input
+pub fn avc_format() -> MediaFormat {
+    let mut format = track("video/avc");
+    format.set(keys::WIDTH, 4);
+    format.set(keys::HEIGHT, 2);
+    format.set(keys::COLOR_FORMAT, 19);
+    format.set(keys::BIT_RATE, 100_000);
+    format.set(keys::FRAME_RATE, 30);
+    format.set(keys::I_FRAME_INTERVAL, 1);
+    format
+}
+
+/// Installs `mock` and configures an encoder with [avc_format]
+pub fn configured_encoder(mock: &MockCodec) -> MediaCodec<'static> {
+    mock.install();
+
+    let mut codec = MediaCodec::create_encoder("video/avc").unwrap();
+    codec
+        .init(&avc_format(), None, BufferFlag::Encode as u32)
+        .unwrap();
+    codec
+}
+
+/// Copies `data` into `buffer`, to be queued at `time`
+pub fn write(buffer: &mut impl InputBuffer, data: &[u8], time: u64) {
+    let (ptr, size) = buffer.buffer();
+    assert!(data.len() <= size);
+
+    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len()) };
+    buffer.set_write_size(data.len());
+    buffer.set_time(time);
+}
+
+/// Decodes a string of hex digits
+pub fn hex(value: &str) -> Vec<u8> {
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+        .collect()
+}
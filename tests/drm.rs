@@ -0,0 +1,195 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use std::sync::mpsc;
+
+use mediacodec::{
+    mock::{MockCodec, MockDrm},
+    CryptoInfo, CryptoInfoMode, DrmEvent, DrmEventType, KeyType, MediaCodec, MediaCrypto, MediaDrm,
+    MediaStatus, SubSample, CLEARKEY_UUID, WIDEVINE_UUID,
+};
+
+use common::pcm_format;
+
+/// A ClearKey `cenc` init data blob, as found in the PSSH box
+const INIT_DATA: &[u8] = b"{\"kids\":[\"AAAAAAAAAAAAAAAAAAAAAA\"]}";
+
+#[test]
+fn exchanges_clearkey_keys() {
+    assert!(MediaDrm::is_scheme_supported(&CLEARKEY_UUID, None));
+    assert!(MediaDrm::is_scheme_supported(
+        &CLEARKEY_UUID,
+        Some("video/mp4")
+    ));
+    assert!(!MediaDrm::is_scheme_supported(
+        &CLEARKEY_UUID,
+        Some("video/mp2t")
+    ));
+    assert!(!MediaDrm::is_scheme_supported(&WIDEVINE_UUID, None));
+    assert!(MediaDrm::new(&WIDEVINE_UUID).is_err());
+
+    let mock = MockDrm::new();
+    mock.install();
+
+    let mut drm = MediaDrm::new(&CLEARKEY_UUID).unwrap();
+    let session = drm.open_session().unwrap();
+    assert_eq!(mock.sessions(), [session.id()]);
+
+    let request = drm
+        .key_request(
+            &session,
+            INIT_DATA,
+            "video/mp4",
+            KeyType::Streaming,
+            &[("app", "tests")],
+        )
+        .unwrap();
+    assert!(request.ends_with(INIT_DATA));
+
+    // Streaming keys have no key set id
+    let key_set_id = drm
+        .provide_key_response(&session, b"{\"keys\":[]}")
+        .unwrap();
+    assert!(key_set_id.is_empty());
+    assert_eq!(
+        mock.key_responses(),
+        [(session.id().to_vec(), b"{\"keys\":[]}".to_vec())]
+    );
+
+    drm.close_session(&session).unwrap();
+    assert!(mock.sessions().is_empty());
+    let error = drm.close_session(&session).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::DRMSessionNotOpened);
+    assert_eq!(error.operation(), "close drm session");
+}
+
+#[test]
+fn restores_offline_keys() {
+    MockDrm::new().install();
+    let mut drm = MediaDrm::new(&CLEARKEY_UUID).unwrap();
+
+    let session = drm.open_session().unwrap();
+    drm.key_request(&session, INIT_DATA, "video/mp4", KeyType::Offline, &[])
+        .unwrap();
+    let key_set_id = drm.provide_key_response(&session, b"license").unwrap();
+    assert!(!key_set_id.is_empty());
+    drm.close_session(&session).unwrap();
+
+    let session = drm.open_session().unwrap();
+    drm.restore_keys(&session, &key_set_id).unwrap();
+    drm.remove_keys(&session).unwrap();
+    assert_eq!(
+        drm.restore_keys(&session, b"unknown").unwrap_err().status(),
+        MediaStatus::ErrorInvalidParameter
+    );
+}
+
+#[test]
+fn accesses_properties() {
+    MockDrm::new().install();
+    let mut drm = MediaDrm::new(&CLEARKEY_UUID).unwrap();
+
+    assert_eq!(drm.property_string("vendor").unwrap(), "Mock");
+    assert_eq!(drm.property_bytes("deviceUniqueId").unwrap().len(), 16);
+    assert_eq!(
+        drm.property_string("missing").unwrap_err().status(),
+        MediaStatus::ErrorInvalidParameter
+    );
+
+    drm.set_property_string("sessionSharing", "enable").unwrap();
+    assert_eq!(drm.property_string("sessionSharing").unwrap(), "enable");
+    drm.set_property_bytes("clientId", &[1, 2, 3]).unwrap();
+    assert_eq!(drm.property_bytes("clientId").unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn provisions_the_device() {
+    let mock = MockDrm::new().unprovisioned();
+    mock.install();
+
+    let mut drm = MediaDrm::new(&CLEARKEY_UUID).unwrap();
+    assert_eq!(
+        drm.open_session().unwrap_err().status(),
+        MediaStatus::DRMNotProvisioned
+    );
+
+    let request = drm.provision_request().unwrap();
+    assert!(!request.data.is_empty());
+    assert!(request.server_url.starts_with("https://"));
+
+    drm.provide_provision_response(b"certificate").unwrap();
+    assert!(mock.is_provisioned());
+    assert!(drm.open_session().is_ok());
+}
+
+#[test]
+fn delivers_events() {
+    let mock = MockDrm::new();
+    mock.install();
+
+    let mut drm = MediaDrm::new(&CLEARKEY_UUID).unwrap();
+    let session = drm.open_session().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    drm.set_event_listener(move |event| sender.send(event).unwrap())
+        .unwrap();
+
+    mock.send_event(DrmEventType::KeyRequired, Some(&session), 0, &[7]);
+    mock.send_event(DrmEventType::Other(42), None, 1, &[]);
+    assert_eq!(
+        receiver.try_recv(),
+        Ok(DrmEvent {
+            event_type: DrmEventType::KeyRequired,
+            session: Some(session),
+            extra: 0,
+            data: vec![7],
+        })
+    );
+    assert_eq!(
+        receiver
+            .try_recv()
+            .map(|event| (event.event_type, event.session)),
+        Ok((DrmEventType::Other(42), None))
+    );
+
+    // The listener goes away along with the MediaDrm
+    drop(drm);
+    mock.send_event(DrmEventType::KeyExpired, None, 0, &[]);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn decrypts_through_media_crypto() {
+    MockDrm::new().install();
+    let mut drm = MediaDrm::new(&CLEARKEY_UUID).unwrap();
+    let session = drm.open_session().unwrap();
+    drm.key_request(&session, INIT_DATA, "audio/mp4", KeyType::Streaming, &[])
+        .unwrap();
+    drm.provide_key_response(&session, b"license").unwrap();
+
+    assert!(MediaCrypto::is_scheme_supported(&CLEARKEY_UUID));
+    assert!(!MediaCrypto::requires_secure_decoder_component("audio/raw"));
+    assert!(MediaCrypto::new(&WIDEVINE_UUID, &session).is_err());
+    let crypto = MediaCrypto::new(&CLEARKEY_UUID, &session).unwrap();
+
+    let mock = MockCodec::new();
+    mock.install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec
+        .init_with_crypto(&pcm_format(), None, &crypto, 0)
+        .unwrap();
+    assert!(mock.has_crypto());
+    codec.start().unwrap();
+
+    let info = CryptoInfo::new(
+        CryptoInfoMode::AesCtr,
+        [1; 16],
+        [2; 16],
+        vec![SubSample::new(4, 4)],
+    );
+    let mut buffer = codec.dequeue_input().unwrap();
+    buffer.data_mut()[..8].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    buffer.queue_secure(&info).unwrap();
+    assert_eq!(mock.secure_inputs(), [info]);
+}
@@ -0,0 +1,152 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    mock::{MockCodec, MockMuxer},
+    BufferFlag, CodecState, Dequeued, MediaMuxer, MediaStatus, OutputFormat, Timeout,
+};
+
+use common::configured_encoder;
+
+#[cfg(feature = "api26")]
+use common::avc_format;
+#[cfg(feature = "api26")]
+use mediacodec::{keys, MediaCodec};
+
+#[test]
+fn encodes_buffers_into_muxer() {
+    let mock = MockCodec::new();
+    let mut codec = configured_encoder(&mock);
+    codec.start().unwrap();
+
+    // The mock hands the parameter sets out first, like a real encoder
+    mock.push_output(&[0, 0, 0, 1, 0x67], 0, BufferFlag::CodecConfig as u32);
+
+    for (time, value) in [(0, 1), (33_333, 2)] {
+        let mut buffer = codec.dequeue_input().unwrap();
+        buffer.data_mut()[..12].fill(value);
+        buffer.set_write_size(12);
+        buffer.set_time(time);
+    }
+    codec.signal_end_of_stream(Timeout::NonBlocking).unwrap();
+    assert_eq!(codec.state(), CodecState::EndOfStream);
+    assert_eq!(
+        codec.dequeue_input().unwrap_err().status(),
+        MediaStatus::ErrorEndOfStream
+    );
+
+    let muxer_mock = MockMuxer::new();
+    muxer_mock.install();
+    let mut muxer = MediaMuxer::new(3, OutputFormat::Mpeg4).unwrap();
+    let track = muxer.add_track(codec.output_format().unwrap()).unwrap() as usize;
+    muxer.start().unwrap();
+
+    loop {
+        let buffer = match codec.dequeue_output_timeout(Timeout::NonBlocking) {
+            Ok(Dequeued::Buffer(buffer)) => buffer,
+            Ok(_) => continue,
+            Err(error) => panic!("Unexpected error {error:?}"),
+        };
+
+        muxer.write_buffer(track, &buffer).unwrap();
+        if buffer.info().is_end_of_stream() {
+            break;
+        }
+    }
+
+    let samples = muxer_mock.samples();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].data, [1; 12]);
+    assert_eq!(samples[1].time_us, 33_333);
+}
+
+#[cfg(feature = "api26")]
+#[test]
+fn encodes_from_input_surface() {
+    let mock = MockCodec::new();
+    let mut codec = configured_encoder(&mock);
+
+    assert_eq!(
+        codec
+            .signal_end_of_stream(Timeout::NonBlocking)
+            .unwrap_err()
+            .status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    let _surface = codec.create_input_surface().unwrap();
+    codec.start().unwrap();
+    assert_eq!(
+        codec.dequeue_input().unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.signal_end_of_stream(Timeout::NonBlocking).unwrap();
+    assert_eq!(codec.state(), CodecState::EndOfStream);
+
+    let buffer = codec.dequeue_output().unwrap();
+    assert!(buffer.info().is_end_of_stream());
+}
+
+#[cfg(feature = "api26")]
+#[test]
+fn reuses_persistent_surfaces() {
+    let surface = MediaCodec::create_persistent_input_surface().unwrap();
+
+    MockCodec::new().install();
+    let mut codec = MediaCodec::create_encoder("video/avc").unwrap();
+    assert_eq!(
+        codec.set_input_surface(&surface).unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec
+        .init(&avc_format(), None, BufferFlag::Encode as u32)
+        .unwrap();
+    codec.set_input_surface(&surface).unwrap();
+    codec.start().unwrap();
+    codec.signal_end_of_stream(Timeout::NonBlocking).unwrap();
+}
+
+#[cfg(feature = "api26")]
+#[test]
+fn updates_parameters_while_running() {
+    let mock = MockCodec::new();
+    let mut codec = configured_encoder(&mock);
+    assert_eq!(
+        codec.set_video_bitrate(50_000).unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+
+    codec.start().unwrap();
+    codec.set_video_bitrate(50_000).unwrap();
+    codec.request_sync_frame().unwrap();
+    codec.suspend_input(true).unwrap();
+
+    let parameters = mock.parameters();
+    assert_eq!(parameters.len(), 3);
+    assert_eq!(parameters[0].get(keys::VIDEO_BITRATE), Some(50_000));
+    assert_eq!(parameters[1].get(keys::REQUEST_SYNC_FRAME), Some(0));
+    assert_eq!(parameters[2].get(keys::SUSPEND), Some(1));
+
+    mock.fail_next("AMediaCodec_setParameters", MediaStatus::ErrorUnsupported);
+    assert_eq!(
+        codec.request_sync_frame().unwrap_err().status(),
+        MediaStatus::ErrorUnsupported
+    );
+}
+
+#[cfg(feature = "api29")]
+#[test]
+fn drops_frames_before_timestamp() {
+    let mock = MockCodec::new();
+    let mut codec = configured_encoder(&mock);
+    codec.start().unwrap();
+
+    codec.drop_frames_before(1_000_000).unwrap();
+
+    let parameters = mock.parameters();
+    assert_eq!(parameters[0].get(keys::SUSPEND), Some(0));
+    assert_eq!(parameters[0].get(keys::SUSPEND_TIME), Some(1_000_000));
+}
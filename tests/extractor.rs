@@ -0,0 +1,503 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    mock::{MockCodec, MockMedia},
+    BufferFlag, CodecState, CryptoInfo, CryptoInfoMode, DrmInitData, Location, MediaCodec,
+    MediaExtractor, MediaStatus, Packet, SampleFlags, SeekMode, SubSample, CLEARKEY_UUID,
+    WIDEVINE_UUID,
+};
+
+use common::track;
+
+#[cfg(feature = "api28")]
+use mediacodec::MediaFormat;
+
+fn media() -> MockMedia {
+    MockMedia::new()
+        .track(&track("audio/raw"))
+        .track(&track("video/raw"))
+        .sample(0, 0, 1, &[1, 2])
+        .sample(1, 0, 1, &[3])
+        .sample(0, 10, 0, &[4, 5, 6])
+}
+
+#[test]
+fn opens_registered_media() {
+    media().register("mock://extractor/open");
+
+    let extractor = MediaExtractor::from_url("mock://extractor/open").unwrap();
+    assert_eq!(extractor.track_count(), 2);
+    assert!(extractor.has_next());
+
+    let format = extractor.track_format(1).unwrap();
+    assert_eq!(format.get_string("mime").as_deref(), Some("video/raw"));
+    assert!(extractor.track_format(2).is_none());
+}
+
+#[test]
+fn fails_on_missing_media() {
+    assert_eq!(
+        MediaExtractor::from_url("mock://extractor/missing")
+            .unwrap_err()
+            .status(),
+        MediaStatus::ErrorIO
+    );
+}
+
+#[test]
+fn rejects_missing_tracks() {
+    media().register("mock://extractor/tracks");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/tracks").unwrap();
+    let error = extractor.select_track(2).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorInvalidParameter);
+    assert_eq!(error.track_index(), Some(2));
+}
+
+#[test]
+fn only_reads_selected_tracks() {
+    media().register("mock://extractor/selected");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/selected").unwrap();
+    extractor.select_track(0).unwrap();
+
+    MockCodec::new().install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&track("audio/raw"), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let mut times = vec![];
+    while extractor.track_index() >= 0 {
+        assert_eq!(extractor.track_index(), 0);
+        times.push(extractor.sample_time());
+
+        let mut buffer = codec.dequeue_input().unwrap();
+        extractor.read_next(&mut buffer).unwrap();
+        drop(buffer);
+        codec.dequeue_output().unwrap();
+    }
+
+    assert_eq!(times, [0, 10]);
+}
+
+#[test]
+fn feeds_samples_to_the_codec() {
+    media().register("mock://extractor/feed");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/feed").unwrap();
+    extractor.select_track(0).unwrap();
+    extractor.select_track(1).unwrap();
+
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&track("audio/raw"), None, 0).unwrap();
+    codec.start().unwrap();
+
+    while extractor.track_index() >= 0 {
+        let mut buffer = codec.dequeue_input().unwrap();
+        extractor.read_next(&mut buffer).unwrap();
+    }
+
+    let queued = mock.queued_inputs();
+    let data: Vec<_> = queued.iter().map(|buffer| buffer.data.clone()).collect();
+    assert_eq!(data, [vec![1, 2], vec![3], vec![4, 5, 6]]);
+    assert_eq!(queued[0].flags, 1);
+    assert_eq!(queued[2].time_us, 10);
+}
+
+#[cfg(unix)]
+#[test]
+fn opens_media_from_files() {
+    use std::io::Write;
+
+    media().register("mock://extractor/file");
+
+    // The media sits in the middle of a bigger file
+    let path = std::env::temp_dir().join(format!("extractor-{}", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(b"headermock://extractor/filetrailer")
+        .unwrap();
+    drop(file);
+
+    let file = std::fs::File::open(&path).unwrap();
+    let extractor = MediaExtractor::from_file(&file, 6, 21).unwrap();
+    assert_eq!(extractor.track_count(), 2);
+    assert!(extractor.has_next());
+
+    drop(file);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        MediaExtractor::from_fd(-1, 0, 0).unwrap_err().status(),
+        MediaStatus::ErrorInvalidParameter
+    );
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn opens_media_from_readers() {
+    media().register("mock://extractor/reader");
+
+    let reader = std::io::Cursor::new(b"mock://extractor/reader".to_vec());
+    let mut extractor = MediaExtractor::from_reader(reader).unwrap();
+    assert_eq!(extractor.track_count(), 2);
+
+    extractor.select_track(1).unwrap();
+    assert_eq!(extractor.track_index(), 1);
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn closes_custom_data_sources() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use mediacodec::DataSource;
+
+    struct Memory {
+        data: &'static [u8],
+        fail: bool,
+        closed: Arc<AtomicBool>,
+    }
+
+    impl DataSource for Memory {
+        fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> std::io::Result<usize> {
+            if self.fail {
+                return Err(std::io::ErrorKind::PermissionDenied.into());
+            }
+
+            let data = self.data.get(offset as usize..).unwrap_or_default();
+            let count = data.len().min(buffer.len());
+            buffer[..count].copy_from_slice(&data[..count]);
+            Ok(count)
+        }
+
+        // Unknown, so the extractor reads until the end
+        fn size(&mut self) -> Option<u64> {
+            None
+        }
+
+        fn close(&mut self) {
+            self.closed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    media().register("mock://extractor/custom");
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let extractor = MediaExtractor::from_data_source(Memory {
+        data: b"mock://extractor/custom",
+        fail: false,
+        closed: closed.clone(),
+    })
+    .unwrap();
+    assert_eq!(extractor.track_count(), 2);
+    assert!(!closed.load(Ordering::SeqCst));
+
+    drop(extractor);
+    assert!(closed.load(Ordering::SeqCst));
+
+    let error = MediaExtractor::from_data_source(Memory {
+        data: b"mock://extractor/custom",
+        fail: true,
+        closed: Arc::new(AtomicBool::new(false)),
+    })
+    .unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorIO);
+}
+
+#[test]
+fn seeks_to_sync_samples() {
+    MockMedia::new()
+        .track(&track("video/raw"))
+        .sample(0, 0, 1, &[0])
+        .sample(0, 10, 0, &[1])
+        .sample(0, 20, 0, &[2])
+        .sample(0, 30, 1, &[3])
+        .sample(0, 40, 0, &[4])
+        .register("mock://extractor/seek");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/seek").unwrap();
+    extractor.select_track(0).unwrap();
+
+    let mut seek = |time_us, mode| {
+        extractor.seek_to(time_us, mode).unwrap();
+        (extractor.sample_time(), extractor.has_next())
+    };
+    assert_eq!(seek(25, SeekMode::PreviousSync), (0, true));
+    assert_eq!(seek(25, SeekMode::NextSync), (30, true));
+    assert_eq!(seek(25, SeekMode::ClosestSync), (30, true));
+    assert_eq!(seek(10, SeekMode::ClosestSync), (0, true));
+
+    // Nothing is left past the last sync sample, until seeking back
+    assert_eq!(seek(35, SeekMode::NextSync), (-1, false));
+    assert_eq!(seek(35, SeekMode::PreviousSync), (30, true));
+    assert!(extractor.advance());
+    assert_eq!(extractor.sample_time(), 40);
+    extractor.advance();
+    assert_eq!(extractor.track_index(), -1);
+}
+
+#[test]
+fn iterates_over_packets() {
+    media().register("mock://extractor/packets");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/packets").unwrap();
+    extractor.select_track(0).unwrap();
+    extractor.select_track(1).unwrap();
+
+    let packets = extractor.packets().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        packets,
+        [
+            Packet::new(0, 0, 1, vec![1, 2]),
+            Packet::new(1, 0, 1, vec![3]),
+            Packet::new(0, 10, 0, vec![4, 5, 6]),
+        ]
+    );
+    assert!(packets[0].is_sync());
+    assert!(!packets[2].is_sync() && !packets[2].is_encrypted());
+    assert!(extractor.read_packet().unwrap().is_none());
+}
+
+#[test]
+fn reports_failed_packet_reads() {
+    let media = MockMedia::new()
+        .track(&track("audio/raw"))
+        .sample(0, 0, 1, &[1, 2])
+        .sample(0, 10, 0, &[3]);
+    media.register("mock://extractor/failed-read");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/failed-read").unwrap();
+    extractor.select_track(0).unwrap();
+
+    media.fail_next("AMediaExtractor_readSampleData", MediaStatus::ErrorIO);
+    let error = extractor.read_packet().unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorIO);
+    assert_eq!(error.track_index(), Some(0));
+
+    // The failed packet is still the current one
+    let packet = extractor.read_packet().unwrap().unwrap();
+    assert_eq!(packet, Packet::new(0, 0, 1, vec![1, 2]));
+
+    media.fail_next("AMediaExtractor_readSampleData", MediaStatus::ErrorIO);
+    let mut packets = extractor.packets();
+    assert!(packets.next().unwrap().is_err());
+    assert!(packets.next().is_none());
+}
+
+#[test]
+fn reads_packets_into_a_reused_buffer() {
+    MockMedia::new()
+        .track(&track("video/raw"))
+        .sample(0, 0, 3, &[7; 100_000])
+        .sample(0, 10, 4, &[8; 10])
+        .register("mock://extractor/reuse");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/reuse").unwrap();
+    extractor.select_track(0).unwrap();
+
+    let mut data = vec![];
+    let packet = extractor.read_packet_into(&mut data).unwrap().unwrap();
+    assert_eq!(packet.data().len(), 100_000);
+    assert!(packet.is_sync() && packet.is_encrypted());
+
+    let packet = extractor.read_packet_into(&mut data).unwrap().unwrap();
+    assert_eq!(packet.data(), [8; 10]);
+    assert!(packet.is_partial());
+    assert_eq!(packet.buffer_info().flags(), 8);
+}
+
+#[test]
+fn flags_the_end_of_stream_once_exhausted() {
+    MockMedia::new()
+        .track(&track("audio/raw"))
+        .sample(0, 0, 1, &[1])
+        .sample(0, 10, 2, &[2])
+        .register("mock://extractor/eos");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/eos").unwrap();
+    extractor.select_track(0).unwrap();
+
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&track("audio/raw"), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let mut read = vec![];
+    while extractor.has_next() {
+        let mut buffer = codec.dequeue_input().unwrap();
+        read.push(extractor.read_next(&mut buffer).unwrap());
+    }
+    assert_eq!(read, [true, true, false]);
+    assert_eq!(codec.state(), CodecState::EndOfStream);
+
+    let queued = mock.queued_inputs();
+    // Encrypted samples aren't mistaken for codec config
+    assert_eq!(queued[1].flags, 0);
+    assert!(queued[2].data.is_empty());
+    assert_eq!(queued[2].flags, BufferFlag::EndOfStream as u32);
+}
+
+#[test]
+fn reports_failed_reads() {
+    let media = media();
+    media.register("mock://extractor/read-failure");
+    let mut extractor = MediaExtractor::from_url("mock://extractor/read-failure").unwrap();
+    extractor.select_track(0).unwrap();
+
+    let mock = MockCodec::new().passthrough(false);
+    mock.install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    codec.init(&track("audio/raw"), None, 0).unwrap();
+    codec.start().unwrap();
+
+    media.fail_next("AMediaExtractor_readSampleData", MediaStatus::ErrorIO);
+    let mut buffer = codec.dequeue_input().unwrap();
+    let error = extractor.read_next(&mut buffer).unwrap_err();
+    drop(buffer);
+    assert_eq!(error.status(), MediaStatus::ErrorIO);
+    assert_eq!(error.track_index(), Some(0));
+
+    // The packet is still there, and the stream didn't end
+    assert_eq!(codec.state(), CodecState::Executing);
+    assert_eq!(extractor.sample_time(), 0);
+    let mut buffer = codec.dequeue_input().unwrap();
+    assert!(extractor.read_next(&mut buffer).unwrap());
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn reads_container_metadata() {
+    let mut file = MediaFormat::new().unwrap();
+    file.set_i64("durationUs", 2_500_000);
+    file.set_string("title", "Holiday");
+    file.set_string("date", "20230401T120000.000Z");
+    file.set_string("location", "+52.5200+013.4050/");
+
+    let mut video = track("video/raw");
+    video.set_i32("rotation-degrees", 90);
+
+    MockMedia::new()
+        .file_format(&file)
+        .track(&track("audio/raw"))
+        .track(&video)
+        .register("mock://extractor/metadata");
+
+    let extractor = MediaExtractor::from_url("mock://extractor/metadata").unwrap();
+    let metadata = extractor.metadata().unwrap();
+    assert_eq!(
+        metadata.duration(),
+        Some(std::time::Duration::from_millis(2500))
+    );
+    assert_eq!(metadata.title().as_deref(), Some("Holiday"));
+    assert_eq!(metadata.artist(), None);
+    assert_eq!(
+        metadata.creation_time().as_deref(),
+        Some("20230401T120000.000Z")
+    );
+    assert_eq!(
+        metadata.location(),
+        Some(Location {
+            latitude: 52.52,
+            longitude: 13.405,
+            altitude: None,
+        })
+    );
+    // Only the video track has it
+    assert_eq!(metadata.rotation(), Some(90));
+}
+
+#[test]
+fn parses_iso_6709_locations() {
+    assert_eq!(
+        Location::parse("-33.8688+151.2093-012.5/"),
+        Some(Location {
+            latitude: -33.8688,
+            longitude: 151.2093,
+            altitude: Some(-12.5),
+        })
+    );
+    assert_eq!(Location::parse("+52.52/"), None);
+    assert_eq!(Location::parse("somewhere"), None);
+}
+
+#[test]
+fn reads_pssh_info() {
+    media().register("mock://extractor/clear");
+    let extractor = MediaExtractor::from_url("mock://extractor/clear").unwrap();
+    assert!(extractor.pssh_info().is_empty());
+
+    media()
+        .pssh(WIDEVINE_UUID, &[1, 2, 3])
+        .pssh(CLEARKEY_UUID, &[4])
+        .register("mock://extractor/protected");
+
+    let extractor = MediaExtractor::from_url("mock://extractor/protected").unwrap();
+    assert_eq!(
+        extractor.pssh_info(),
+        vec![
+            DrmInitData {
+                uuid: WIDEVINE_UUID,
+                data: vec![1, 2, 3],
+            },
+            DrmInitData {
+                uuid: CLEARKEY_UUID,
+                data: vec![4],
+            },
+        ]
+    );
+}
+
+#[test]
+fn describes_encrypted_samples() {
+    let info = CryptoInfo::new(
+        CryptoInfoMode::AesCtr,
+        [1; 16],
+        [2; 16],
+        vec![SubSample::new(5, 16), SubSample::new(0, 11)],
+    );
+
+    MockMedia::new()
+        .track(&track("video/raw"))
+        .encrypted_sample(0, 0, 1, &[0; 32], &info)
+        .sample(0, 10, 0, &[1])
+        .register("mock://extractor/encrypted");
+
+    let mut extractor = MediaExtractor::from_url("mock://extractor/encrypted").unwrap();
+    extractor.select_track(0).unwrap();
+
+    let flags = extractor.sample_flags();
+    assert!(flags.is_sync() && flags.is_encrypted());
+    assert_eq!(flags, SampleFlags::SYNC | SampleFlags::ENCRYPTED);
+    assert_eq!(extractor.sample_crypto_info(), Some(info));
+
+    extractor.advance();
+    assert_eq!(extractor.sample_flags(), SampleFlags::empty());
+    assert_eq!(extractor.sample_crypto_info(), None);
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn reports_the_cached_duration() {
+    use std::time::Duration;
+
+    media().register("mock://extractor/local");
+    let extractor = MediaExtractor::from_url("mock://extractor/local").unwrap();
+    assert_eq!(extractor.cached_duration(), None);
+
+    media()
+        .cached_duration(1_500_000)
+        .register("http://example.com/stream.mp4");
+    let extractor = MediaExtractor::from_url("http://example.com/stream.mp4").unwrap();
+    assert_eq!(
+        extractor.cached_duration(),
+        Some(Duration::from_millis(1500))
+    );
+}
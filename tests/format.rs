@@ -0,0 +1,253 @@
+#![cfg(mock_ndk)]
+
+use mediacodec::{keys, FormatMap, FormatValue, Key, MediaFormat};
+
+#[test]
+fn values_round_trip() {
+    let mut format = MediaFormat::new().unwrap();
+
+    assert!(format.set_i32("channel-count", 2));
+    assert!(format.set_i64("durationUs", 1_000_000));
+    assert!(format.set_f32("frame-rate", 29.97));
+    assert!(format.set_string("mime", "audio/raw"));
+
+    assert_eq!(format.get_i32("channel-count"), Some(2));
+    assert_eq!(format.get_i64("durationUs"), Some(1_000_000));
+    assert_eq!(format.get_f32("frame-rate"), Some(29.97));
+    assert_eq!(format.get_string("mime").as_deref(), Some("audio/raw"));
+    assert!(format.is_audio());
+    assert!(!format.is_video());
+}
+
+#[test]
+fn getters_are_type_checked() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set_i64("durationUs", 42);
+
+    assert_eq!(format.get_i32("durationUs"), None);
+    assert_eq!(format.get_string("durationUs"), None);
+    assert_eq!(format.get_i64("missing"), None);
+}
+
+#[test]
+fn to_string_matches_the_ndk() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/avc");
+    format.set_i32("width", 1920);
+    format.set_i64("durationUs", 5);
+    format.set_f32("frame-rate", 30.0);
+
+    assert_eq!(
+        format.to_string(),
+        "mime: string(video/avc), width: int32(1920), durationUs: int64(5), frame-rate: float(30.000000)"
+    );
+}
+
+#[test]
+fn typed_keys_round_trip() {
+    let mut format = MediaFormat::new().unwrap();
+
+    assert!(format.set(keys::MIME, "audio/raw"));
+    assert!(format.set(keys::CHANNEL_COUNT, 2));
+    assert!(format.set(keys::DURATION, 1_000_000));
+
+    assert_eq!(format.get(keys::MIME).as_deref(), Some("audio/raw"));
+    assert_eq!(format.get(keys::CHANNEL_COUNT), Some(2));
+    assert_eq!(format.get(keys::DURATION), Some(1_000_000));
+    assert_eq!(format.get(keys::SAMPLE_RATE), None);
+
+    // Shared with the string based accessors
+    assert_eq!(format.get_i32("channel-count"), Some(2));
+    assert_eq!(keys::CHANNEL_COUNT.name(), "channel-count");
+}
+
+#[test]
+fn custom_keys_are_type_checked() {
+    const VENDOR: Key<i64> = Key::new("vendor.frame-count");
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_i32("vendor.frame-count", 10);
+    assert_eq!(format.get(VENDOR), None);
+
+    format.set(VENDOR, 10);
+    assert_eq!(format.get(VENDOR), Some(10));
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn typed_keys_cover_newer_api_levels() {
+    let mut format = MediaFormat::new().unwrap();
+
+    format.set(keys::OPERATING_RATE, 120.0);
+    format.set(keys::PCM_ENCODING, 4);
+    format.set(keys::TEMPORAL_LAYERING, "android.generic.2");
+
+    assert_eq!(format.get(keys::OPERATING_RATE), Some(120.0));
+    assert_eq!(format.get(keys::PCM_ENCODING), Some(4));
+    assert_eq!(
+        format.get(keys::TEMPORAL_LAYERING).as_deref(),
+        Some("android.generic.2")
+    );
+}
+
+#[test]
+fn buffers_round_trip() {
+    let mut format = MediaFormat::new().unwrap();
+    let sps = [0, 0, 0, 1, 0x67, 0x42];
+
+    assert!(format.set_buffer("csd-0", &sps));
+    assert!(format.set_buffer("csd-1", &[]));
+
+    assert_eq!(format.get_buffer("csd-0"), Some(&sps[..]));
+    assert_eq!(format.get_buffer("csd-1"), Some(&[][..]));
+    assert_eq!(format.get_buffer("csd-2"), None);
+    assert_eq!(format.get(Key::<Vec<u8>>::new("csd-0")), Some(sps.to_vec()));
+    assert_eq!(format.to_string(), "csd-0: data, csd-1: data");
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn sizes_and_rects_round_trip() {
+    use mediacodec::Rect;
+
+    let mut format = MediaFormat::new().unwrap();
+    let crop = Rect {
+        left: 0,
+        top: 0,
+        right: 1919,
+        bottom: 1079,
+    };
+
+    assert!(format.set(keys::DISPLAY_CROP, crop));
+    assert!(format.set_size("buffer-size", 4096));
+
+    assert_eq!(format.get_rect("crop"), Some(crop));
+    assert_eq!(
+        format.get(keys::DISPLAY_CROP).map(|r| r.width()),
+        Some(1920)
+    );
+    assert_eq!(format.get_size("buffer-size"), Some(4096));
+    assert_eq!(format.get_rect("buffer-size"), None);
+}
+
+#[test]
+fn clones_every_entry() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/avc");
+    format.set_string("title", "Commas, and (parens), width: int32(1)");
+    format.set_i32("width", 1920);
+    format.set_i64("durationUs", 5);
+    format.set_f32("frame-rate", 29.97);
+    format.set_buffer("csd-0", &[1, 2, 3]);
+
+    let mut copy = format.clone();
+    assert_eq!(copy.to_string(), format.to_string());
+    assert_eq!(copy.get_buffer("csd-0"), Some(&[1, 2, 3][..]));
+    assert_eq!(format.try_clone().unwrap().to_string(), format.to_string());
+
+    // The copy is independent
+    copy.set_i32("width", 1280);
+    assert_eq!(format.get_i32("width"), Some(1920));
+}
+
+#[cfg(feature = "api28")]
+#[test]
+fn clones_api_28_entries() {
+    use mediacodec::Rect;
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_f64("capture-rate", 240.0);
+    format.set_size("buffer-size", 4096);
+    format.set_rect(
+        "crop",
+        Rect {
+            left: 1,
+            top: 2,
+            right: 3,
+            bottom: 4,
+        },
+    );
+
+    let copy = format.clone();
+    assert_eq!(copy.to_string(), format.to_string());
+}
+
+#[test]
+fn converts_to_and_from_format_maps() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/avc");
+    format.set_i32("width", 1920);
+    format.set_i64("durationUs", 5);
+    format.set_f32("frame-rate", 29.97);
+    format.set_buffer("csd-0", &[1, 2, 3]);
+
+    let map = FormatMap::from(&format);
+    assert_eq!(
+        map.iter().collect::<Vec<_>>(),
+        [
+            ("mime", &FormatValue::String("video/avc".to_string())),
+            ("width", &FormatValue::I32(1920)),
+            ("durationUs", &FormatValue::I64(5)),
+            ("frame-rate", &FormatValue::F32(29.97)),
+            ("csd-0", &FormatValue::Buffer(vec![1, 2, 3])),
+        ]
+    );
+
+    let rebuilt = MediaFormat::try_from(&map).unwrap();
+    assert_eq!(rebuilt.to_string(), format.to_string());
+    assert_eq!(FormatMap::from(&rebuilt), map);
+}
+
+#[test]
+fn format_maps_keep_insertion_order() {
+    let mut map: FormatMap = [("width", 1280), ("height", 720)].into_iter().collect();
+    assert_eq!(map.insert("width", 1920), Some(FormatValue::I32(1280)));
+    map.insert("mime", "video/avc");
+
+    assert_eq!(
+        map.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["width", "height", "mime"]
+    );
+    assert_eq!(map.remove("height"), Some(FormatValue::I32(720)));
+    assert!(!map.contains_key("height"));
+    assert_eq!(map.len(), 2);
+}
+
+#[cfg(not(feature = "api28"))]
+#[test]
+fn format_maps_need_api_28_for_doubles() {
+    use mediacodec::MediaStatus;
+
+    let map: FormatMap = [("capture-rate", 240.0f64)].into_iter().collect();
+
+    let error = map.to_media_format().unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorUnsupported);
+    assert_eq!(error.detail(), Some("capture-rate"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn format_maps_serialize_to_json() {
+    use mediacodec::Rect;
+
+    let mut map = FormatMap::new();
+    map.insert("mime", "audio/raw");
+    map.insert("sample-rate", 48000);
+    map.insert("csd-0", &[0x12u8, 0x10][..]);
+    map.insert(
+        "crop",
+        Rect {
+            left: 0,
+            top: 0,
+            right: 9,
+            bottom: 9,
+        },
+    );
+
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(
+        json,
+        r#"{"mime":{"String":"audio/raw"},"sample-rate":{"I32":48000},"csd-0":{"Buffer":[18,16]},"crop":{"Rect":{"left":0,"top":0,"right":9,"bottom":9}}}"#
+    );
+    assert_eq!(serde_json::from_str::<FormatMap>(&json).unwrap(), map);
+}
@@ -0,0 +1,117 @@
+#![cfg(mock_ndk)]
+
+use mediacodec::{
+    mock::MockCodec, AacProfile, AudioFormatBuilder, BitrateMode, BufferFlag, ColorFormat,
+    ColorRange, ColorStandard, ColorTransfer, MediaCodec, MediaStatus, PcmEncoding,
+    VideoFormatBuilder,
+};
+
+#[test]
+fn builds_video_encoder_formats() {
+    let format = VideoFormatBuilder::new("video/avc", 1920, 1080)
+        .bitrate(8_000_000)
+        .bitrate_mode(BitrateMode::Variable)
+        .frame_rate(30.0)
+        .i_frame_interval(1.0)
+        .color_format(ColorFormat::YUV420Flexible)
+        .profile(8, Some(0x1000))
+        .color_standard(ColorStandard::Bt709)
+        .color_range(ColorRange::Limited)
+        .color_transfer(ColorTransfer::SdrVideo)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        format.to_string(),
+        "mime: string(video/avc), width: int32(1920), height: int32(1080), bitrate: int32(8000000), \
+         bitrate-mode: int32(1), frame-rate: int32(30), i-frame-interval: int32(1), \
+         color-format: int32(2135033992), profile: int32(8), level: int32(4096), color-standard: int32(1), \
+         color-range: int32(2), color-transfer: int32(3)"
+    );
+
+    // Ready to configure an encoder with
+    MockCodec::new().install();
+    let mut codec = MediaCodec::create_encoder("video/avc").unwrap();
+    codec
+        .init(&format, None, BufferFlag::Encode as u32)
+        .unwrap();
+}
+
+#[test]
+fn keeps_fractional_rates() {
+    let format = VideoFormatBuilder::new("video/hevc", 1280, 720)
+        .frame_rate(29.97)
+        .i_frame_interval(0.5)
+        .build()
+        .unwrap();
+
+    assert_eq!(format.get_f32("frame-rate"), Some(29.97));
+    assert_eq!(format.get_f32("i-frame-interval"), Some(0.5));
+    assert_eq!(format.get_i32("bitrate"), None);
+}
+
+#[test]
+fn rejects_invalid_video_formats() {
+    let detail = |builder: VideoFormatBuilder| {
+        let error = builder.build().unwrap_err();
+        assert_eq!(error.status(), MediaStatus::ErrorInvalidParameter);
+        error.detail().unwrap().to_string()
+    };
+
+    assert_eq!(
+        detail(VideoFormatBuilder::new("audio/raw", 16, 16)),
+        "audio/raw is not a video mime type"
+    );
+    assert_eq!(
+        detail(VideoFormatBuilder::new("video/avc", 0, 16)),
+        "invalid size 0x16"
+    );
+    assert_eq!(
+        detail(VideoFormatBuilder::new("video/avc", 16, 16).bitrate_mode(BitrateMode::Constant)),
+        "the bitrate mode needs a bitrate"
+    );
+    assert_eq!(
+        detail(VideoFormatBuilder::new("video/avc", 16, 16).frame_rate(f32::NAN)),
+        "invalid frame rate NaN"
+    );
+    assert!(VideoFormatBuilder::new("video/avc", 16, 16)
+        .bitrate_mode(BitrateMode::ConstantQuality)
+        .validate()
+        .is_ok());
+}
+
+#[test]
+fn builds_audio_formats() {
+    let format = AudioFormatBuilder::new("audio/mp4a-latm", 48000, 2)
+        .channel_mask(0xC)
+        .aac_profile(AacProfile::Lc)
+        .bitrate(128_000)
+        .pcm_encoding(PcmEncoding::I16)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        format.to_string(),
+        "mime: string(audio/mp4a-latm), sample-rate: int32(48000), channel-count: int32(2), \
+         channel-mask: int32(12), aac-profile: int32(2), bitrate: int32(128000), pcm-encoding: int32(2)"
+    );
+}
+
+#[test]
+fn rejects_invalid_audio_formats() {
+    let detail =
+        |builder: AudioFormatBuilder| builder.build().unwrap_err().detail().unwrap().to_string();
+
+    assert_eq!(
+        detail(AudioFormatBuilder::new("audio/raw", 0, 2)),
+        "invalid sample rate 0"
+    );
+    assert_eq!(
+        detail(AudioFormatBuilder::new("audio/raw", 44100, 2).channel_mask(0x4)),
+        "the channel mask 0x4 doesn't have 2 channels"
+    );
+    assert_eq!(
+        detail(AudioFormatBuilder::new("audio/opus", 48000, 2).aac_profile(AacProfile::He)),
+        "audio/opus doesn't take an AAC profile"
+    );
+}
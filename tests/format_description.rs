@@ -0,0 +1,187 @@
+#![cfg(mock_ndk)]
+
+use mediacodec::{
+    DescribedValue, FormatDescription, FormatDifference, FormatValue, MediaFormat, ParseError, Rect,
+};
+
+/// Printed by a Pixel for the video track of an MP4
+const VIDEO_TRACK: &str = "mime: string(video/avc), durationUs: int64(10000000), width: int32(1920), height: int32(1080), max-input-size: int32(1048576), frame-rate: int32(30), csd-0: data, csd-1: data, track-id: int32(1), language: string(und), rotation-degrees: int32(90)";
+
+/// Printed by a hardware decoder once it knows the output format
+const DECODER_OUTPUT: &str = "color-format: int32(2135033992), mime: string(video/raw), crop: Rect(0, 0, 1919, 1079), stride: int32(1920), slice-height: int32(1088), width: int32(1920), height: int32(1080), operating-rate: float(120.000000), capture-rate: double(239.952000), buffer-size: size_t(4096), hdr-static-info: data";
+
+fn value(value: impl Into<FormatValue>) -> DescribedValue {
+    DescribedValue::Value(value.into())
+}
+
+#[test]
+fn parses_captured_track_formats() {
+    let description: FormatDescription = VIDEO_TRACK.parse().unwrap();
+
+    assert_eq!(description.len(), 11);
+    assert_eq!(description.get("mime"), Some(&value("video/avc")));
+    assert_eq!(description.get("durationUs"), Some(&value(10_000_000i64)));
+    assert_eq!(description.get("rotation-degrees"), Some(&value(90)));
+    assert_eq!(description.get("csd-0"), Some(&DescribedValue::Data));
+    assert_eq!(description.to_string(), VIDEO_TRACK);
+}
+
+#[test]
+fn parses_every_value_type() {
+    let description: FormatDescription = DECODER_OUTPUT.parse().unwrap();
+
+    assert_eq!(
+        description.get("crop"),
+        Some(&value(Rect {
+            left: 0,
+            top: 0,
+            right: 1919,
+            bottom: 1079,
+        }))
+    );
+    assert_eq!(description.get("operating-rate"), Some(&value(120.0f32)));
+    assert_eq!(description.get("capture-rate"), Some(&value(239.952f64)));
+    assert_eq!(description.get("buffer-size"), Some(&value(4096usize)));
+    assert_eq!(description.to_string(), DECODER_OUTPUT);
+
+    // Buffers have no content to keep
+    let map = description.to_format_map();
+    assert_eq!(map.len(), 10);
+    assert!(!map.contains_key("hdr-static-info"));
+}
+
+#[test]
+fn parses_strings_holding_separators() {
+    let description: FormatDescription =
+        "title: string(Live (Remastered), width: int32(1)), year: string(1999), empty: string()"
+            .parse()
+            .unwrap();
+
+    assert_eq!(
+        description.iter().collect::<Vec<_>>(),
+        [
+            ("title", &value("Live (Remastered), width: int32(1)")),
+            ("year", &value("1999")),
+            ("empty", &value("")),
+        ]
+    );
+}
+
+#[test]
+fn keeps_unknown_types() {
+    let description: FormatDescription = "vendor.x: unknown(7), vendor.y: message, width: int32(2)"
+        .parse()
+        .unwrap();
+
+    assert_eq!(
+        description.get("vendor.x"),
+        Some(&DescribedValue::Unknown("unknown(7)".to_string()))
+    );
+    assert_eq!(
+        description.get("vendor.y"),
+        Some(&DescribedValue::Unknown("message".to_string()))
+    );
+    assert_eq!(description.get("width"), Some(&value(2)));
+}
+
+#[test]
+fn parses_empty_descriptions() {
+    let description: FormatDescription = "".parse().unwrap();
+    assert!(description.is_empty());
+    assert_eq!(description.to_string(), "");
+}
+
+#[test]
+fn reports_malformed_descriptions() {
+    let error = |description: &str| description.parse::<FormatDescription>().unwrap_err();
+
+    assert_eq!(
+        error("width int32(1)"),
+        ParseError {
+            offset: 0,
+            reason: "expected `name: value`"
+        }
+    );
+    assert_eq!(error("width: int32(wide)").reason, "invalid value");
+    assert_eq!(error("width: int32(1").reason, "unterminated value");
+    assert_eq!(
+        error("crop: Rect(1, 2, 3)").reason,
+        "expected 4 rectangle coordinates"
+    );
+    assert_eq!(
+        error("width: int32(1); height: int32(2)"),
+        ParseError {
+            offset: 15,
+            reason: "expected `, ` between entries"
+        }
+    );
+}
+
+#[test]
+fn parses_long_descriptions() {
+    let description = (0..20_000)
+        .map(|i| format!("key-{i}: int32({i})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let description: FormatDescription = description.parse().unwrap();
+    assert_eq!(description.len(), 20_000);
+    assert_eq!(description.get("key-19999"), Some(&value(19_999)));
+
+    // Every `)` could end a string, which must not be retried for every entry
+    let description = format!("k: string({}), z: bogus(", "), k: string(".repeat(20_000));
+    assert_eq!(
+        description.parse::<FormatDescription>().unwrap_err().reason,
+        "unterminated value"
+    );
+}
+
+#[test]
+fn compares_descriptions() {
+    let before: FormatDescription = "mime: string(video/raw), width: int32(1920), csd-0: data"
+        .parse()
+        .unwrap();
+    let after: FormatDescription =
+        "mime: string(video/raw), width: int32(1280), stride: int32(1280)"
+            .parse()
+            .unwrap();
+
+    assert_eq!(
+        before.diff(&after),
+        [
+            FormatDifference {
+                name: "width",
+                left: Some(&value(1920)),
+                right: Some(&value(1280)),
+            },
+            FormatDifference {
+                name: "csd-0",
+                left: Some(&DescribedValue::Data),
+                right: None,
+            },
+            FormatDifference {
+                name: "stride",
+                left: None,
+                right: Some(&value(1280)),
+            },
+        ]
+    );
+    assert!(before.diff(&before).is_empty());
+}
+
+#[test]
+fn parses_media_formats() {
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "audio/raw");
+    format.set_i32("sample-rate", 48000);
+    format.set_buffer("csd-0", &[1]);
+
+    let description: FormatDescription = format.to_string().parse().unwrap();
+    assert_eq!(
+        description.iter().collect::<Vec<_>>(),
+        [
+            ("mime", &value("audio/raw")),
+            ("sample-rate", &value(48000)),
+            ("csd-0", &DescribedValue::Data),
+        ]
+    );
+}
@@ -0,0 +1,182 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    ChromaFormat, ColorRange, ColorStandard, ColorTransfer, FrameCrop, HevcConfigurationRecord,
+    HevcParameterSets, HevcPps, HevcSps, HevcVps, MediaFormat, MediaStatus, HEVC_PROFILE_MAIN_10,
+    HEVC_PROFILE_RANGE_EXTENSIONS,
+};
+
+use common::hex;
+
+/// Main 10@5.1, 3840x2160 at 59.94 fps, BT.2020 PQ
+const MAIN10_VPS: &str = "40010c01ffff02200000030090000003000003009995c090";
+const MAIN10_SPS: &str = "420101022000000300900000030000030099a001e020021c4d96579246d9bfdb780b50910090400000fa40003a9802";
+const MAIN10_PPS: &str = "4401c1b896b06120";
+/// Format range extensions, High tier@4.1, 4:2:2 10 bits, two temporal layers, 1920x1088 cropped to 1080 rows, full
+/// range BT.709, with scaling lists, PCM and long term references
+const REXT_VPS: &str =
+    "40010c03ffff24080000030090000003000003007bc00024080000030090000003000003007895cae048";
+const REXT_SPS: &str = "42010224080000030090000003000003007bc0002408000003009000000300000300784c00f0200441f12d94459244bb4d34d34d34d2aaaaaaaaaddee97a10dff80008000db808080f1cb1";
+/// With 2x2 tiles
+const REXT_PPS: &str = "4401481b8968a6492e12";
+/// The Main 10 parameter sets, in an `hvcC` box
+const MAIN10_HVCC: &str = "01022000000090000000000099f000fcfdfafa00000f03a00001001840010c01ffff02200000030090000003000003009995c090a10001002f420101022000000300900000030000030099a001e020021c4d96579246d9bfdb780b50910090400000fa40003a9802a2000100084401c1b896b06120";
+
+#[test]
+fn parses_vps() {
+    let vps = HevcVps::parse(&hex(MAIN10_VPS)).unwrap();
+    assert_eq!(vps.id, 0);
+    assert_eq!(vps.max_sub_layers, 1);
+    assert_eq!(vps.profile_tier_level.profile(), HEVC_PROFILE_MAIN_10);
+    assert_eq!(vps.profile_tier_level.level_idc, 153);
+
+    let vps = HevcVps::parse(&hex(REXT_VPS)).unwrap();
+    assert_eq!(vps.max_sub_layers, 2);
+    assert!(vps.profile_tier_level.high_tier);
+}
+
+#[test]
+fn parses_main10_hdr_sps() {
+    let sps = HevcSps::parse(&hex(MAIN10_SPS)).unwrap();
+
+    let ptl = sps.profile_tier_level;
+    assert!(ptl.is_compatible_with(HEVC_PROFILE_MAIN_10));
+    assert!(ptl.progressive_source());
+    assert!(!ptl.interlaced_source());
+    assert!(sps.is_main10());
+    assert!(!sps.is_422());
+    assert_eq!(sps.codec_profile(), Some(0x1000));
+    assert_eq!(sps.codec_level(), Some(0x10000));
+
+    assert_eq!(sps.chroma_format, ChromaFormat::Yuv420);
+    assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (10, 10));
+    assert_eq!((sps.width(), sps.height()), (3840, 2160));
+    assert_eq!(sps.conformance_window, FrameCrop::default());
+
+    let vui = sps.vui.unwrap();
+    assert_eq!(vui.sample_aspect_ratio, Some((1, 1)));
+    assert_eq!(vui.color_range(), ColorRange::Limited);
+    let color = vui.color.unwrap();
+    assert_eq!(color.color_standard(), Some(ColorStandard::Bt2020));
+    assert_eq!(color.color_transfer(), Some(ColorTransfer::St2084));
+    assert!(sps.is_hdr());
+    assert!((sps.frame_rate().unwrap() - 59.94).abs() < 0.001);
+}
+
+#[test]
+fn parses_range_extensions_sps() {
+    let sps = HevcSps::parse(&hex(REXT_SPS)).unwrap();
+
+    assert_eq!(sps.id, 1);
+    assert_eq!(sps.max_sub_layers, 2);
+    assert_eq!(
+        sps.profile_tier_level.profile(),
+        HEVC_PROFILE_RANGE_EXTENSIONS
+    );
+    assert!(sps.profile_tier_level.high_tier);
+    assert!(sps.is_422());
+    assert!(!sps.is_main10());
+    assert_eq!(sps.codec_profile(), None);
+    assert_eq!(sps.codec_level(), Some(0x2000));
+
+    assert_eq!((sps.bit_depth_luma, sps.bit_depth_chroma), (10, 10));
+    assert_eq!((sps.coded_width, sps.coded_height), (1920, 1088));
+    assert_eq!(
+        sps.conformance_window,
+        FrameCrop {
+            bottom: 8,
+            ..Default::default()
+        }
+    );
+    assert_eq!((sps.width(), sps.height()), (1920, 1080));
+    assert_eq!(sps.display_rect().bottom, 1079);
+    assert_eq!(
+        sps.default_display_window,
+        Some(FrameCrop {
+            top: 4,
+            ..Default::default()
+        })
+    );
+
+    let vui = sps.vui.unwrap();
+    assert_eq!(vui.color_range(), ColorRange::Full);
+    assert_eq!(
+        vui.color.unwrap().color_standard(),
+        Some(ColorStandard::Bt709)
+    );
+    assert!(!sps.is_hdr());
+    assert_eq!(sps.frame_rate(), None);
+}
+
+#[test]
+fn parses_pps() {
+    let pps = HevcPps::parse(&hex(MAIN10_PPS)).unwrap();
+    assert_eq!((pps.id, pps.sps_id), (0, 0));
+    assert!(pps.sign_data_hiding_enabled);
+    assert_eq!(pps.num_ref_idx_l0_default_active, 3);
+    assert_eq!(pps.init_qp, 22);
+    assert!(pps.cu_qp_delta_enabled);
+    assert_eq!(pps.tiles, None);
+    assert!(pps.entropy_coding_sync_enabled);
+
+    let pps = HevcPps::parse(&hex(REXT_PPS)).unwrap();
+    assert_eq!((pps.id, pps.sps_id), (1, 1));
+    assert_eq!((pps.cb_qp_offset, pps.cr_qp_offset), (-2, 3));
+    assert!(pps.weighted_pred);
+    assert_eq!(pps.tiles, Some((2, 2)));
+    assert!(!pps.entropy_coding_sync_enabled);
+}
+
+#[test]
+fn parses_hvcc_records() {
+    let data = hex(MAIN10_HVCC);
+    let record = HevcConfigurationRecord::parse(&data).unwrap();
+
+    assert_eq!(record.profile_tier_level.profile(), HEVC_PROFILE_MAIN_10);
+    assert_eq!(record.profile_tier_level.level_idc, 153);
+    assert_eq!(record.chroma_format, ChromaFormat::Yuv420);
+    assert_eq!((record.bit_depth_luma, record.bit_depth_chroma), (10, 10));
+    assert_eq!(record.nal_length_size, 4);
+    assert_eq!(record.nal_units.len(), 3);
+
+    let sets = HevcParameterSets::parse(&data).unwrap();
+    assert_eq!(sets.vps, [HevcVps::parse(&hex(MAIN10_VPS)).unwrap()]);
+    assert_eq!(sets.sps, [HevcSps::parse(&hex(MAIN10_SPS)).unwrap()]);
+    assert_eq!(sets.pps, [HevcPps::parse(&hex(MAIN10_PPS)).unwrap()]);
+
+    let error = HevcConfigurationRecord::parse(&data[..40]).unwrap_err();
+    assert_eq!(error.detail(), Some("unexpected end of data"));
+}
+
+#[test]
+fn parses_track_formats() {
+    let csd = [REXT_VPS, REXT_SPS, REXT_PPS]
+        .iter()
+        .flat_map(|nal| [vec![0, 0, 0, 1], hex(nal)].concat())
+        .collect::<Vec<_>>();
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/hevc");
+    format.set_buffer("csd-0", &csd);
+
+    let sets = HevcParameterSets::from_format(&format).unwrap();
+    assert_eq!((sets.vps.len(), sets.sps.len(), sets.pps.len()), (1, 1, 1));
+    assert!(sets.first_sps().unwrap().is_422());
+}
+
+#[test]
+fn rejects_malformed_parameter_sets() {
+    let error = HevcSps::parse(&hex(&MAIN10_SPS[..40])).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorMalformed);
+    assert_eq!(error.detail(), Some("unexpected end of data"));
+
+    let error = HevcSps::parse(&hex(MAIN10_VPS)).unwrap_err();
+    assert_eq!(error.detail(), Some("expected a NAL unit of type 33"));
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_buffer("csd-0", &hex(&format!("00000001{MAIN10_PPS}")));
+    let error = HevcParameterSets::from_format(&format).unwrap_err();
+    assert_eq!(error.detail(), Some("missing SPS"));
+}
@@ -0,0 +1,107 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    mock::{MockMedia, MockMuxer, MockMuxerState, MockSample},
+    BufferInfo, MediaExtractor, MediaMuxer, MediaStatus, OutputFormat,
+};
+
+use common::track;
+
+#[test]
+fn writes_samples_once_started() {
+    let mock = MockMuxer::new();
+    mock.install();
+
+    let mut muxer = MediaMuxer::new(3, OutputFormat::Mpeg4).unwrap();
+    muxer.set_location(52.5, 13.4).set_orientation_hint(90);
+
+    let index = muxer.add_track(track("audio/mp4a-latm")).unwrap();
+    assert_eq!(index, 0);
+    assert_eq!(
+        muxer.write_sample_data(0, &[], &BufferInfo::default()),
+        Err(MediaStatus::ErrorInvalidOperation)
+    );
+
+    muxer.start().unwrap();
+    assert_eq!(mock.state(), MockMuxerState::Started);
+    assert_eq!(mock.location(), Some((52.5, 13.4)));
+    assert_eq!(mock.orientation_hint(), 90);
+
+    muxer
+        .write_sample_data(0, &[], &BufferInfo::default())
+        .unwrap();
+    assert_eq!(mock.samples().len(), 1);
+
+    muxer.stop().unwrap();
+    assert_eq!(mock.state(), MockMuxerState::Released);
+}
+
+#[test]
+fn refuses_to_start_without_tracks() {
+    MockMuxer::new().install();
+
+    let mut muxer = MediaMuxer::new(3, OutputFormat::Webm).unwrap();
+    assert_eq!(muxer.start(), Err(MediaStatus::ErrorInvalidOperation));
+}
+
+#[test]
+fn reports_injected_errors() {
+    let mock = MockMuxer::new();
+    mock.install();
+    mock.fail_next("AMediaMuxer_addTrack", MediaStatus::ErrorUnsupported);
+
+    let mut muxer = MediaMuxer::new(3, OutputFormat::ThreeGpp).unwrap();
+    assert_eq!(
+        muxer.add_track(track("audio/mp4a-latm")),
+        Err(MediaStatus::ErrorUnsupported)
+    );
+    assert_eq!(muxer.track_count(), 0);
+}
+
+#[test]
+fn fails_on_invalid_file_descriptors() {
+    assert!(MediaMuxer::new(-1, OutputFormat::Mpeg4).is_err());
+}
+
+#[test]
+fn remuxes_extracted_packets() {
+    MockMedia::new()
+        .track(&track("audio/mp4a-latm"))
+        .sample(0, 0, 1, &[1, 2])
+        .sample(0, 10, 0, &[3])
+        .register("mock://muxer/remux");
+
+    let mut extractor = MediaExtractor::from_url("mock://muxer/remux").unwrap();
+    extractor.select_track(0).unwrap();
+
+    let mock = MockMuxer::new();
+    mock.install();
+    let mut muxer = MediaMuxer::new(3, OutputFormat::Mpeg4).unwrap();
+    muxer.add_track(extractor.track_format(0).unwrap()).unwrap();
+    muxer.start().unwrap();
+
+    for packet in extractor.packets() {
+        let packet = packet.unwrap();
+        muxer.write_packet(packet.track_index(), &packet).unwrap();
+    }
+
+    assert_eq!(
+        mock.samples(),
+        [
+            MockSample {
+                track: 0,
+                time_us: 0,
+                flags: 1,
+                data: vec![1, 2],
+            },
+            MockSample {
+                track: 0,
+                time_us: 10,
+                flags: 0,
+                data: vec![3],
+            },
+        ]
+    );
+}
@@ -0,0 +1,157 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    keys, mock::MockCodec, Codec, CodecState, Frame, InputBuffer, MediaCodec, MediaFormat,
+    MediaStatus, OutputBuffer, PassthroughCodec, SampleFormat, VideoFrame, VideoPlanes,
+};
+
+use common::{pcm_format, write};
+
+/// Pushes one buffer of PCM samples through any codec, and returns what came out
+fn roundtrip<C: Codec>(codec: &mut C, samples: &[i16]) -> (Vec<i16>, i64) {
+    codec.configure(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_ne_bytes()).collect();
+    {
+        let mut buffer = codec.dequeue_input().unwrap();
+        write(&mut buffer, &bytes, 1000);
+    }
+
+    let buffer = codec.dequeue_output().unwrap();
+    let time = buffer.info().presentation_time_us();
+    let decoded = match buffer.frame() {
+        Some(Frame::Audio(frame)) => match frame.format() {
+            SampleFormat::S16(data) => data.to_vec(),
+            format => panic!("Unexpected sample format {format:?}"),
+        },
+        frame => panic!("Unexpected frame {frame:?}"),
+    };
+    drop(buffer);
+
+    codec.stop().unwrap();
+    (decoded, time)
+}
+
+#[test]
+fn backends_agree_on_pcm() {
+    let samples = [1, -1, 2, -2, 3, -3];
+
+    let mut reference = PassthroughCodec::new("audio/raw").unwrap();
+    let expected = roundtrip(&mut reference, &samples);
+    assert_eq!(expected, (samples.to_vec(), 1000));
+
+    MockCodec::new().install();
+    let mut codec = MediaCodec::create_decoder("audio/raw").unwrap();
+    assert_eq!(roundtrip(&mut codec, &samples), expected);
+}
+
+#[test]
+fn passes_raw_video_through() {
+    let mut codec = PassthroughCodec::new("video/raw").unwrap();
+
+    let mut format = MediaFormat::new().unwrap();
+    format.set_string("mime", "video/raw");
+    format.set_i32("width", 4);
+    format.set_i32("height", 2);
+    codec.configure(&format, None, 0).unwrap();
+    codec.start().unwrap();
+
+    let output = codec.output_format().unwrap();
+    assert_eq!(output.get_i32("stride"), Some(4));
+    assert_eq!(output.get_i32("slice-height"), Some(2));
+
+    let frame: Vec<u8> = (0..12).collect();
+    {
+        let mut buffer = codec.dequeue_input().unwrap();
+        assert_eq!(buffer.size(), 12);
+        buffer.data_mut().copy_from_slice(&frame);
+        buffer.set_write_size(frame.len());
+    }
+
+    let buffer = codec.dequeue_output().unwrap();
+    match buffer.frame() {
+        Some(Frame::Video(VideoFrame::RawFrame(raw))) => {
+            assert_eq!(raw.buffer, frame);
+
+            // I420: a 4x2 Y plane followed by 2x1 U and V planes
+            match raw.planes() {
+                Some(VideoPlanes::Planar { y, u, v }) => {
+                    assert_eq!(y.data, &frame[..8]);
+                    assert_eq!((u.data, u.stride), (&frame[8..10], 2));
+                    assert_eq!(v.data, &frame[10..12]);
+                }
+                planes => panic!("Unexpected planes {planes:?}"),
+            }
+        }
+        frame => panic!("Unexpected frame {frame:?}"),
+    }
+}
+
+#[test]
+fn enforces_codec_state() {
+    assert!(PassthroughCodec::new("video/avc").is_err());
+
+    let mut codec = PassthroughCodec::new("audio/raw").unwrap();
+    assert_eq!(
+        codec.start().unwrap_err().status(),
+        MediaStatus::ErrorInvalidOperation
+    );
+    assert!(codec.dequeue_input().is_err());
+
+    let mut incomplete = MediaFormat::new().unwrap();
+    incomplete.set_string("mime", "audio/raw");
+    assert_eq!(
+        codec.configure(&incomplete, None, 0).unwrap_err().status(),
+        MediaStatus::ErrorInvalidParameter
+    );
+
+    codec.configure(&pcm_format(), None, 0).unwrap();
+    let error = codec.configure(&pcm_format(), None, 0).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorInvalidOperation);
+    assert_eq!(error.operation(), "configure");
+    assert_eq!(error.codec_state(), Some(CodecState::Configured));
+    codec.start().unwrap();
+
+    // Nothing was queued yet
+    assert_eq!(
+        codec.dequeue_output().unwrap_err().status(),
+        MediaStatus::ErrorWouldBlock
+    );
+}
+
+#[test]
+fn rejects_invalid_video_sizes() {
+    for (width, height) in [(0, 2), (4, -2), (1 << 20, 1 << 20)] {
+        let mut format = MediaFormat::new().unwrap();
+        format.set(keys::MIME, "video/raw");
+        format.set(keys::WIDTH, width);
+        format.set(keys::HEIGHT, height);
+
+        let mut codec = PassthroughCodec::new("video/raw").unwrap();
+        assert_eq!(
+            codec.configure(&format, None, 0).unwrap_err().status(),
+            MediaStatus::ErrorInvalidParameter
+        );
+        assert_eq!(codec.state(), CodecState::Uninitialized);
+    }
+}
+
+#[test]
+fn flush_recycles_pending_buffers() {
+    let mut codec = PassthroughCodec::new("audio/raw").unwrap();
+    codec.configure(&pcm_format(), None, 0).unwrap();
+    codec.start().unwrap();
+
+    for _ in 0..4 {
+        drop(codec.dequeue_input().unwrap());
+    }
+    // Every input buffer is now pending
+    assert!(codec.dequeue_input().is_err());
+
+    codec.flush().unwrap();
+    assert!(codec.dequeue_output().is_err());
+    assert!(codec.dequeue_input().is_ok());
+}
@@ -0,0 +1,77 @@
+#![cfg(mock_ndk)]
+
+mod common;
+
+use mediacodec::{
+    mock::{MockCodec, MockMedia},
+    MediaCodec, MediaExtractor, MediaStatus, SeekDecoder,
+};
+
+use common::track;
+
+fn decoder(mime: &str) -> MediaCodec<'static> {
+    MockCodec::new().install();
+
+    let mut codec = MediaCodec::create_decoder(mime).unwrap();
+    codec.init(&track(mime), None, 0).unwrap();
+    codec.start().unwrap();
+    codec
+}
+
+/// A video track with sync samples every 30us, interleaved with an audio track
+fn seek_decoder(location: &str) -> SeekDecoder<'static> {
+    MockMedia::new()
+        .track(&track("video/raw"))
+        .track(&track("audio/raw"))
+        .sample(0, 0, 1, &[0])
+        .sample(1, 0, 1, &[100])
+        .sample(0, 10, 0, &[10])
+        .sample(0, 20, 0, &[20])
+        .sample(1, 20, 1, &[120])
+        .sample(0, 30, 1, &[30])
+        .sample(0, 40, 0, &[40])
+        .register(location);
+
+    let extractor = MediaExtractor::from_url(location).unwrap();
+    let mut seek = SeekDecoder::new(extractor);
+    seek.add_decoder(0, decoder("video/raw")).unwrap();
+    seek.add_decoder(1, decoder("audio/raw")).unwrap();
+    seek
+}
+
+#[test]
+fn decodes_forward_to_the_exact_frame() {
+    let mut seek = seek_decoder("mock://seek/exact");
+
+    let frame = seek.seek(0, 15).unwrap();
+    assert_eq!(frame.info().presentation_time_us(), 20);
+    assert_eq!(frame.data(), Some(&[20][..]));
+    drop(frame);
+
+    // Landing right on a sync sample
+    let frame = seek.seek(0, 30).unwrap();
+    assert_eq!(frame.info().presentation_time_us(), 30);
+    drop(frame);
+
+    // Seeking backwards
+    let frame = seek.seek(0, 5).unwrap();
+    assert_eq!(frame.info().presentation_time_us(), 10);
+}
+
+#[test]
+fn seeks_again_after_the_end() {
+    let mut seek = seek_decoder("mock://seek/end");
+
+    let error = seek.seek(0, 100).unwrap_err();
+    assert_eq!(error.status(), MediaStatus::ErrorEndOfStream);
+    assert_eq!(error.track_index(), Some(0));
+
+    let frame = seek.seek(0, 40).unwrap();
+    assert_eq!(frame.info().presentation_time_us(), 40);
+    drop(frame);
+
+    assert_eq!(
+        seek.seek(2, 0).unwrap_err().status(),
+        MediaStatus::ErrorInvalidParameter
+    );
+}